@@ -0,0 +1,216 @@
+use super::TimeBuckets;
+use crate::grouper::LogGrouper;
+use crate::models::{LogEvent, LogGroup};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// A single HTTP request from a CLF/Combined access log
+#[derive(Debug, Clone)]
+pub struct AccessRecord {
+    pub timestamp: Option<DateTime<Utc>>,
+    pub client: String,
+    pub method: Option<String>,
+    pub path: Option<String>,
+    pub status: u16,
+    pub bytes: u64,
+    pub referer: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl AccessRecord {
+    /// Build a record from the access-log fields set by the parser
+    pub fn from_event(event: &LogEvent) -> Option<Self> {
+        let field = |key: &str| event.fields.get(key).cloned();
+
+        Some(Self {
+            timestamp: event.timestamp,
+            client: field("client")?,
+            method: field("method"),
+            path: field("path"),
+            status: field("status")?.parse().ok()?,
+            bytes: field("bytes").and_then(|b| b.parse().ok()).unwrap_or(0),
+            referer: field("referer"),
+            user_agent: field("user_agent"),
+        })
+    }
+
+    /// Status class label such as "2xx"
+    pub fn status_class(&self) -> String {
+        format!("{}xx", self.status / 100)
+    }
+}
+
+/// Request counts per status class within one time bucket
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusBucket {
+    pub start: DateTime<Utc>,
+    pub classes: BTreeMap<String, usize>,
+}
+
+/// Traffic and server errors of one templated path
+#[derive(Debug, Clone, Serialize)]
+pub struct PathStats {
+    pub path: String,
+    pub requests: usize,
+    pub server_errors: usize,
+    pub error_rate: f64,
+    pub bytes: u64,
+}
+
+/// Traffic of one client address
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientStats {
+    pub client: String,
+    pub requests: usize,
+    pub bytes: u64,
+}
+
+/// HTTP access-log analytics
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessReport {
+    pub total_requests: usize,
+    pub total_bytes: u64,
+    pub status_classes: BTreeMap<String, usize>,
+    pub timeline: Vec<StatusBucket>,
+    /// Paths ranked by 5xx rate
+    pub top_error_paths: Vec<PathStats>,
+    /// Paths ranked by bytes served
+    pub top_paths_by_bytes: Vec<PathStats>,
+    pub top_clients: Vec<ClientStats>,
+    pub top_user_agents: Vec<(String, usize)>,
+    pub top_referers: Vec<(String, usize)>,
+}
+
+/// Aggregates access-log records into HTTP traffic reports
+pub struct AccessAnalyzer {
+    grouper: LogGrouper,
+}
+
+impl AccessAnalyzer {
+    pub fn new() -> Self {
+        Self {
+            grouper: LogGrouper::new(),
+        }
+    }
+
+    /// Analyze all access-log events, or return None if there are none
+    pub fn analyze(&self, groups: &[LogGroup], top_n: usize, buckets: Option<&TimeBuckets>) -> Option<AccessReport> {
+        let records: Vec<AccessRecord> = groups
+            .iter()
+            .flat_map(|g| &g.events)
+            .filter_map(AccessRecord::from_event)
+            .collect();
+
+        if records.is_empty() {
+            return None;
+        }
+
+        let mut status_classes: BTreeMap<String, usize> = BTreeMap::new();
+        let mut paths: HashMap<String, PathStats> = HashMap::new();
+        let mut clients: HashMap<&str, ClientStats> = HashMap::new();
+        let mut agents: HashMap<&str, usize> = HashMap::new();
+        let mut referers: HashMap<&str, usize> = HashMap::new();
+        let mut timeline: Vec<BTreeMap<String, usize>> =
+            vec![BTreeMap::new(); buckets.map(|b| b.count).unwrap_or(0)];
+
+        for record in &records {
+            let class = record.status_class();
+            *status_classes.entry(class.clone()).or_insert(0) += 1;
+
+            if let (Some(b), Some(ts)) = (buckets, record.timestamp) {
+                *timeline[b.index(ts)].entry(class).or_insert(0) += 1;
+            }
+
+            if let Some(ref path) = record.path {
+                let template = match record.method {
+                    Some(ref method) => format!("{} {}", method, self.grouper.template_path(path)),
+                    None => self.grouper.template_path(path),
+                };
+                let stats = paths.entry(template.clone()).or_insert(PathStats {
+                    path: template,
+                    requests: 0,
+                    server_errors: 0,
+                    error_rate: 0.0,
+                    bytes: 0,
+                });
+                stats.requests += 1;
+                stats.bytes += record.bytes;
+                if record.status >= 500 {
+                    stats.server_errors += 1;
+                }
+            }
+
+            let client = clients.entry(&record.client).or_insert(ClientStats {
+                client: record.client.clone(),
+                requests: 0,
+                bytes: 0,
+            });
+            client.requests += 1;
+            client.bytes += record.bytes;
+
+            if let Some(ref agent) = record.user_agent {
+                *agents.entry(agent).or_insert(0) += 1;
+            }
+            if let Some(ref referer) = record.referer {
+                *referers.entry(referer).or_insert(0) += 1;
+            }
+        }
+
+        let mut paths: Vec<PathStats> = paths
+            .into_values()
+            .map(|mut p| {
+                p.error_rate = p.server_errors as f64 / p.requests as f64;
+                p
+            })
+            .collect();
+
+        let mut top_error_paths: Vec<PathStats> = paths.iter().filter(|p| p.server_errors > 0).cloned().collect();
+        top_error_paths.sort_by(|a, b| {
+            b.error_rate
+                .total_cmp(&a.error_rate)
+                .then(b.server_errors.cmp(&a.server_errors))
+        });
+        top_error_paths.truncate(top_n);
+
+        paths.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(b.requests.cmp(&a.requests)));
+        paths.truncate(top_n);
+
+        let mut top_clients: Vec<ClientStats> = clients.into_values().collect();
+        top_clients.sort_by(|a, b| b.requests.cmp(&a.requests).then(b.bytes.cmp(&a.bytes)));
+        top_clients.truncate(top_n);
+
+        let timeline = buckets
+            .map(|b| {
+                timeline
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, classes)| !classes.is_empty())
+                    .map(|(i, classes)| StatusBucket {
+                        start: b.bucket_start(i),
+                        classes,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(AccessReport {
+            total_requests: records.len(),
+            total_bytes: records.iter().map(|r| r.bytes).sum(),
+            status_classes,
+            timeline,
+            top_error_paths,
+            top_paths_by_bytes: paths,
+            top_clients,
+            top_user_agents: Self::top_counts(agents, top_n),
+            top_referers: Self::top_counts(referers, top_n),
+        })
+    }
+
+    fn top_counts(counts: HashMap<&str, usize>, top_n: usize) -> Vec<(String, usize)> {
+        let mut result: Vec<(String, usize)> = counts.into_iter().map(|(k, n)| (k.to_string(), n)).collect();
+        result.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        result.truncate(top_n);
+        result
+    }
+}
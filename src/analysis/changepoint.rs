@@ -0,0 +1,276 @@
+use super::TimeBuckets;
+use crate::models::LogGroup;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Shortest segment (in buckets) between two change points
+const MIN_SEGMENT: usize = 3;
+/// Penalty multiplier on ln(n) for adding a change point
+const PENALTY_FACTOR: f64 = 3.0;
+/// Patterns listed as contributors to each shift
+const MAX_CONTRIBUTORS: usize = 3;
+
+/// A pattern whose rate moved the most across a change point
+#[derive(Debug, Clone, Serialize)]
+pub struct Contributor {
+    /// Rank of the pattern (1-based, as displayed)
+    pub pattern_id: usize,
+    pub pattern: String,
+    /// Mean events per bucket in the segments before and after
+    pub before: f64,
+    pub after: f64,
+}
+
+/// A moment where a global series shifted its mean
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangePoint {
+    /// Series that shifted: volume, error_ratio or active_patterns
+    pub metric: String,
+    pub at: DateTime<Utc>,
+    /// Segment means of the series before and after the change
+    pub before: f64,
+    pub after: f64,
+    pub contributors: Vec<Contributor>,
+}
+
+/// Change points detected over all global series
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangePointReport {
+    pub bucket_secs: i64,
+    pub buckets: usize,
+    pub change_points: Vec<ChangePoint>,
+}
+
+/// Finds shifts in overall log volume, error ratio and pattern diversity
+/// using PELT with a Gaussian mean-change cost
+pub struct ChangePointDetector {
+    buckets: TimeBuckets,
+}
+
+impl ChangePointDetector {
+    pub fn new(buckets: TimeBuckets) -> Self {
+        Self { buckets }
+    }
+
+    pub fn detect(&self, groups: &[LogGroup]) -> ChangePointReport {
+        let n = self.buckets.count;
+
+        // Per-pattern counts per bucket, all events and errors only
+        let mut counts = vec![vec![0.0; n]; groups.len()];
+        let mut error_counts = vec![vec![0.0; n]; groups.len()];
+        for (i, group) in groups.iter().enumerate() {
+            for event in &group.events {
+                if let Some(ts) = event.timestamp {
+                    let b = self.buckets.index(ts);
+                    counts[i][b] += 1.0;
+                    if event.level.as_ref().is_some_and(|l| l.is_error()) {
+                        error_counts[i][b] += 1.0;
+                    }
+                }
+            }
+        }
+
+        let volume: Vec<f64> = (0..n).map(|b| counts.iter().map(|c| c[b]).sum()).collect();
+        let errors: Vec<f64> = (0..n).map(|b| error_counts.iter().map(|c| c[b]).sum()).collect();
+        let error_ratio: Vec<f64> = volume
+            .iter()
+            .zip(&errors)
+            .map(|(v, e)| if *v > 0.0 { e / v } else { 0.0 })
+            .collect();
+        let active: Vec<f64> = (0..n)
+            .map(|b| counts.iter().filter(|c| c[b] > 0.0).count() as f64)
+            .collect();
+
+        let mut change_points = Vec::new();
+        for (metric, series, per_pattern) in [
+            ("volume", &volume, &counts),
+            ("error_ratio", &error_ratio, &error_counts),
+            ("active_patterns", &active, &counts),
+        ] {
+            let breaks = pelt(series);
+            let mut bounds = vec![0];
+            bounds.extend(&breaks);
+            bounds.push(n);
+
+            for (k, &cp) in breaks.iter().enumerate() {
+                let (prev, next) = (bounds[k], bounds[k + 2]);
+                change_points.push(ChangePoint {
+                    metric: metric.to_string(),
+                    at: self.buckets.bucket_start(cp),
+                    before: mean(&series[prev..cp]),
+                    after: mean(&series[cp..next]),
+                    contributors: Self::contributors(groups, per_pattern, prev, cp, next),
+                });
+            }
+        }
+
+        change_points.sort_by_key(|c| c.at);
+
+        ChangePointReport {
+            bucket_secs: self.buckets.width.num_seconds(),
+            buckets: n,
+            change_points,
+        }
+    }
+
+    /// Patterns with the largest rate change between [prev, cp) and [cp, next)
+    fn contributors(groups: &[LogGroup], counts: &[Vec<f64>], prev: usize, cp: usize, next: usize) -> Vec<Contributor> {
+        let mut result: Vec<Contributor> = counts
+            .iter()
+            .enumerate()
+            .map(|(i, c)| Contributor {
+                pattern_id: i + 1,
+                pattern: groups[i].pattern.clone(),
+                before: mean(&c[prev..cp]),
+                after: mean(&c[cp..next]),
+            })
+            .filter(|c| c.before != c.after)
+            .collect();
+
+        result.sort_by(|a, b| (b.after - b.before).abs().total_cmp(&(a.after - a.before).abs()));
+        result.truncate(MAX_CONTRIBUTORS);
+        result
+    }
+}
+
+/// Pruned Exact Linear Time segmentation for changes in mean.
+/// Returns the indices where new segments start.
+fn pelt(series: &[f64]) -> Vec<usize> {
+    let n = series.len();
+    if n < 2 * MIN_SEGMENT {
+        return Vec::new();
+    }
+
+    let sigma2 = noise_variance(series);
+    if sigma2 <= 0.0 {
+        return Vec::new();
+    }
+
+    let mut s1 = vec![0.0; n + 1];
+    let mut s2 = vec![0.0; n + 1];
+    for (i, x) in series.iter().enumerate() {
+        s1[i + 1] = s1[i] + x;
+        s2[i + 1] = s2[i] + x * x;
+    }
+
+    // Squared error of series[s..t] around its mean, scaled by the noise
+    let cost = |s: usize, t: usize| {
+        let len = (t - s) as f64;
+        let sum = s1[t] - s1[s];
+        ((s2[t] - s2[s]) - sum * sum / len) / sigma2
+    };
+
+    let penalty = PENALTY_FACTOR * (n as f64).ln();
+    let mut best = vec![f64::INFINITY; n + 1];
+    let mut last = vec![0; n + 1];
+    best[0] = -penalty;
+    let mut candidates: Vec<usize> = vec![0];
+    let mut seen: HashSet<usize> = HashSet::from([0]);
+
+    for t in MIN_SEGMENT..=n {
+        let eligible = candidates.iter().copied().filter(|s| t - s >= MIN_SEGMENT);
+        let Some((value, s)) = eligible
+            .map(|s| (best[s] + cost(s, t) + penalty, s))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+        else {
+            continue;
+        };
+        best[t] = value;
+        last[t] = s;
+
+        candidates.retain(|&s| t - s < MIN_SEGMENT || best[s] + cost(s, t) <= best[t]);
+        if t + MIN_SEGMENT <= n && seen.insert(t) {
+            candidates.push(t);
+        }
+    }
+
+    let mut breaks = Vec::new();
+    let mut t = n;
+    while t > 0 {
+        let s = last[t];
+        if s > 0 {
+            breaks.push(s);
+        }
+        t = s;
+    }
+    breaks.reverse();
+    breaks
+}
+
+/// Robust noise variance from the median absolute first difference,
+/// falling back to the sample variance
+fn noise_variance(series: &[f64]) -> f64 {
+    let mut diffs: Vec<f64> = series.windows(2).map(|w| (w[1] - w[0]).abs()).collect();
+    diffs.sort_by(|a, b| a.total_cmp(b));
+    let mad = diffs[diffs.len() / 2];
+    let sigma = 1.4826 * mad / std::f64::consts::SQRT_2;
+    if sigma > 0.0 {
+        return sigma * sigma;
+    }
+
+    let m = mean(series);
+    series.iter().map(|x| (x - m).powi(2)).sum::<f64>() / series.len() as f64
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::testing::{group, start};
+    use crate::models::LogLevel;
+    use chrono::Duration;
+
+    #[test]
+    fn pelt_finds_a_shift_in_mean() {
+        let noise = [0.3, -0.2, 0.1, -0.4, 0.2, 0.0, -0.1, 0.4, -0.3, 0.1];
+        let series: Vec<f64> = (0..40).map(|i| if i < 20 { 2.0 } else { 10.0 } + noise[i % noise.len()]).collect();
+
+        assert_eq!(pelt(&series), vec![20]);
+    }
+
+    #[test]
+    fn pelt_ignores_noise_around_a_constant_mean() {
+        let noise = [0.3, -0.2, 0.1, -0.4, 0.2, 0.0, -0.1, 0.4, -0.3, 0.1];
+        let series: Vec<f64> = (0..40).map(|i| 5.0 + noise[i % noise.len()]).collect();
+
+        assert!(pelt(&series).is_empty());
+    }
+
+    #[test]
+    fn attributes_a_burst_of_errors() {
+        // Requests every 6s for an hour, timeouts every 2s in the second half
+        let requests: Vec<i64> = (0..3600).step_by(6).collect();
+        let timeouts: Vec<i64> = (1800..3600).step_by(2).collect();
+        let groups = vec![
+            group("request ok", LogLevel::Info, &requests),
+            group("db timeout", LogLevel::Error, &timeouts),
+        ];
+
+        let buckets = TimeBuckets::covering(&groups, Some(Duration::seconds(60))).unwrap();
+        let report = ChangePointDetector::new(buckets).detect(&groups);
+
+        assert_eq!((report.bucket_secs, report.buckets), (60, 60));
+        let shifts: Vec<(&str, i64)> = report
+            .change_points
+            .iter()
+            .map(|c| (c.metric.as_str(), (c.at - start()).num_seconds()))
+            .collect();
+        assert_eq!(shifts.len(), 3);
+        for metric in ["volume", "error_ratio", "active_patterns"] {
+            assert!(shifts.contains(&(metric, 1800)), "{:?}", shifts);
+        }
+
+        let volume = report.change_points.iter().find(|c| c.metric == "volume").unwrap();
+        assert_eq!((volume.before, volume.after), (10.0, 40.0));
+        assert_eq!(volume.contributors[0].pattern, "db timeout");
+        assert_eq!((volume.contributors[0].before, volume.contributors[0].after), (0.0, 30.0));
+    }
+}
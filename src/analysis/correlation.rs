@@ -0,0 +1,245 @@
+use crate::models::{LogEvent, LogGroup, LogLevel};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// One event of a reconstructed session
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionStep {
+    pub line_number: usize,
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Rank of the event's pattern (1-based, as displayed)
+    pub pattern_id: usize,
+    pub level: Option<LogLevel>,
+}
+
+/// All events sharing one correlation ID, in order
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    /// Correlation key the ID was found under
+    pub key: String,
+    pub id: String,
+    pub steps: Vec<SessionStep>,
+    pub ended_in_error: bool,
+}
+
+impl Session {
+    /// Pattern IDs along the session, with consecutive repeats collapsed
+    pub fn path(&self) -> Vec<usize> {
+        let mut path: Vec<usize> = self.steps.iter().map(|s| s.pattern_id).collect();
+        path.dedup();
+        path
+    }
+}
+
+/// How often a sequence of patterns occurs across sessions
+#[derive(Debug, Clone, Serialize)]
+pub struct SequenceSummary {
+    /// Pattern IDs in order, e.g. [1, 4, 2] for #1→#4→#2
+    pub path: Vec<usize>,
+    pub sessions: usize,
+    pub ended_in_error: usize,
+}
+
+/// Result of session reconstruction
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionReport {
+    pub total_sessions: usize,
+    pub error_sessions: usize,
+
+    /// Most frequent sequences across all sessions
+    pub common_sequences: Vec<SequenceSummary>,
+
+    /// Most frequent sequences among sessions that ended in an error
+    pub error_sequences: Vec<SequenceSummary>,
+
+    /// Sample of reconstructed sessions (errors first, then longest)
+    pub sessions: Vec<Session>,
+}
+
+/// Links events that carry the same request/trace/correlation ID
+pub struct SessionCorrelator {
+    /// (key, regex extracting the key's value from a raw line)
+    extractors: Vec<(String, Regex)>,
+}
+
+impl SessionCorrelator {
+    pub fn new(keys: &[String]) -> Self {
+        let extractors = keys
+            .iter()
+            .map(|key| {
+                // Matches: request_id=abc, "trace_id": "abc", X-Correlation-ID: abc
+                let regex = Regex::new(&format!(
+                    r#"(?i)(?:^|[^\w-])["']?{}["']?\s*[=:]\s*["']?([A-Za-z0-9][\w\-.:/]*)"#,
+                    regex::escape(key)
+                ))
+                .unwrap();
+                (key.clone(), regex)
+            })
+            .collect();
+
+        Self { extractors }
+    }
+
+    /// Find the first configured correlation ID on an event.
+    /// Structured fields take precedence over the raw line.
+    pub fn extract_id(&self, event: &LogEvent) -> Option<(String, String)> {
+        for (key, regex) in &self.extractors {
+            if let Some((_, value)) = event.fields.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)) {
+                return Some((key.clone(), value.clone()));
+            }
+
+            if let Some(m) = regex.captures(&event.raw).and_then(|cap| cap.get(1)) {
+                return Some((key.clone(), m.as_str().to_string()));
+            }
+        }
+
+        None
+    }
+
+    /// Reconstruct sessions from grouped events and aggregate their sequences
+    pub fn reconstruct(&self, groups: &[LogGroup], top_n: usize) -> SessionReport {
+        let mut by_id: HashMap<(String, String), Vec<SessionStep>> = HashMap::new();
+
+        for (i, group) in groups.iter().enumerate() {
+            for event in &group.events {
+                if let Some(id) = self.extract_id(event) {
+                    by_id.entry(id).or_default().push(SessionStep {
+                        line_number: event.line_number,
+                        timestamp: event.timestamp,
+                        pattern_id: i + 1,
+                        level: event.level.clone(),
+                    });
+                }
+            }
+        }
+
+        let mut sessions: Vec<Session> = by_id
+            .into_iter()
+            .map(|((key, id), mut steps)| {
+                steps.sort_by_key(|s| (s.timestamp, s.line_number));
+                let ended_in_error = steps
+                    .last()
+                    .and_then(|s| s.level.as_ref())
+                    .is_some_and(|l| l.is_error());
+                Session { key, id, steps, ended_in_error }
+            })
+            .collect();
+
+        let common_sequences = Self::aggregate(sessions.iter());
+        let error_sequences = Self::aggregate(sessions.iter().filter(|s| s.ended_in_error));
+
+        let total_sessions = sessions.len();
+        let error_sessions = sessions.iter().filter(|s| s.ended_in_error).count();
+
+        sessions.sort_by(|a, b| {
+            b.ended_in_error
+                .cmp(&a.ended_in_error)
+                .then(b.steps.len().cmp(&a.steps.len()))
+                .then(a.id.cmp(&b.id))
+        });
+        sessions.truncate(top_n);
+
+        SessionReport {
+            total_sessions,
+            error_sessions,
+            common_sequences: common_sequences.into_iter().take(top_n).collect(),
+            error_sequences: error_sequences.into_iter().take(top_n).collect(),
+            sessions,
+        }
+    }
+
+    /// Count sessions per collapsed pattern path, most frequent first
+    fn aggregate<'a>(sessions: impl Iterator<Item = &'a Session>) -> Vec<SequenceSummary> {
+        let mut counts: HashMap<Vec<usize>, (usize, usize)> = HashMap::new();
+
+        for session in sessions {
+            let entry = counts.entry(session.path()).or_insert((0, 0));
+            entry.0 += 1;
+            if session.ended_in_error {
+                entry.1 += 1;
+            }
+        }
+
+        let mut result: Vec<SequenceSummary> = counts
+            .into_iter()
+            .map(|(path, (sessions, ended_in_error))| SequenceSummary {
+                path,
+                sessions,
+                ended_in_error,
+            })
+            .collect();
+
+        result.sort_by(|a, b| b.sessions.cmp(&a.sessions).then(a.path.cmp(&b.path)));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::testing::event;
+
+    /// A group of raw lines, one event per (seconds, line)
+    fn group(pattern: &str, level: LogLevel, lines: &[(i64, &str)]) -> LogGroup {
+        let mut group = LogGroup::new(pattern.to_string());
+        for &(seconds, line) in lines {
+            group.add_event(event(seconds, level.clone(), line));
+        }
+        group
+    }
+
+    #[test]
+    fn splits_sessions_by_id() {
+        let groups = vec![
+            group("request started", LogLevel::Info, &[
+                (0, "request_id=req-1 request started"),
+                (1, "request_id=req-2 request started"),
+                (2, "request_id=req-3 request started"),
+                (3, "request started without an id"),
+            ]),
+            group("db query", LogLevel::Info, &[
+                (4, "request_id=req-1 db query"),
+                (5, "request_id=req-2 db query"),
+                (6, "request_id=req-3 db query"),
+            ]),
+            group("request failed", LogLevel::Error, &[(8, "request_id=req-2 request failed")]),
+            group("request done", LogLevel::Info, &[
+                (7, "request_id=req-1 request done"),
+                (9, "request_id=req-3 request done"),
+            ]),
+        ];
+
+        let report = SessionCorrelator::new(&["request_id".to_string()]).reconstruct(&groups, 10);
+
+        assert_eq!(report.total_sessions, 3);
+        assert_eq!(report.error_sessions, 1);
+
+        let common: Vec<(Vec<usize>, usize)> = report.common_sequences.iter().map(|s| (s.path.clone(), s.sessions)).collect();
+        assert_eq!(common, vec![(vec![1, 2, 4], 2), (vec![1, 2, 3], 1)]);
+        assert_eq!(report.error_sequences.len(), 1);
+        assert_eq!(report.error_sequences[0].path, vec![1, 2, 3]);
+
+        // Sessions that ended in an error come first
+        let ids: Vec<&str> = report.sessions.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["req-2", "req-1", "req-3"]);
+        assert!(report.sessions[0].ended_in_error);
+        assert_eq!(report.sessions[0].key, "request_id");
+    }
+
+    #[test]
+    fn prefers_structured_fields_over_the_raw_line() {
+        let correlator = SessionCorrelator::new(&["trace_id".to_string(), "request_id".to_string()]);
+
+        let json = event(0, LogLevel::Info, r#"{"msg": "ok", "Trace_ID": "4bf92f35"}"#);
+        assert_eq!(correlator.extract_id(&json), Some(("trace_id".to_string(), "4bf92f35".to_string())));
+
+        let mut structured = event(0, LogLevel::Info, "request_id=from-the-line");
+        structured.fields.insert("request_id".to_string(), "from-a-field".to_string());
+        assert_eq!(correlator.extract_id(&structured), Some(("request_id".to_string(), "from-a-field".to_string())));
+
+        // Part of a longer key is not a match
+        assert_eq!(correlator.extract_id(&event(0, LogLevel::Info, "parent_request_id=abc")), None);
+    }
+}
@@ -0,0 +1,276 @@
+use super::TimeBuckets;
+use crate::models::LogGroup;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Timestamped samples of one metric, with its unit
+type Samples = (&'static str, Vec<(Option<DateTime<Utc>>, f64)>);
+
+/// Summary statistics over a set of metric values
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricStats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
+
+impl MetricStats {
+    /// Compute statistics using nearest-rank percentiles
+    pub fn from_values(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let percentile = |p: f64| {
+            let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+
+        Some(Self {
+            count: sorted.len(),
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            p50: percentile(50.0),
+            p90: percentile(90.0),
+            p99: percentile(99.0),
+        })
+    }
+}
+
+/// Statistics of one metric within one time bucket
+#[derive(Debug, Clone, Serialize)]
+pub struct BucketStats {
+    pub start: DateTime<Utc>,
+    pub stats: MetricStats,
+}
+
+/// A numeric value extracted from every message of a pattern
+#[derive(Debug, Clone, Serialize)]
+pub struct PatternMetric {
+    /// Metric name, e.g. "duration", "size_2" or "rows"
+    pub name: String,
+    /// Normalized unit: ms, bytes or count
+    pub unit: String,
+    pub overall: MetricStats,
+    pub buckets: Vec<BucketStats>,
+}
+
+/// All metrics extracted for one pattern
+#[derive(Debug, Clone, Serialize)]
+pub struct PatternMetrics {
+    /// Rank of the pattern (1-based, as displayed)
+    pub pattern_id: usize,
+    pub pattern: String,
+    pub metrics: Vec<PatternMetric>,
+}
+
+/// Extracts durations, sizes and counts from log messages
+pub struct MetricExtractor {
+    /// Matches: 532ms, 1.2s, 30 sec, 2min
+    duration_regex: Regex,
+    /// Matches: 512B, 1.5 MB, 64KiB
+    size_regex: Regex,
+    /// Matches: 12 rows, 3 retries, 40 connections
+    count_regex: Regex,
+}
+
+impl MetricExtractor {
+    pub fn new() -> Self {
+        Self {
+            duration_regex: Regex::new(
+                r"(?i)\b(\d+(?:\.\d+)?)\s?(ns|us|µs|ms|s|secs?|seconds?|m|mins?|minutes?|h|hours?)\b"
+            ).unwrap(),
+
+            size_regex: Regex::new(
+                r"(?i)\b(\d+(?:\.\d+)?)\s?(b|bytes?|kb|kib|mb|mib|gb|gib)\b"
+            ).unwrap(),
+
+            count_regex: Regex::new(
+                r"(?i)\b(\d+)\s+(rows|records|items|entries|retries|attempts|requests|connections|messages|events|files|keys)\b"
+            ).unwrap(),
+        }
+    }
+
+    /// Extract (name, unit, value) triples from a message. Repeated metrics of
+    /// the same kind are numbered by position ("duration", "duration_2", ...).
+    pub fn extract(&self, message: &str) -> Vec<(String, &'static str, f64)> {
+        let mut result = Vec::new();
+
+        for (i, cap) in self.duration_regex.captures_iter(message).enumerate() {
+            if let Ok(value) = cap[1].parse::<f64>() {
+                let factor = match cap[2].to_lowercase().as_str() {
+                    "ns" => 1e-6,
+                    "us" | "µs" => 1e-3,
+                    "ms" => 1.0,
+                    "m" | "min" | "mins" | "minute" | "minutes" => 60_000.0,
+                    "h" | "hour" | "hours" => 3_600_000.0,
+                    _ => 1000.0,
+                };
+                result.push((Self::numbered("duration", i), "ms", value * factor));
+            }
+        }
+
+        for (i, cap) in self.size_regex.captures_iter(message).enumerate() {
+            if let Ok(value) = cap[1].parse::<f64>() {
+                let factor = match cap[2].to_lowercase().as_str() {
+                    "kb" => 1e3,
+                    "kib" => 1024.0,
+                    "mb" => 1e6,
+                    "mib" => 1024.0 * 1024.0,
+                    "gb" => 1e9,
+                    "gib" => 1024.0 * 1024.0 * 1024.0,
+                    _ => 1.0,
+                };
+                result.push((Self::numbered("size", i), "bytes", value * factor));
+            }
+        }
+
+        for cap in self.count_regex.captures_iter(message) {
+            if let Ok(value) = cap[1].parse::<f64>() {
+                result.push((cap[2].to_lowercase(), "count", value));
+            }
+        }
+
+        result
+    }
+
+    /// Compute per-pattern metric statistics for the top `top_n` groups
+    pub fn analyze(&self, groups: &[LogGroup], top_n: usize, buckets: Option<&TimeBuckets>) -> Vec<PatternMetrics> {
+        groups
+            .iter()
+            .take(top_n)
+            .enumerate()
+            .filter_map(|(i, group)| {
+                let metrics = self.analyze_group(group, buckets);
+                (!metrics.is_empty()).then(|| PatternMetrics {
+                    pattern_id: i + 1,
+                    pattern: group.pattern.clone(),
+                    metrics,
+                })
+            })
+            .collect()
+    }
+
+    /// Compute statistics for every metric found in a group
+    pub fn analyze_group(&self, group: &LogGroup, buckets: Option<&TimeBuckets>) -> Vec<PatternMetric> {
+        let mut samples: BTreeMap<String, Samples> = BTreeMap::new();
+
+        for event in &group.events {
+            for (name, unit, value) in self.extract(&event.message) {
+                samples
+                    .entry(name)
+                    .or_insert((unit, Vec::new()))
+                    .1
+                    .push((event.timestamp, value));
+            }
+        }
+
+        samples
+            .into_iter()
+            .filter_map(|(name, (unit, values))| {
+                let all: Vec<f64> = values.iter().map(|(_, v)| *v).collect();
+                let overall = MetricStats::from_values(&all)?;
+
+                let buckets = buckets
+                    .map(|b| {
+                        let mut per_bucket: Vec<Vec<f64>> = vec![Vec::new(); b.count];
+                        for (ts, value) in values.iter().filter_map(|(ts, v)| ts.map(|t| (t, *v))) {
+                            per_bucket[b.index(ts)].push(value);
+                        }
+                        per_bucket
+                            .iter()
+                            .enumerate()
+                            .filter_map(|(idx, vals)| {
+                                MetricStats::from_values(vals).map(|stats| BucketStats {
+                                    start: b.bucket_start(idx),
+                                    stats,
+                                })
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                Some(PatternMetric {
+                    name,
+                    unit: unit.to_string(),
+                    overall,
+                    buckets,
+                })
+            })
+            .collect()
+    }
+
+    fn numbered(kind: &str, index: usize) -> String {
+        if index == 0 {
+            kind.to_string()
+        } else {
+            format!("{}_{}", kind, index + 1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::testing::event;
+    use crate::models::LogLevel;
+    use chrono::Duration;
+
+    #[test]
+    fn computes_nearest_rank_percentiles() {
+        let values: Vec<f64> = (1..=100).rev().map(f64::from).collect();
+        let stats = MetricStats::from_values(&values).unwrap();
+
+        assert_eq!((stats.count, stats.min, stats.max, stats.mean), (100, 1.0, 100.0, 50.5));
+        assert_eq!((stats.p50, stats.p90, stats.p99), (50.0, 90.0, 99.0));
+
+        let small = MetricStats::from_values(&[30.0, 10.0, 20.0]).unwrap();
+        assert_eq!((small.p50, small.p90, small.p99), (20.0, 30.0, 30.0));
+        assert!(MetricStats::from_values(&[]).is_none());
+    }
+
+    #[test]
+    fn normalizes_units() {
+        let extractor = MetricExtractor::new();
+
+        let metrics = extractor.extract("query took 250ms then 1.5s, wrote 2 KiB and 3 rows");
+        assert_eq!(
+            metrics,
+            vec![
+                ("duration".to_string(), "ms", 250.0),
+                ("duration_2".to_string(), "ms", 1500.0),
+                ("size".to_string(), "bytes", 2048.0),
+                ("rows".to_string(), "count", 3.0),
+            ]
+        );
+
+        assert_eq!(extractor.extract("idle for 2min")[0].2, 120_000.0);
+        assert_eq!(extractor.extract("lock held 500us")[0].2, 0.5);
+    }
+
+    #[test]
+    fn splits_statistics_by_bucket() {
+        let mut group = LogGroup::new("request took <VAR>".to_string());
+        for (seconds, message) in [(0, "request took 100ms"), (30, "request took 300ms"), (90, "request took 2s")] {
+            group.add_event(event(seconds, LogLevel::Info, message));
+        }
+        let buckets = TimeBuckets::covering(std::slice::from_ref(&group), Some(Duration::seconds(60))).unwrap();
+
+        let metrics = MetricExtractor::new().analyze_group(&group, Some(&buckets));
+
+        assert_eq!(metrics.len(), 1);
+        assert_eq!((metrics[0].name.as_str(), metrics[0].unit.as_str()), ("duration", "ms"));
+        assert_eq!(metrics[0].overall.max, 2000.0);
+        let means: Vec<f64> = metrics[0].buckets.iter().map(|b| b.stats.mean).collect();
+        assert_eq!(means, vec![200.0, 2000.0]);
+    }
+}
@@ -0,0 +1,168 @@
+pub mod access;
+pub mod changepoint;
+pub mod correlation;
+pub mod metrics;
+pub mod periodicity;
+pub mod precedence;
+pub mod root_cause;
+pub mod similarity;
+
+use crate::ingest::ParseSummary;
+use crate::models::LogGroup;
+use crate::reasoning::Explanation;
+use access::AccessReport;
+use changepoint::ChangePointReport;
+use chrono::{DateTime, Duration, Utc};
+use correlation::SessionReport;
+use metrics::PatternMetrics;
+use periodicity::Periodicity;
+use precedence::Precedence;
+use root_cause::RootCauseCandidate;
+use serde::Serialize;
+use similarity::{SemanticCluster, SimilarPatterns};
+
+/// Optional analyses computed on top of the grouped events
+#[derive(Debug, Default, Serialize)]
+pub struct AnalysisReport {
+    /// How the input was parsed (format and confidence)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<ParseSummary>,
+
+    /// Periodic patterns and their missed beats
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub periodicity: Option<Vec<Periodicity>>,
+
+    /// Ranked "A precedes B" relationships
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub precedence: Option<Vec<Precedence>>,
+
+    /// Patterns ranked by how likely they caused the incident
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root_causes: Option<Vec<RootCauseCandidate>>,
+
+    /// Request sessions reconstructed from correlation IDs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sessions: Option<SessionReport>,
+
+    /// Shifts in global volume, error ratio and pattern diversity
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub change_points: Option<ChangePointReport>,
+
+    /// Durations, sizes and counts extracted per pattern
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<Vec<PatternMetrics>>,
+
+    /// HTTP access-log analytics
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access: Option<AccessReport>,
+
+    /// Patterns grouped by embedding similarity
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clusters: Option<Vec<SemanticCluster>>,
+
+    /// The patterns most similar to a chosen one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similar: Option<SimilarPatterns>,
+
+    /// Probable causes and next steps for the top patterns, from an LLM
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanations: Option<Vec<Explanation>>,
+}
+
+/// Target number of buckets when no bucket width is given
+const AUTO_BUCKETS: i64 = 100;
+
+/// Most buckets allowed; analyses keep per-pattern counts for every bucket
+const MAX_BUCKETS: i64 = 10_000;
+
+/// Fixed-width time buckets spanning all timestamped events
+#[derive(Debug, Clone)]
+pub struct TimeBuckets {
+    pub start: DateTime<Utc>,
+    pub width: Duration,
+    pub count: usize,
+}
+
+impl TimeBuckets {
+    /// Cover the time range of all groups. Without an explicit width the
+    /// range is split into roughly 100 whole-second buckets. A width that
+    /// would need more than 10,000 buckets is widened, with a warning.
+    pub fn covering(groups: &[LogGroup], width: Option<Duration>) -> Option<Self> {
+        let (start, end) = groups
+            .iter()
+            .filter_map(|g| g.time_window)
+            .reduce(|(s1, e1), (s2, e2)| (s1.min(s2), e1.max(e2)))?;
+
+        let span = (end - start).num_seconds();
+        let mut width = width.unwrap_or_else(|| Duration::seconds((span + AUTO_BUCKETS - 1) / AUTO_BUCKETS).max(Duration::seconds(1)));
+
+        if span / width.num_seconds() >= MAX_BUCKETS {
+            let widened = Duration::seconds((span + MAX_BUCKETS - 2) / (MAX_BUCKETS - 1));
+            eprintln!(
+                "⚠ Warning: {}s buckets over {}s of logs would make too many buckets, using {}s buckets",
+                width.num_seconds(),
+                span,
+                widened.num_seconds()
+            );
+            width = widened;
+        }
+        let count = (span / width.num_seconds()) as usize + 1;
+
+        Some(Self { start, width, count })
+    }
+
+    /// Bucket containing `ts`
+    pub fn index(&self, ts: DateTime<Utc>) -> usize {
+        let offset = (ts - self.start).num_seconds().max(0) / self.width.num_seconds();
+        (offset as usize).min(self.count - 1)
+    }
+
+    pub fn bucket_start(&self, index: usize) -> DateTime<Utc> {
+        self.start + self.width * index as i32
+    }
+}
+
+/// Collect the timestamps of a group's events in ascending order
+pub fn sorted_timestamps(group: &LogGroup) -> Vec<DateTime<Utc>> {
+    let mut timestamps: Vec<DateTime<Utc>> = group
+        .events
+        .iter()
+        .filter_map(|e| e.timestamp)
+        .collect();
+    timestamps.sort();
+    timestamps
+}
+
+/// Builders for the analyses' tests
+#[cfg(test)]
+pub(crate) mod testing {
+    use crate::models::{LogEvent, LogGroup, LogLevel};
+    use chrono::{DateTime, Duration, Utc};
+    use std::collections::BTreeMap;
+
+    /// 2024-01-05T12:00:00Z, the start of every test timeline
+    pub fn start() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_704_456_000, 0).unwrap()
+    }
+
+    /// An event the given number of seconds after `start()`
+    pub fn event(seconds: i64, level: LogLevel, message: &str) -> LogEvent {
+        LogEvent {
+            timestamp: Some(start() + Duration::seconds(seconds)),
+            level: Some(level),
+            message: message.to_string(),
+            raw: message.to_string(),
+            line_number: 0,
+            fields: BTreeMap::new(),
+        }
+    }
+
+    /// A group of one message at the given seconds after `start()`
+    pub fn group(pattern: &str, level: LogLevel, seconds: &[i64]) -> LogGroup {
+        let mut group = LogGroup::new(pattern.to_string());
+        for &s in seconds {
+            group.add_event(event(s, level.clone(), pattern));
+        }
+        group
+    }
+}
@@ -0,0 +1,228 @@
+use super::sorted_timestamps;
+use crate::models::LogGroup;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Minimum events needed to judge a pattern's periodicity
+const MIN_EVENTS: usize = 5;
+/// Share of intervals that must be close to the median interval
+const MIN_REGULARITY: f64 = 0.8;
+/// Relative tolerance around the median for an interval to count as regular
+const INTERVAL_TOLERANCE: f64 = 0.25;
+/// An interval this many times the period (plus jitter) is a missed beat
+const GAP_FACTOR: f64 = 1.5;
+
+/// A gap in a periodic pattern where one or more beats are missing
+#[derive(Debug, Clone, Serialize)]
+pub struct MissedBeat {
+    pub gap_start: DateTime<Utc>,
+    pub gap_end: DateTime<Utc>,
+    pub missed: usize,
+    /// The pattern never resumed: the gap runs to the end of the log
+    pub stopped: bool,
+}
+
+/// A pattern recurring at a regular interval (health checks, cron jobs, ...)
+#[derive(Debug, Clone, Serialize)]
+pub struct Periodicity {
+    /// Rank of the pattern (1-based, as displayed)
+    pub pattern_id: usize,
+    pub pattern: String,
+
+    /// Estimated period (median inter-arrival time)
+    pub interval_secs: f64,
+
+    /// Median absolute deviation of the regular intervals
+    pub jitter_secs: f64,
+
+    /// Share of intervals within tolerance of the period
+    pub regularity: f64,
+
+    /// Gaps where expected occurrences are missing
+    pub missed_beats: Vec<MissedBeat>,
+}
+
+/// Detects periodic patterns from their inter-arrival times
+pub struct PeriodicityDetector;
+
+impl PeriodicityDetector {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Find all periodic groups
+    pub fn detect(&self, groups: &[LogGroup]) -> Vec<Periodicity> {
+        let log_end = groups.iter().filter_map(|g| g.time_window.map(|(_, end)| end)).max();
+
+        groups
+            .iter()
+            .enumerate()
+            .filter_map(|(i, group)| self.analyze(i + 1, group, log_end))
+            .collect()
+    }
+
+    fn analyze(&self, pattern_id: usize, group: &LogGroup, log_end: Option<DateTime<Utc>>) -> Option<Periodicity> {
+        let timestamps = sorted_timestamps(group);
+        if timestamps.len() < MIN_EVENTS {
+            return None;
+        }
+
+        let intervals: Vec<f64> = timestamps
+            .windows(2)
+            .map(|w| (w[1] - w[0]).num_milliseconds() as f64 / 1000.0)
+            .collect();
+
+        let period = median(&intervals);
+        if period <= 0.0 {
+            return None;
+        }
+
+        let tolerance = (period * INTERVAL_TOLERANCE).max(1.0);
+        let regular: Vec<f64> = intervals
+            .iter()
+            .copied()
+            .filter(|d| (d - period).abs() <= tolerance)
+            .collect();
+
+        let regularity = regular.len() as f64 / intervals.len() as f64;
+        if regularity < MIN_REGULARITY {
+            return None;
+        }
+
+        let deviations: Vec<f64> = regular.iter().map(|d| (d - period).abs()).collect();
+        let jitter = median(&deviations);
+
+        let gap_threshold = period * GAP_FACTOR + jitter * 3.0;
+        let mut missed_beats: Vec<MissedBeat> = timestamps
+            .windows(2)
+            .zip(&intervals)
+            .filter(|(_, d)| **d > gap_threshold)
+            .map(|(w, d)| MissedBeat {
+                gap_start: w[0],
+                gap_end: w[1],
+                missed: ((d / period).round() as usize).saturating_sub(1).max(1),
+                stopped: false,
+            })
+            .collect();
+
+        // A heartbeat that stops for good leaves no closing beat, so measure
+        // the silence up to the end of the log
+        let last = timestamps[timestamps.len() - 1];
+        if let Some(end) = log_end {
+            let silence = (end - last).num_milliseconds() as f64 / 1000.0;
+            if silence > gap_threshold {
+                missed_beats.push(MissedBeat {
+                    gap_start: last,
+                    gap_end: end,
+                    missed: ((silence / period).floor() as usize).max(1),
+                    stopped: true,
+                });
+            }
+        }
+
+        Some(Periodicity {
+            pattern_id,
+            pattern: group.pattern.clone(),
+            interval_secs: period,
+            jitter_secs: jitter,
+            regularity,
+            missed_beats,
+        })
+    }
+
+    /// Move periodic groups behind all other groups, keeping relative order,
+    /// and renumber the detected periodicities accordingly
+    pub fn demote(groups: &mut Vec<LogGroup>, periodic: &mut [Periodicity]) {
+        let is_periodic = |i: usize| periodic.iter().any(|p| p.pattern_id == i + 1);
+
+        let mut order: Vec<usize> = (0..groups.len()).filter(|i| !is_periodic(*i)).collect();
+        order.extend((0..groups.len()).filter(|i| is_periodic(*i)));
+
+        for p in periodic.iter_mut() {
+            p.pattern_id = order.iter().position(|i| *i + 1 == p.pattern_id).unwrap() + 1;
+        }
+
+        let mut slots: Vec<Option<LogGroup>> = std::mem::take(groups).into_iter().map(Some).collect();
+        *groups = order.iter().filter_map(|i| slots[*i].take()).collect();
+    }
+}
+
+/// Median of a slice (0.0 when empty)
+fn median(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let mid = sorted.len() / 2;
+
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::testing::{group, start};
+    use crate::models::LogLevel;
+    use chrono::Duration;
+
+    #[test]
+    fn finds_missed_beats_in_a_regular_heartbeat() {
+        let beats: Vec<i64> = (0..=1800).step_by(30).filter(|s| !(600..=690).contains(s)).collect();
+        let groups = vec![
+            group("health check ok", LogLevel::Info, &beats),
+            group("user logged in", LogLevel::Info, &[5, 17, 400, 410, 1200, 1790]),
+        ];
+
+        let periodic = PeriodicityDetector::new().detect(&groups);
+
+        assert_eq!(periodic.len(), 1);
+        let heartbeat = &periodic[0];
+        assert_eq!(heartbeat.pattern_id, 1);
+        assert_eq!(heartbeat.interval_secs, 30.0);
+        assert_eq!(heartbeat.jitter_secs, 0.0);
+
+        assert_eq!(heartbeat.missed_beats.len(), 1);
+        let gap = &heartbeat.missed_beats[0];
+        assert_eq!((gap.gap_start, gap.gap_end), (start() + Duration::seconds(570), start() + Duration::seconds(720)));
+        assert_eq!(gap.missed, 4);
+        assert!(!gap.stopped);
+    }
+
+    #[test]
+    fn reports_a_heartbeat_that_stops_before_the_end() {
+        let beats: Vec<i64> = (0..=600).step_by(30).collect();
+        let groups = vec![
+            group("health check ok", LogLevel::Info, &beats),
+            group("request failed", LogLevel::Error, &[650, 1000, 1800]),
+        ];
+
+        let periodic = PeriodicityDetector::new().detect(&groups);
+
+        assert_eq!(periodic.len(), 1);
+        let gap = &periodic[0].missed_beats[0];
+        assert_eq!(periodic[0].missed_beats.len(), 1);
+        assert!(gap.stopped);
+        assert_eq!((gap.gap_start, gap.gap_end), (start() + Duration::seconds(600), start() + Duration::seconds(1800)));
+        assert_eq!(gap.missed, 40);
+    }
+
+    #[test]
+    fn moves_periodic_groups_last() {
+        let mut groups = vec![
+            group("health check ok", LogLevel::Info, &(0..300).step_by(10).collect::<Vec<_>>()),
+            group("request failed", LogLevel::Error, &[3, 4, 250]),
+        ];
+        let mut periodic = PeriodicityDetector::new().detect(&groups);
+
+        PeriodicityDetector::demote(&mut groups, &mut periodic);
+
+        assert_eq!(groups[0].pattern, "request failed");
+        assert_eq!(periodic[0].pattern_id, 2);
+    }
+}
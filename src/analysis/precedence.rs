@@ -0,0 +1,219 @@
+use super::sorted_timestamps;
+use crate::models::LogGroup;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+/// Minimum number of preceded occurrences for a relationship to be reported
+const MIN_SUPPORT: usize = 2;
+/// Minimum share of consequent occurrences that must be preceded
+const MIN_CONFIDENCE: f64 = 0.5;
+/// Minimum lift; antecedents that cover the whole timeline precede everything
+const MIN_LIFT: f64 = 1.1;
+
+/// A lagged "antecedent precedes consequent" relationship between two patterns
+#[derive(Debug, Clone, Serialize)]
+pub struct Precedence {
+    /// Rank of the antecedent pattern (1-based, as displayed)
+    pub antecedent_id: usize,
+    pub antecedent: String,
+
+    /// Rank of the consequent pattern (1-based, as displayed)
+    pub consequent_id: usize,
+    pub consequent: String,
+
+    /// Consequent occurrences preceded by the antecedent within the window
+    pub support: usize,
+
+    /// P(antecedent within window before | consequent)
+    pub confidence: f64,
+
+    /// Confidence relative to the chance of the antecedent covering a random instant
+    pub lift: f64,
+
+    /// Average lag between the closest preceding antecedent and the consequent
+    pub mean_lag_ms: i64,
+}
+
+/// Mines lagged co-occurrence between the most frequent patterns
+pub struct PrecedenceMiner {
+    window: Duration,
+}
+
+impl PrecedenceMiner {
+    pub fn new(window: Duration) -> Self {
+        Self { window }
+    }
+
+    /// Compute precedence relationships between the top `top_n` groups,
+    /// ranked by confidence, then lift, then support
+    pub fn mine(&self, groups: &[LogGroup], top_n: usize) -> Vec<Precedence> {
+        let series: Vec<Vec<DateTime<Utc>>> = groups
+            .iter()
+            .take(top_n)
+            .map(sorted_timestamps)
+            .collect();
+
+        let span = Self::overall_span(&series);
+        let mut result = Vec::new();
+
+        for (a, a_times) in series.iter().enumerate() {
+            if a_times.is_empty() {
+                continue;
+            }
+            let coverage = self.coverage(a_times, span);
+
+            for (b, b_times) in series.iter().enumerate() {
+                if a == b || b_times.is_empty() {
+                    continue;
+                }
+
+                let (support, lag_sum) = self.count_preceded(a_times, b_times);
+                if support < MIN_SUPPORT {
+                    continue;
+                }
+
+                let confidence = support as f64 / b_times.len() as f64;
+                if confidence < MIN_CONFIDENCE {
+                    continue;
+                }
+
+                let lift = if coverage > 0.0 { confidence / coverage } else { 0.0 };
+                if lift < MIN_LIFT {
+                    continue;
+                }
+
+                result.push(Precedence {
+                    antecedent_id: a + 1,
+                    antecedent: groups[a].pattern.clone(),
+                    consequent_id: b + 1,
+                    consequent: groups[b].pattern.clone(),
+                    support,
+                    confidence,
+                    lift,
+                    mean_lag_ms: lag_sum / support as i64,
+                });
+            }
+        }
+
+        result.sort_by(|x, y| {
+            y.confidence
+                .total_cmp(&x.confidence)
+                .then(y.lift.total_cmp(&x.lift))
+                .then(y.support.cmp(&x.support))
+        });
+
+        result
+    }
+
+    /// Count consequent events with an antecedent strictly before them and
+    /// within the window. Returns (hits, summed lag in milliseconds).
+    fn count_preceded(&self, antecedent: &[DateTime<Utc>], consequent: &[DateTime<Utc>]) -> (usize, i64) {
+        let mut hits = 0;
+        let mut lag_sum = 0;
+
+        for tb in consequent {
+            let idx = antecedent.partition_point(|ta| ta < tb);
+            if idx == 0 {
+                continue;
+            }
+
+            let lag = *tb - antecedent[idx - 1];
+            if lag <= self.window {
+                hits += 1;
+                lag_sum += lag.num_milliseconds();
+            }
+        }
+
+        (hits, lag_sum)
+    }
+
+    /// Fraction of the timeline lying within `window` after some antecedent event
+    fn coverage(&self, times: &[DateTime<Utc>], span: Option<(DateTime<Utc>, DateTime<Utc>)>) -> f64 {
+        let Some((start, end)) = span else {
+            return 0.0;
+        };
+        let total = (end - start).num_milliseconds();
+        if total <= 0 {
+            return 1.0;
+        }
+
+        let mut covered = 0;
+        let mut current: Option<(DateTime<Utc>, DateTime<Utc>)> = None;
+
+        for &t in times {
+            let interval_end = (t + self.window).min(end);
+            current = match current {
+                Some((s, e)) if t <= e => Some((s, e.max(interval_end))),
+                Some((s, e)) => {
+                    covered += (e - s).num_milliseconds();
+                    Some((t, interval_end))
+                }
+                None => Some((t, interval_end)),
+            };
+        }
+        if let Some((s, e)) = current {
+            covered += (e - s).num_milliseconds();
+        }
+
+        (covered as f64 / total as f64).min(1.0)
+    }
+
+    /// Earliest and latest timestamp across all series
+    fn overall_span(series: &[Vec<DateTime<Utc>>]) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let start = series.iter().filter_map(|s| s.first()).min()?;
+        let end = series.iter().filter_map(|s| s.last()).max()?;
+        Some((*start, *end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::testing::group;
+    use crate::models::LogLevel;
+
+    #[test]
+    fn measures_support_confidence_and_lift() {
+        let groups = vec![
+            group("cache miss", LogLevel::Warn, &[0, 100, 200, 300]),
+            group("db timeout", LogLevel::Error, &[3, 103, 203, 303]),
+            group("heartbeat", LogLevel::Info, &[25, 75, 125, 175, 225, 275]),
+        ];
+
+        let relations = PrecedenceMiner::new(Duration::seconds(5)).mine(&groups, 3);
+
+        assert_eq!(relations.len(), 1);
+        let relation = &relations[0];
+        assert_eq!((relation.antecedent_id, relation.consequent_id), (1, 2));
+        assert_eq!(relation.support, 4);
+        assert_eq!(relation.confidence, 1.0);
+        // The misses cover 5 + 5 + 5 + 3 of the 303 seconds
+        assert!((relation.lift - 303.0 / 18.0).abs() < 1e-9, "lift {}", relation.lift);
+        assert_eq!(relation.mean_lag_ms, 3000);
+    }
+
+    #[test]
+    fn ignores_an_antecedent_that_covers_the_whole_timeline() {
+        let every_second: Vec<i64> = (0..=300).collect();
+        let groups = vec![
+            group("tick", LogLevel::Info, &every_second),
+            group("db timeout", LogLevel::Error, &[3, 103, 203, 300]),
+        ];
+
+        assert!(PrecedenceMiner::new(Duration::seconds(5)).mine(&groups, 2).is_empty());
+    }
+
+    #[test]
+    fn only_counts_antecedents_within_the_window() {
+        let groups = vec![
+            group("cache miss", LogLevel::Warn, &[0, 100, 200]),
+            group("db timeout", LogLevel::Error, &[3, 110, 203, 300]),
+        ];
+
+        let relations = PrecedenceMiner::new(Duration::seconds(5)).mine(&groups, 2);
+
+        assert_eq!(relations.len(), 1);
+        assert_eq!(relations[0].support, 2);
+        assert_eq!(relations[0].confidence, 0.5);
+    }
+}
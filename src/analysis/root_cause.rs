@@ -0,0 +1,244 @@
+use super::sorted_timestamps;
+use crate::formats::timestamp::TimestampParser;
+use crate::models::{LogGroup, LogLevel};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+/// Weights of the individual signals in the final score
+const ONSET_WEIGHT: f64 = 0.3;
+const RATE_WEIGHT: f64 = 0.3;
+const SEVERITY_WEIGHT: f64 = 0.2;
+const PRECEDENCE_WEIGHT: f64 = 0.2;
+
+/// Rate increase (as a factor) that saturates the rate-change signal
+const RATE_SATURATION: f64 = 100.0;
+
+/// Individual signals behind a candidate's score, each in [0, 1]
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoreComponents {
+    /// How suspicious the first appearance is relative to the incident
+    pub onset: f64,
+    /// Rate increase inside the incident window versus before it
+    pub rate_change: f64,
+    /// Severity of the pattern's dominant level
+    pub severity: f64,
+    /// Share of other error/warning patterns this one appears before
+    pub precedence: f64,
+}
+
+/// A pattern ranked by how likely it is to be causal for the incident
+#[derive(Debug, Clone, Serialize)]
+pub struct RootCauseCandidate {
+    /// Rank of the pattern (1-based, as displayed)
+    pub pattern_id: usize,
+    pub pattern: String,
+
+    /// Weighted combination of the components
+    pub score: f64,
+    pub components: ScoreComponents,
+
+    /// Seconds between first appearance and the incident (negative = before)
+    pub first_seen_offset_secs: i64,
+
+    /// Occurrences per minute before and inside the incident window
+    pub baseline_rate_per_min: Option<f64>,
+    pub window_rate_per_min: f64,
+
+    /// Human-readable reasons for each component
+    pub explanation: Vec<String>,
+}
+
+/// Ranks patterns as root-cause candidates for an incident
+pub struct RootCauseRanker {
+    incident: DateTime<Utc>,
+    window: Duration,
+}
+
+impl RootCauseRanker {
+    pub fn new(incident: DateTime<Utc>, window: Duration) -> Self {
+        Self { incident, window }
+    }
+
+    /// Parse an incident time such as `2024-01-05T12:03Z` or `2024-01-05 12:03:30`
+    /// in the same timezone as the log's timestamps
+    pub fn parse_incident_time(s: &str, timestamps: &TimestampParser) -> Result<DateTime<Utc>> {
+        timestamps
+            .parse(s)
+            .ok_or_else(|| anyhow!("Invalid incident time '{}'. Expected e.g. 2024-01-05T12:03Z", s))
+    }
+
+    /// Score every group active in the incident window, highest score first
+    pub fn rank(&self, groups: &[LogGroup]) -> Vec<RootCauseCandidate> {
+        let window_start = self.incident - self.window;
+        let window_end = self.incident + self.window;
+
+        let series: Vec<Vec<DateTime<Utc>>> = groups.iter().map(sorted_timestamps).collect();
+        let window_minutes = (window_end - window_start).num_seconds().max(1) as f64 / 60.0;
+
+        // Length of the baseline period, if the log starts before the window
+        let baseline_minutes = series
+            .iter()
+            .filter_map(|s| s.first())
+            .min()
+            .filter(|start| **start < window_start)
+            .map(|start| (window_start - *start).num_seconds().max(1) as f64 / 60.0);
+
+        // First in-window occurrence of every error/warning pattern
+        let alerting_onsets: Vec<(usize, DateTime<Utc>)> = groups
+            .iter()
+            .zip(&series)
+            .enumerate()
+            .filter(|(_, (g, _))| matches!(g.dominant_level, Some(LogLevel::Critical | LogLevel::Error | LogLevel::Warn)))
+            .filter_map(|(i, (_, times))| {
+                times
+                    .iter()
+                    .find(|t| **t >= window_start && **t <= window_end)
+                    .map(|t| (i, *t))
+            })
+            .collect();
+
+        let mut candidates = Vec::new();
+
+        for (i, (group, times)) in groups.iter().zip(&series).enumerate() {
+            let in_window: Vec<&DateTime<Utc>> = times
+                .iter()
+                .filter(|t| **t >= window_start && **t <= window_end)
+                .collect();
+            let Some(&&first_in_window) = in_window.first() else {
+                continue;
+            };
+
+            let mut explanation = Vec::new();
+
+            // Onset: new patterns right before the incident are most suspicious
+            let first_seen = times[0];
+            let offset = (first_seen - self.incident).num_seconds();
+            let onset = if first_seen < window_start {
+                explanation.push("already present before the incident window".to_string());
+                0.0
+            } else if first_seen <= self.incident {
+                explanation.push(format!("first appeared {}s before the incident", -offset));
+                1.0
+            } else {
+                explanation.push(format!("first appeared {}s after the incident", offset));
+                0.5
+            };
+
+            // Rate change versus the period before the window
+            let window_rate = in_window.len() as f64 / window_minutes;
+            let baseline_rate = baseline_minutes.map(|minutes| {
+                times.iter().filter(|t| **t < window_start).count() as f64 / minutes
+            });
+
+            let rate_change = match (baseline_rate, baseline_minutes) {
+                (Some(baseline), Some(minutes)) => {
+                    // Half an occurrence over the baseline period stands in for zero
+                    let floor = 0.5 / minutes;
+                    let factor = window_rate / baseline.max(floor);
+                    explanation.push(format!(
+                        "rate {:.1}/min in window vs {:.2}/min before (×{:.1})",
+                        window_rate, baseline, factor
+                    ));
+                    (factor.max(1.0).ln() / RATE_SATURATION.ln()).min(1.0)
+                }
+                _ => {
+                    explanation.push("no baseline before the incident window".to_string());
+                    0.0
+                }
+            };
+
+            // Severity of the dominant level
+            let severity = match group.dominant_level {
+                Some(LogLevel::Critical | LogLevel::Error) => 1.0,
+                Some(LogLevel::Warn) => 0.6,
+                Some(LogLevel::Info) => 0.2,
+                Some(LogLevel::Debug | LogLevel::Trace) => 0.1,
+                None => 0.3,
+            };
+            match group.dominant_level {
+                Some(ref level) => explanation.push(format!("dominant level {:?}", level)),
+                None => explanation.push("no log level detected".to_string()),
+            }
+
+            // Precedence of an alerting pattern over the other alerting patterns
+            let others: Vec<&DateTime<Utc>> = alerting_onsets
+                .iter()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, t)| t)
+                .collect();
+            let is_alerting = alerting_onsets.iter().any(|(j, _)| *j == i);
+            let precedence = if !is_alerting || others.is_empty() {
+                0.0
+            } else {
+                let preceded = others.iter().filter(|t| ***t > first_in_window).count();
+                explanation.push(format!(
+                    "appears before {}/{} other error/warning patterns",
+                    preceded,
+                    others.len()
+                ));
+                preceded as f64 / others.len() as f64
+            };
+
+            let score = ONSET_WEIGHT * onset
+                + RATE_WEIGHT * rate_change
+                + SEVERITY_WEIGHT * severity
+                + PRECEDENCE_WEIGHT * precedence;
+
+            candidates.push(RootCauseCandidate {
+                pattern_id: i + 1,
+                pattern: group.pattern.clone(),
+                score,
+                components: ScoreComponents {
+                    onset,
+                    rate_change,
+                    severity,
+                    precedence,
+                },
+                first_seen_offset_secs: offset,
+                baseline_rate_per_min: baseline_rate,
+                window_rate_per_min: window_rate,
+                explanation,
+            });
+        }
+
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::testing::{group, start};
+
+    #[test]
+    fn ranks_a_precursor_inside_the_window_above_an_unrelated_pattern() {
+        let steady: Vec<i64> = (0..4000).step_by(60).collect();
+        let groups = vec![
+            group("disk usage above threshold", LogLevel::Warn, &steady),
+            group("connection pool exhausted", LogLevel::Error, &[3540, 3550, 3560, 3570, 3580, 3590]),
+            group("request failed", LogLevel::Error, &[3610, 3620, 3630]),
+            group("nightly backup finished", LogLevel::Info, &[100]),
+        ];
+
+        let incident = start() + Duration::seconds(3600);
+        let candidates = RootCauseRanker::new(incident, Duration::seconds(300)).rank(&groups);
+
+        // The backup never ran inside the window, so it is not a candidate
+        let ranked: Vec<usize> = candidates.iter().map(|c| c.pattern_id).collect();
+        assert_eq!(ranked, vec![2, 3, 1]);
+
+        let precursor = &candidates[0];
+        assert_eq!(precursor.first_seen_offset_secs, -60);
+        assert_eq!(precursor.components.onset, 1.0);
+        // The disk warning's in-window onset comes first, the failures after
+        assert_eq!(precursor.components.precedence, 0.5);
+        assert!(precursor.components.rate_change > 0.5);
+
+        let unrelated = &candidates[2];
+        assert_eq!(unrelated.components.onset, 0.0);
+        assert!(unrelated.components.rate_change < 0.1);
+        assert!(precursor.score > unrelated.score + 0.5);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::index::HnswIndex;
+use crate::models::LogGroup;
+use serde::Serialize;
+
+/// Nearest neighbors linked per pattern when clustering
+const NEIGHBORS: usize = 10;
+/// Patterns listed per cluster in text output
+pub const MAX_SHOWN_MEMBERS: usize = 5;
+
+/// A pattern in a cluster or a similar-pattern lookup
+#[derive(Debug, Clone, Serialize)]
+pub struct SimilarPattern {
+    /// Rank of the pattern (1-based, as displayed)
+    pub pattern_id: usize,
+    pub pattern: String,
+    pub count: usize,
+    /// Cosine similarity to the cluster's largest pattern, or to the looked-up one
+    pub similarity: f32,
+}
+
+/// Patterns whose embeddings are close: different wordings of the same problem
+#[derive(Debug, Clone, Serialize)]
+pub struct SemanticCluster {
+    /// Events over all member patterns
+    pub total_count: usize,
+    /// Members by rank, the largest first
+    pub members: Vec<SimilarPattern>,
+}
+
+/// The patterns most similar to one pattern
+#[derive(Debug, Clone, Serialize)]
+pub struct SimilarPatterns {
+    pub pattern_id: usize,
+    pub pattern: String,
+    pub similar: Vec<SimilarPattern>,
+}
+
+/// Groups patterns by embedding similarity, using a nearest-neighbor index so
+/// each pattern is compared with its closest neighbors rather than all others
+pub struct SemanticClusterer {
+    threshold: f32,
+}
+
+impl SemanticClusterer {
+    pub fn new(threshold: f32) -> Self {
+        Self { threshold }
+    }
+
+    /// Link each pattern to its neighbors above the threshold; clusters are
+    /// the connected components with more than one pattern, largest first
+    pub fn cluster(&self, groups: &[LogGroup], index: &HnswIndex) -> Vec<SemanticCluster> {
+        let mut parents: Vec<usize> = (0..groups.len()).collect();
+
+        for i in 0..groups.len() {
+            let Some(vector) = index.vector(i) else {
+                continue;
+            };
+            for (j, similarity) in index.search(vector, NEIGHBORS + 1) {
+                if j != i && j < groups.len() && similarity >= self.threshold {
+                    union(&mut parents, i, j);
+                }
+            }
+        }
+
+        let mut components: Vec<Vec<usize>> = vec![Vec::new(); groups.len()];
+        for i in 0..groups.len() {
+            let root = find(&mut parents, i);
+            components[root].push(i);
+        }
+
+        // Members are in index order, so the first is the largest pattern
+        let mut clusters: Vec<SemanticCluster> = components
+            .into_iter()
+            .filter(|members| members.len() > 1)
+            .map(|members| {
+                let lead = index.vector(members[0]).unwrap_or_default();
+                SemanticCluster {
+                    total_count: members.iter().map(|&i| groups[i].count).sum(),
+                    members: members
+                        .iter()
+                        .map(|&i| similar_pattern(groups, i, index.vector(i).map(|v| dot(lead, v)).unwrap_or(0.0)))
+                        .collect(),
+                }
+            })
+            .collect();
+        clusters.sort_by_key(|c| (std::cmp::Reverse(c.total_count), c.members[0].pattern_id));
+        clusters
+    }
+
+    /// The k patterns most similar to one pattern (1-based id)
+    pub fn similar_to(groups: &[LogGroup], index: &HnswIndex, pattern_id: usize, k: usize) -> Option<SimilarPatterns> {
+        let target = pattern_id.checked_sub(1)?;
+        let vector = index.vector(target)?;
+
+        let similar = index
+            .search(vector, k + 1)
+            .into_iter()
+            .filter(|&(i, _)| i != target && i < groups.len())
+            .take(k)
+            .map(|(i, similarity)| similar_pattern(groups, i, similarity))
+            .collect();
+
+        Some(SimilarPatterns {
+            pattern_id,
+            pattern: groups[target].pattern.clone(),
+            similar,
+        })
+    }
+}
+
+fn similar_pattern(groups: &[LogGroup], index: usize, similarity: f32) -> SimilarPattern {
+    SimilarPattern {
+        pattern_id: index + 1,
+        pattern: groups[index].pattern.clone(),
+        count: groups[index].count,
+        similarity,
+    }
+}
+
+/// Index vectors are unit length, so this is the cosine similarity
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn find(parents: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    // Path compression
+    let mut node = i;
+    while parents[node] != root {
+        let next = parents[node];
+        parents[node] = root;
+        node = next;
+    }
+    root
+}
+
+/// Join two sets under the smaller index, so each root is its cluster's largest pattern
+fn union(parents: &mut [usize], a: usize, b: usize) {
+    let (a, b) = (find(parents, a), find(parents, b));
+    if a != b {
+        parents[a.max(b)] = a.min(b);
+    }
+}
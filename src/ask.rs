@@ -0,0 +1,299 @@
+use crate::backends::{EmbeddingBackend, ReasoningBackend};
+use crate::cache::EmbeddingCache;
+use crate::embedding::EmbeddingGenerator;
+use crate::models::{LogGroup, LogLevel};
+use crate::reasoning::{PromptBuilder, truncate};
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+/// Default number of patterns retrieved per question
+const DEFAULT_TOP_K: usize = 5;
+
+/// Default number of sample events shown per retrieved pattern
+const DEFAULT_SAMPLES: usize = 3;
+
+/// Default token budget for the retrieved context
+const DEFAULT_BUDGET: usize = 3000;
+
+/// Distinct lines per pattern considered when picking samples; they are
+/// spread evenly over the pattern so every part of the log is represented
+const MAX_CANDIDATES: usize = 20;
+
+/// Sample lines longer than this are truncated in the prompt
+const MAX_SAMPLE_CHARS: usize = 300;
+
+const SYSTEM_PROMPT: &str = "You are an experienced site reliability engineer answering questions about application logs. \
+Answer only from the log context provided, which lists log patterns as [#id] and sample lines as [line N]. \
+If the context doesn't answer the question, say so. Cite every pattern and line your answer relies on. \
+Reply with JSON of the form {\"answer\": \"...\", \"citations\": [{\"pattern_id\": 1, \"lines\": [12, 40]}]}.";
+
+/// A sample event shown to the model
+#[derive(Debug, Clone, Serialize)]
+pub struct SampleLine {
+    /// 1-based line number in the source file
+    pub line_number: usize,
+    pub raw: String,
+}
+
+/// A pattern retrieved as context for a question
+#[derive(Debug, Clone, Serialize)]
+pub struct RetrievedPattern {
+    /// Rank of the pattern (1-based, as in `analyze`)
+    pub pattern_id: usize,
+    pub pattern: String,
+    pub count: usize,
+    /// Cosine similarity to the question
+    pub similarity: f32,
+    pub samples: Vec<SampleLine>,
+}
+
+/// A pattern and the lines the answer relies on
+#[derive(Debug, Clone, Serialize)]
+pub struct Citation {
+    pub pattern_id: usize,
+    pub pattern: String,
+    pub line_numbers: Vec<usize>,
+}
+
+/// A grounded answer to a question about the logs
+#[derive(Debug, Clone, Serialize)]
+pub struct Answer {
+    pub question: String,
+    pub answer: String,
+    /// Citations the model gave, restricted to the context it was shown
+    pub citations: Vec<Citation>,
+    /// Everything that was retrieved as context
+    pub retrieved: Vec<RetrievedPattern>,
+}
+
+/// The JSON reply requested from the model
+#[derive(Deserialize)]
+struct AnswerReply {
+    answer: String,
+    #[serde(default)]
+    citations: Vec<CitationReply>,
+}
+
+#[derive(Deserialize)]
+struct CitationReply {
+    pattern_id: usize,
+    #[serde(default)]
+    lines: Vec<usize>,
+}
+
+/// Answers questions about grouped events with retrieval-augmented generation:
+/// the question is embedded, the most similar patterns and sample events are
+/// retrieved, and a reasoning backend answers from them
+pub struct QuestionAnswerer<E: EmbeddingBackend, R: ReasoningBackend> {
+    embeddings: EmbeddingGenerator<E>,
+    reasoner: R,
+    /// Patterns retrieved per question
+    top_k: usize,
+    /// Sample events per retrieved pattern
+    samples: usize,
+    /// Token budget for the retrieved context
+    budget: usize,
+}
+
+impl<E: EmbeddingBackend, R: ReasoningBackend> QuestionAnswerer<E, R> {
+    pub fn new(embedder: E, reasoner: R) -> Self {
+        Self {
+            embeddings: EmbeddingGenerator::new(embedder),
+            reasoner,
+            top_k: DEFAULT_TOP_K,
+            samples: DEFAULT_SAMPLES,
+            budget: DEFAULT_BUDGET,
+        }
+    }
+
+    pub fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k;
+        self
+    }
+
+    pub fn with_samples(mut self, samples: usize) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    pub fn with_budget(mut self, budget: usize) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Reuse pattern embeddings from earlier runs
+    pub fn with_cache(mut self, cache: EmbeddingCache) -> Self {
+        self.embeddings = self.embeddings.with_cache(cache);
+        self
+    }
+
+    /// Save the pattern index to a file and reuse it while the patterns are unchanged
+    pub fn with_index_file(mut self, path: PathBuf) -> Self {
+        self.embeddings = self.embeddings.with_index_file(path);
+        self
+    }
+
+    /// Whether to load a matching saved index (default) or always rebuild it
+    pub fn with_index_reuse(mut self, reuse: bool) -> Self {
+        self.embeddings = self.embeddings.with_index_reuse(reuse);
+        self
+    }
+
+    /// Answer a question about groups sorted by count
+    pub fn ask(&self, question: &str, groups: &[LogGroup]) -> Result<Answer> {
+        let retrieved = self.retrieve(question, groups)?;
+        if retrieved.is_empty() {
+            return Err(anyhow!("No log patterns to answer from"));
+        }
+
+        let prompt = self.build_prompt(question, groups, &retrieved);
+        let reply = self.reasoner.generate(SYSTEM_PROMPT, &prompt)?;
+        let reply = reply.trim();
+        if reply.is_empty() {
+            return Err(anyhow!("The model returned an empty reply"));
+        }
+
+        // A model that ignored the format still gets its plain-text answer used
+        let (answer, citations) = match serde_json::from_str::<AnswerReply>(reply) {
+            Ok(parsed) => (parsed.answer.trim().to_string(), Self::check_citations(parsed.citations, &retrieved)),
+            Err(_) => (reply.to_string(), Vec::new()),
+        };
+
+        Ok(Answer {
+            question: question.to_string(),
+            answer,
+            citations,
+            retrieved,
+        })
+    }
+
+    /// Find the patterns most similar to the question, then the events of
+    /// each pattern most similar to it
+    pub fn retrieve(&self, question: &str, groups: &[LogGroup]) -> Result<Vec<RetrievedPattern>> {
+        if groups.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let query = self
+            .embeddings
+            .embed_texts(&[question.to_string()])?
+            .pop()
+            .ok_or_else(|| anyhow!("No embedding returned for the question"))?;
+
+        let ranked = self.embeddings.index_groups(groups)?.search(&query, self.top_k);
+
+        let mut retrieved = Vec::new();
+        for (index, similarity) in ranked {
+            let group = &groups[index];
+            retrieved.push(RetrievedPattern {
+                pattern_id: index + 1,
+                pattern: group.pattern.clone(),
+                count: group.count,
+                similarity,
+                samples: self.select_samples(&query, group)?,
+            });
+        }
+
+        Ok(retrieved)
+    }
+
+    /// Rank distinct candidate lines of a group by similarity to the query
+    fn select_samples(&self, query: &[f32], group: &LogGroup) -> Result<Vec<SampleLine>> {
+        let step = group.events.len().div_ceil(MAX_CANDIDATES).max(1);
+        let mut seen = HashSet::new();
+        let candidates: Vec<SampleLine> = group
+            .events
+            .iter()
+            .step_by(step)
+            .filter(|e| seen.insert(e.raw.as_str()))
+            .map(|e| SampleLine {
+                line_number: e.line_number,
+                raw: e.raw.clone(),
+            })
+            .collect();
+
+        let texts: Vec<String> = candidates.iter().map(|c| c.raw.clone()).collect();
+        let embeddings = self.embeddings.embed_texts(&texts)?;
+
+        let mut scored: Vec<(f32, SampleLine)> = embeddings
+            .iter()
+            .map(|e| EmbeddingGenerator::<E>::cosine_similarity(query, e))
+            .zip(candidates)
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mut samples: Vec<SampleLine> = scored.into_iter().take(self.samples).map(|(_, s)| s).collect();
+        samples.sort_by_key(|s| s.line_number);
+        Ok(samples)
+    }
+
+    /// Context in order of similarity, so the least relevant patterns are
+    /// the ones left out when the budget runs out
+    fn build_prompt(&self, question: &str, groups: &[LogGroup], retrieved: &[RetrievedPattern]) -> String {
+        let mut context = PromptBuilder::new(self.budget);
+
+        for pattern in retrieved {
+            let group = &groups[pattern.pattern_id - 1];
+            let window = group
+                .time_window
+                .map(|(first, last)| {
+                    format!(", {} to {}", first.format("%Y-%m-%d %H:%M:%S"), last.format("%Y-%m-%d %H:%M:%S"))
+                })
+                .unwrap_or_default();
+            let title = format!(
+                "[#{}] {} ({} events, {:?}{})",
+                pattern.pattern_id,
+                truncate(&pattern.pattern, MAX_SAMPLE_CHARS),
+                pattern.count,
+                group.dominant_level.as_ref().unwrap_or(&LogLevel::Info),
+                window
+            );
+
+            context.section(
+                &title,
+                pattern
+                    .samples
+                    .iter()
+                    .map(|s| format!("[line {}] {}", s.line_number, truncate(&s.raw, MAX_SAMPLE_CHARS))),
+            );
+        }
+
+        format!("Log context:\n\n{}\n\nQuestion: {}", context.build(), question)
+    }
+
+    /// Keep only citations of patterns and lines that were in the context
+    fn check_citations(citations: Vec<CitationReply>, retrieved: &[RetrievedPattern]) -> Vec<Citation> {
+        let by_id: HashMap<usize, &RetrievedPattern> = retrieved.iter().map(|p| (p.pattern_id, p)).collect();
+
+        let mut checked: Vec<Citation> = Vec::new();
+        for citation in citations {
+            let Some(pattern) = by_id.get(&citation.pattern_id) else {
+                continue;
+            };
+            let mut line_numbers: Vec<usize> = citation
+                .lines
+                .into_iter()
+                .filter(|n| pattern.samples.iter().any(|s| s.line_number == *n))
+                .collect();
+            line_numbers.sort_unstable();
+            line_numbers.dedup();
+
+            match checked.iter_mut().find(|c| c.pattern_id == citation.pattern_id) {
+                Some(existing) => {
+                    existing.line_numbers.extend(line_numbers);
+                    existing.line_numbers.sort_unstable();
+                    existing.line_numbers.dedup();
+                }
+                None => checked.push(Citation {
+                    pattern_id: pattern.pattern_id,
+                    pattern: pattern.pattern.clone(),
+                    line_numbers,
+                }),
+            }
+        }
+
+        checked
+    }
+}
@@ -0,0 +1,226 @@
+use super::{Backend, EmbeddingBackend, ReasoningBackend};
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Recorded model traffic: embeddings by input text and completions by
+/// system prompt and prompt. Stored as pretty-printed JSON so fixtures can be
+/// reviewed and diffed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Fixture {
+    /// Model id of the recorded backend, e.g. "ollama:nomic-embed-text"
+    #[serde(default)]
+    pub model_id: String,
+    #[serde(default)]
+    pub embeddings: BTreeMap<String, Vec<f32>>,
+    #[serde(default)]
+    pub completions: Vec<Completion>,
+}
+
+/// One recorded chat completion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Completion {
+    pub system: String,
+    pub prompt: String,
+    pub reply: String,
+}
+
+impl Fixture {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read fixture: {}", path.display()))?;
+
+        serde_json::from_str(&text).with_context(|| format!("Invalid fixture: {}", path.display()))
+    }
+
+    /// Load the fixture if the file exists, or start an empty one
+    fn load_or_default(path: &Path) -> Result<Self> {
+        if path.exists() { Self::load(path) } else { Ok(Self::default()) }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize fixture")?;
+
+        // Write to a temporary file first so an interrupted save can't leave a truncated fixture
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, json + "\n").with_context(|| format!("Failed to write fixture: {}", tmp.display()))?;
+        std::fs::rename(&tmp, path).with_context(|| format!("Failed to write fixture: {}", path.display()))
+    }
+
+    /// Add a completion, replacing an earlier one for the same prompts
+    fn add_completion(&mut self, completion: Completion) {
+        self.completions.retain(|c| c.system != completion.system || c.prompt != completion.prompt);
+        self.completions.push(completion);
+    }
+
+    fn completion(&self, system: &str, prompt: &str) -> Option<&Completion> {
+        self.completions.iter().find(|c| c.system == system && c.prompt == prompt)
+    }
+}
+
+/// Wraps a real backend and records every embedding and completion to a
+/// fixture file, adding to what the file already holds. Requests are
+/// buffered and written out once, when the backend is dropped; the file is
+/// re-read then, so recorders that ran in the meantime aren't overwritten.
+pub struct RecordingBackend {
+    inner: Box<Backend>,
+    path: PathBuf,
+    /// Traffic not yet written to the fixture file
+    pending: Mutex<Fixture>,
+}
+
+impl RecordingBackend {
+    pub fn new(inner: Backend, path: PathBuf) -> Result<Self> {
+        // Fail early on a fixture that can't be added to
+        Fixture::load_or_default(&path)?;
+
+        Ok(Self {
+            inner: Box::new(inner),
+            path,
+            pending: Mutex::new(Fixture::default()),
+        })
+    }
+
+    pub fn inner(&self) -> &Backend {
+        &self.inner
+    }
+
+    /// Add the recorded traffic to the fixture file
+    pub fn flush(&self) -> Result<()> {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        if pending.embeddings.is_empty() && pending.completions.is_empty() {
+            return Ok(());
+        }
+
+        let mut fixture = Fixture::load_or_default(&self.path)?;
+        fixture.model_id = self.inner.model_id();
+        fixture.embeddings.extend(pending.embeddings);
+        for completion in pending.completions {
+            fixture.add_completion(completion);
+        }
+        fixture.save(&self.path)
+    }
+}
+
+impl Drop for RecordingBackend {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            eprintln!("⚠ Warning: Recorded traffic was not saved: {:#}", e);
+        }
+    }
+}
+
+impl EmbeddingBackend for RecordingBackend {
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let embeddings = self.inner.embed(texts)?;
+
+        let mut pending = self.pending.lock().unwrap();
+        for (text, embedding) in texts.iter().zip(&embeddings) {
+            pending.embeddings.insert(text.clone(), embedding.clone());
+        }
+
+        Ok(embeddings)
+    }
+
+    fn model_id(&self) -> String {
+        self.inner.model_id()
+    }
+
+    fn batch_size(&self) -> usize {
+        self.inner.batch_size()
+    }
+}
+
+impl ReasoningBackend for RecordingBackend {
+    fn generate(&self, system: &str, prompt: &str) -> Result<String> {
+        let reply = self.inner.generate(system, prompt)?;
+
+        self.pending.lock().unwrap().add_completion(Completion {
+            system: system.to_string(),
+            prompt: prompt.to_string(),
+            reply: reply.clone(),
+        });
+
+        Ok(reply)
+    }
+}
+
+/// Serves embeddings and completions from a fixture file, without a model
+/// server. Anything that wasn't recorded is an error.
+pub struct ReplayBackend {
+    path: PathBuf,
+    fixture: Fixture,
+}
+
+impl ReplayBackend {
+    pub fn new(path: PathBuf) -> Result<Self> {
+        let fixture = Fixture::load(&path)?;
+        Ok(Self { path, fixture })
+    }
+}
+
+impl EmbeddingBackend for ReplayBackend {
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        texts
+            .iter()
+            .map(|text| {
+                self.fixture.embeddings.get(text).cloned().ok_or_else(|| {
+                    anyhow!("No recorded embedding for '{}' in {}; record it again with --record", text, self.path.display())
+                })
+            })
+            .collect()
+    }
+
+    fn model_id(&self) -> String {
+        self.fixture.model_id.clone()
+    }
+}
+
+impl ReasoningBackend for ReplayBackend {
+    fn generate(&self, system: &str, prompt: &str) -> Result<String> {
+        self.fixture
+            .completion(system, prompt)
+            .map(|c| c.reply.clone())
+            .ok_or_else(|| anyhow!("No recorded completion for this prompt in {}; record it again with --record", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::mock::MockBackend;
+
+    fn recorder(path: &Path) -> RecordingBackend {
+        RecordingBackend::new(Backend::Mock(MockBackend::new()), path.to_path_buf()).unwrap()
+    }
+
+    #[test]
+    fn writes_recorded_traffic_once_when_dropped() {
+        let dir = std::env::temp_dir().join(format!("log-reasoner-fixture-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("fixture.json");
+
+        let first = recorder(&path);
+        first.embed(&["db timeout".to_string()]).unwrap();
+        first.generate("system", "explain").unwrap();
+        assert!(!path.exists());
+
+        // A recorder that finishes in the meantime is added to, not overwritten
+        let second = recorder(&path);
+        second.embed(&["pool exhausted".to_string()]).unwrap();
+        drop(second);
+        drop(first);
+
+        let fixture = Fixture::load(&path).unwrap();
+        assert_eq!(fixture.model_id, "mock");
+        assert_eq!(fixture.embeddings.keys().collect::<Vec<_>>(), ["db timeout", "pool exhausted"]);
+        assert_eq!(fixture.completions.len(), 1);
+        assert!(!path.with_extension("tmp").exists());
+
+        let replay = ReplayBackend::new(path).unwrap();
+        assert!(replay.generate("system", "explain").is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,170 @@
+use super::EmbeddingBackend;
+use crate::cache::fnv1a;
+use anyhow::{Context, Result, anyhow};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Length of the hashed n-gram vector
+const DIMENSIONS: usize = 256;
+
+/// Character n-gram lengths, taken over each word padded with `<` and `>`
+const MIN_NGRAM: usize = 3;
+const MAX_NGRAM: usize = 5;
+
+/// Placeholder left by normalization; it says nothing about the pattern
+const PLACEHOLDER: &str = "VAR";
+
+/// Embeds text locally, without a model server: words and their character
+/// n-grams are hashed into a fixed-size vector with sublinear term
+/// frequencies (`1 + ln tf`). Similar spellings share n-grams, so
+/// "connection refused" and "connect refused" end up close. Optionally a
+/// word-vector file adds word meanings beyond spelling.
+pub struct HashedBackend {
+    /// Word vectors from a local file, keyed by lowercase word
+    word_vectors: Option<WordVectors>,
+}
+
+struct WordVectors {
+    /// File name, part of the model id
+    name: String,
+    dimensions: usize,
+    vectors: HashMap<String, Vec<f32>>,
+}
+
+impl HashedBackend {
+    pub fn new() -> Self {
+        Self { word_vectors: None }
+    }
+
+    /// Load word vectors in the GloVe / word2vec text format: a word followed
+    /// by its values on each line, with an optional "count dimensions" header
+    pub fn with_word_vectors(mut self, path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read word vectors: {}", path.display()))?;
+
+        let mut dimensions = 0;
+        let mut vectors = HashMap::new();
+        for (i, line) in text.lines().enumerate() {
+            let mut fields = line.split_whitespace();
+            let Some(word) = fields.next() else {
+                continue;
+            };
+            let values: Vec<f32> = match fields.map(str::parse).collect() {
+                Ok(values) => values,
+                Err(_) => return Err(anyhow!("Invalid word vector on line {} of {}", i + 1, path.display())),
+            };
+
+            // word2vec files start with the vocabulary size and dimensions
+            if i == 0 && values.len() == 1 && word.parse::<usize>().is_ok() {
+                continue;
+            }
+            if dimensions == 0 {
+                dimensions = values.len();
+            }
+            if values.len() != dimensions {
+                return Err(anyhow!(
+                    "Word vector on line {} of {} has {} values, expected {}",
+                    i + 1,
+                    path.display(),
+                    values.len(),
+                    dimensions
+                ));
+            }
+            vectors.insert(word.to_lowercase(), values);
+        }
+
+        if vectors.is_empty() {
+            return Err(anyhow!("No word vectors in {}", path.display()));
+        }
+
+        self.word_vectors = Some(WordVectors {
+            name: path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+            dimensions,
+            vectors,
+        });
+        Ok(self)
+    }
+
+    /// Lowercase alphanumeric words, without normalization placeholders
+    fn words(text: &str) -> Vec<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty() && *w != PLACEHOLDER)
+            .map(str::to_lowercase)
+            .collect()
+    }
+
+    fn ngram_vector(words: &[String]) -> Vec<f32> {
+        let mut counts: HashMap<u64, f32> = HashMap::new();
+        for word in words {
+            *counts.entry(fnv1a(format!("w:{}", word).as_bytes())).or_default() += 1.0;
+
+            let padded: Vec<char> = format!("<{}>", word).chars().collect();
+            for n in MIN_NGRAM..=MAX_NGRAM {
+                for ngram in padded.windows(n) {
+                    let ngram: String = ngram.iter().collect();
+                    *counts.entry(fnv1a(format!("n:{}", ngram).as_bytes())).or_default() += 1.0;
+                }
+            }
+        }
+
+        // The top bit of the hash picks the sign, so collisions tend to cancel out
+        let mut vector = vec![0.0; DIMENSIONS];
+        for (hash, count) in counts {
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[(hash % DIMENSIONS as u64) as usize] += sign * (1.0 + count.ln());
+        }
+        normalize(&mut vector);
+        vector
+    }
+
+    /// Mean of the known words' vectors
+    fn word_vector(word_vectors: &WordVectors, words: &[String]) -> Vec<f32> {
+        let mut vector = vec![0.0; word_vectors.dimensions];
+        for known in words.iter().filter_map(|w| word_vectors.vectors.get(w)) {
+            for (sum, value) in vector.iter_mut().zip(known) {
+                *sum += value;
+            }
+        }
+        normalize(&mut vector);
+        vector
+    }
+}
+
+impl EmbeddingBackend for HashedBackend {
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        Ok(texts
+            .iter()
+            .map(|text| {
+                let words = Self::words(text);
+                let mut vector = Self::ngram_vector(&words);
+
+                // Both halves are unit length, so the cosine similarity is
+                // the mean of the spelling and the word-meaning similarity
+                if let Some(ref word_vectors) = self.word_vectors {
+                    vector.extend(Self::word_vector(word_vectors, &words));
+                }
+                vector
+            })
+            .collect())
+    }
+
+    fn model_id(&self) -> String {
+        match self.word_vectors {
+            Some(ref word_vectors) => format!("hashed:ngram-{}+{}", DIMENSIONS, word_vectors.name),
+            None => format!("hashed:ngram-{}", DIMENSIONS),
+        }
+    }
+
+    /// Embedding is cheap and local, so everything goes in one batch
+    fn batch_size(&self) -> usize {
+        usize::MAX
+    }
+}
+
+/// Scale a vector to unit length, leaving all-zero vectors alone
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
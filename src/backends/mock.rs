@@ -0,0 +1,48 @@
+use super::hashed::HashedBackend;
+use super::{EmbeddingBackend, ReasoningBackend};
+use anyhow::Result;
+
+/// Canned reply with the fields every caller asks for, so explanations,
+/// incident reports and answers all parse it
+const REPLY: &str = r#"{
+  "cause": "Mock explanation: no model was asked.",
+  "next_steps": ["Run with a real backend for actual analysis"],
+  "summary": "Mock incident summary: no model was asked.",
+  "impact": "Unknown (mock backend).",
+  "trigger": "Unknown (mock backend).",
+  "timeline": [],
+  "answer": "Mock answer: no model was asked.",
+  "citations": []
+}"#;
+
+/// Deterministic stand-in for a model server: hashed n-gram embeddings and a
+/// fixed completion, for exercising the AI features without a network
+pub struct MockBackend {
+    embedder: HashedBackend,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self { embedder: HashedBackend::new() }
+    }
+}
+
+impl EmbeddingBackend for MockBackend {
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.embedder.embed(texts)
+    }
+
+    fn model_id(&self) -> String {
+        "mock".to_string()
+    }
+
+    fn batch_size(&self) -> usize {
+        self.embedder.batch_size()
+    }
+}
+
+impl ReasoningBackend for MockBackend {
+    fn generate(&self, _system: &str, _prompt: &str) -> Result<String> {
+        Ok(REPLY.to_string())
+    }
+}
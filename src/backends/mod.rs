@@ -1,9 +1,335 @@
+pub mod fixture;
+pub mod hashed;
+pub mod mock;
 pub mod ollama;
+pub mod openai;
 
-use anyhow::Result;
+use crate::config::{BackendConfig, OpenAiConfig};
+use anyhow::{Result, anyhow};
+use fixture::{RecordingBackend, ReplayBackend};
+use hashed::HashedBackend;
+use mock::MockBackend;
+use ollama::OllamaBackend;
+use openai::OpenAiCompatibleBackend;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 
-/// Trait for AI backends (embedding + reasoning)
+/// Names accepted by `--backend`
+pub const BACKEND_NAMES: [&str; 6] = ["none", "ollama", "openai", "hashed", "mock", "replay"];
+
+/// Backend used when none is configured
+pub const DEFAULT_BACKEND: &str = "ollama";
+
+/// Texts sent per embedding call
+pub const DEFAULT_BATCH_SIZE: usize = 64;
+
+/// Parallel requests for backends that embed one text per request
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Attempts per request, including the first
+const MAX_ATTEMPTS: u32 = 4;
+
+/// Delay before the first retry; doubled after every attempt
+const INITIAL_BACKOFF_MS: u64 = 500;
+
+/// Trait for embedding backends
 pub trait EmbeddingBackend {
-    /// Generate embeddings for a batch of texts
+    /// Generate embeddings for a batch of texts, in input order
     fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
-}
\ No newline at end of file
+
+    /// Backend and embedding model, e.g. "ollama:nomic-embed-text"; embeddings
+    /// are only cached and compared under the same id
+    fn model_id(&self) -> String;
+
+    /// Number of texts to pass to `embed` at once
+    fn batch_size(&self) -> usize {
+        DEFAULT_BATCH_SIZE
+    }
+}
+
+/// Trait for reasoning backends (text generation with an LLM)
+pub trait ReasoningBackend {
+    /// Answer a prompt, following the system instructions
+    fn generate(&self, system: &str, prompt: &str) -> Result<String>;
+}
+
+/// A borrowed backend, so one backend can serve several consumers
+impl<B: EmbeddingBackend + ?Sized> EmbeddingBackend for &B {
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        (**self).embed(texts)
+    }
+
+    fn model_id(&self) -> String {
+        (**self).model_id()
+    }
+
+    fn batch_size(&self) -> usize {
+        (**self).batch_size()
+    }
+}
+
+impl<B: ReasoningBackend + ?Sized> ReasoningBackend for &B {
+    fn generate(&self, system: &str, prompt: &str) -> Result<String> {
+        (**self).generate(system, prompt)
+    }
+}
+
+/// The model server selected with `--backend`
+pub enum Backend {
+    Ollama(OllamaBackend),
+    OpenAi(OpenAiCompatibleBackend),
+    /// Offline embeddings only; no reasoning
+    Hashed(HashedBackend),
+    /// Deterministic embeddings and canned completions
+    Mock(MockBackend),
+    /// Embeddings and completions from a fixture file
+    Replay(ReplayBackend),
+    /// Another backend whose traffic is written to a fixture file
+    Record(RecordingBackend),
+}
+
+impl Backend {
+    /// Check that the server is up and serves the embedding model
+    pub fn check_embedding(&self) -> Result<()> {
+        match self {
+            Backend::Ollama(backend) => backend.check_available(),
+            Backend::OpenAi(backend) => backend.check_available(),
+            Backend::Hashed(_) | Backend::Mock(_) | Backend::Replay(_) => Ok(()),
+            Backend::Record(backend) => backend.inner().check_embedding(),
+        }
+    }
+
+    /// Check that the server is up and serves the reasoning model
+    pub fn check_reasoning(&self) -> Result<()> {
+        match self {
+            Backend::Ollama(backend) => backend.check_llm_available(),
+            Backend::OpenAi(backend) => backend.check_available(),
+            Backend::Hashed(_) => Err(anyhow!("The hashed backend only computes embeddings; use ollama or openai")),
+            Backend::Mock(_) | Backend::Replay(_) => Ok(()),
+            Backend::Record(backend) => backend.inner().check_reasoning(),
+        }
+    }
+
+    /// Name for progress messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Ollama(_) => "Ollama",
+            Backend::OpenAi(_) => "OpenAI-compatible server",
+            Backend::Hashed(_) => "Hashed n-gram embedding",
+            Backend::Mock(_) => "Mock backend",
+            Backend::Replay(_) => "Fixture replay",
+            Backend::Record(backend) => backend.inner().name(),
+        }
+    }
+
+    /// Whether embeddings are worth caching: they come from a model server
+    /// and aren't being recorded (a cache hit would be missing from the fixture)
+    pub fn is_cacheable(&self) -> bool {
+        matches!(self, Backend::Ollama(_) | Backend::OpenAi(_))
+    }
+
+    /// Whether the backend's traffic is being written to a fixture file
+    pub fn is_recording(&self) -> bool {
+        matches!(self, Backend::Record(_))
+    }
+
+    /// Build the configured backend, or None for `none`. With `record` set,
+    /// the backend's traffic is written to the fixture file.
+    pub fn from_config(config: &BackendConfig, openai: &OpenAiConfig) -> Result<Option<Self>> {
+        let Some(backend) = Self::build(config, openai)? else {
+            return Ok(None);
+        };
+
+        if config.record != Some(true) {
+            return Ok(Some(backend));
+        }
+        if matches!(backend, Backend::Replay(_)) {
+            return Err(anyhow!("Can't record the replay backend"));
+        }
+        let path = config.fixture.as_ref().ok_or_else(|| anyhow!("Recording needs a fixture file (--fixture)"))?;
+        Ok(Some(Backend::Record(RecordingBackend::new(backend, PathBuf::from(path))?)))
+    }
+
+    fn build(config: &BackendConfig, openai: &OpenAiConfig) -> Result<Option<Self>> {
+        let name = config.name.as_deref().unwrap_or(DEFAULT_BACKEND);
+        let timeout = config.timeout_secs.map(Duration::from_secs);
+
+        let backend = match name.to_lowercase().as_str() {
+            "none" => return Ok(None),
+            "ollama" => {
+                let mut backend = OllamaBackend::new();
+                if let Some(ref url) = config.url {
+                    backend = backend.with_url(url.clone());
+                }
+                if let Some(ref model) = config.embed_model {
+                    backend = backend.with_model(model.clone());
+                }
+                if let Some(ref model) = config.llm_model {
+                    backend = backend.with_llm_model(model.clone());
+                }
+                if let Some(timeout) = timeout {
+                    backend = backend.with_timeout(timeout);
+                }
+                if let Some(batch_size) = config.batch_size {
+                    backend = backend.with_batch_size(batch_size);
+                }
+                if let Some(concurrency) = config.concurrency {
+                    backend = backend.with_concurrency(concurrency);
+                }
+                Backend::Ollama(backend)
+            }
+            "openai" => {
+                let mut backend = OpenAiCompatibleBackend::new().with_config(openai);
+                if let Some(ref url) = config.url {
+                    backend = backend.with_url(url.clone());
+                }
+                if let Some(ref model) = config.embed_model {
+                    backend = backend.with_model(model.clone());
+                }
+                if let Some(ref model) = config.llm_model {
+                    backend = backend.with_llm_model(model.clone());
+                }
+                if let Some(timeout) = timeout {
+                    backend = backend.with_timeout(timeout);
+                }
+                if let Some(batch_size) = config.batch_size {
+                    backend = backend.with_batch_size(batch_size);
+                }
+                Backend::OpenAi(backend)
+            }
+            "hashed" => {
+                let mut backend = HashedBackend::new();
+                if let Some(ref path) = config.vectors {
+                    backend = backend.with_word_vectors(std::path::Path::new(path))?;
+                }
+                Backend::Hashed(backend)
+            }
+            "mock" => Backend::Mock(MockBackend::new()),
+            "replay" => {
+                let path = config.fixture.as_ref().ok_or_else(|| anyhow!("The replay backend needs a fixture file (--fixture)"))?;
+                Backend::Replay(ReplayBackend::new(PathBuf::from(path))?)
+            }
+            _ => return Err(anyhow!("Unknown backend '{}'. Available: {}", name, BACKEND_NAMES.join(", "))),
+        };
+
+        Ok(Some(backend))
+    }
+}
+
+impl EmbeddingBackend for Backend {
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        match self {
+            Backend::Ollama(backend) => backend.embed(texts),
+            Backend::OpenAi(backend) => backend.embed(texts),
+            Backend::Hashed(backend) => backend.embed(texts),
+            Backend::Mock(backend) => backend.embed(texts),
+            Backend::Replay(backend) => backend.embed(texts),
+            Backend::Record(backend) => backend.embed(texts),
+        }
+    }
+
+    fn model_id(&self) -> String {
+        match self {
+            Backend::Ollama(backend) => backend.model_id(),
+            Backend::OpenAi(backend) => backend.model_id(),
+            Backend::Hashed(backend) => backend.model_id(),
+            Backend::Mock(backend) => backend.model_id(),
+            Backend::Replay(backend) => backend.model_id(),
+            Backend::Record(backend) => backend.model_id(),
+        }
+    }
+
+    fn batch_size(&self) -> usize {
+        match self {
+            Backend::Ollama(backend) => backend.batch_size(),
+            Backend::OpenAi(backend) => backend.batch_size(),
+            Backend::Hashed(backend) => backend.batch_size(),
+            Backend::Mock(backend) => backend.batch_size(),
+            Backend::Replay(backend) => backend.batch_size(),
+            Backend::Record(backend) => backend.batch_size(),
+        }
+    }
+}
+
+impl ReasoningBackend for Backend {
+    fn generate(&self, system: &str, prompt: &str) -> Result<String> {
+        match self {
+            Backend::Ollama(backend) => backend.generate(system, prompt),
+            Backend::OpenAi(backend) => backend.generate(system, prompt),
+            Backend::Hashed(_) => Err(anyhow!("The hashed backend can't generate text")),
+            Backend::Mock(backend) => backend.generate(system, prompt),
+            Backend::Replay(backend) => backend.generate(system, prompt),
+            Backend::Record(backend) => backend.generate(system, prompt),
+        }
+    }
+}
+
+/// Send a request, retrying connection errors, timeouts, 429 and 5xx
+/// responses with exponential backoff. The last response is returned as is.
+pub(crate) fn send_with_retry(
+    what: &str,
+    request: impl Fn() -> reqwest::blocking::RequestBuilder,
+) -> reqwest::Result<reqwest::blocking::Response> {
+    let mut backoff = Duration::from_millis(INITIAL_BACKOFF_MS);
+    let mut attempt = 1;
+
+    loop {
+        let result = request().send();
+        let failure = match result {
+            Ok(ref response)
+                if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS || response.status().is_server_error() =>
+            {
+                response.status().to_string()
+            }
+            Err(ref e) if e.is_timeout() || e.is_connect() => e.to_string(),
+            _ => return result,
+        };
+        if attempt == MAX_ATTEMPTS {
+            return result;
+        }
+
+        eprintln!("⚠ Warning: {} request failed ({}), retrying in {:.1?}", what, failure, backoff);
+        std::thread::sleep(backoff);
+        backoff *= 2;
+        attempt += 1;
+    }
+}
+
+/// Embed texts one request at a time on up to `concurrency` threads,
+/// returning the embeddings in input order. Stops at the first error.
+pub(crate) fn embed_concurrently(
+    texts: &[String],
+    concurrency: usize,
+    embed_one: impl Fn(&str) -> Result<Vec<f32>> + Sync,
+) -> Result<Vec<Vec<f32>>> {
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Vec<f32>>>> = Mutex::new(vec![None; texts.len()]);
+    let failure: Mutex<Option<anyhow::Error>> = Mutex::new(None);
+
+    std::thread::scope(|scope| {
+        for _ in 0..concurrency.clamp(1, texts.len().max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= texts.len() || failure.lock().unwrap().is_some() {
+                        break;
+                    }
+                    match embed_one(&texts[index]) {
+                        Ok(embedding) => results.lock().unwrap()[index] = Some(embedding),
+                        Err(e) => {
+                            failure.lock().unwrap().get_or_insert(e);
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    if let Some(e) = failure.into_inner().unwrap() {
+        return Err(e);
+    }
+    Ok(results.into_inner().unwrap().into_iter().flatten().collect())
+}
@@ -1,13 +1,31 @@
-use super::EmbeddingBackend;
+use super::{DEFAULT_BATCH_SIZE, DEFAULT_CONCURRENCY, EmbeddingBackend, ReasoningBackend, embed_concurrently, send_with_retry};
 use anyhow::{Context, Result, anyhow};
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
 const DEFAULT_MODEL: &str = "nomic-embed-text";
+const DEFAULT_LLM_MODEL: &str = "llama3.2";
+
+/// Generation can take minutes on a CPU, so requests get a generous timeout
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
+
+/// Availability checks give up quickly so runs aren't held up without a server
+const CHECK_TIMEOUT_SECS: u64 = 5;
 
 pub struct OllamaBackend {
     base_url: String,
     model: String,
+    /// Model used for reasoning (text generation)
+    llm_model: String,
+    timeout: Duration,
+    /// Texts per `/api/embed` request
+    batch_size: usize,
+    /// Parallel requests to the legacy `/api/embeddings` endpoint
+    concurrency: usize,
+    /// Set once the server turns out to predate `/api/embed`
+    legacy_embeddings: AtomicBool,
     client: reqwest::blocking::Client,
 }
 
@@ -16,22 +34,61 @@ impl OllamaBackend {
         Self {
             base_url: DEFAULT_OLLAMA_URL.to_string(),
             model: DEFAULT_MODEL.to_string(),
+            llm_model: DEFAULT_LLM_MODEL.to_string(),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            batch_size: DEFAULT_BATCH_SIZE,
+            concurrency: DEFAULT_CONCURRENCY,
+            legacy_embeddings: AtomicBool::new(false),
             client: reqwest::blocking::Client::new(),
         }
     }
 
-    #[allow(dead_code)]
+    pub fn with_url(mut self, url: String) -> Self {
+        self.base_url = url.trim_end_matches('/').to_string();
+        self
+    }
+
     pub fn with_model(mut self, model: String) -> Self {
         self.model = model;
         self
     }
 
+    pub fn with_llm_model(mut self, model: String) -> Self {
+        self.llm_model = model;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
     /// Check if Ollama is running and model is available
     pub fn check_available(&self) -> Result<()> {
+        self.check_model(&self.model)
+    }
+
+    /// Check if Ollama is running and the reasoning model is available
+    pub fn check_llm_available(&self) -> Result<()> {
+        self.check_model(&self.llm_model)
+    }
+
+    fn check_model(&self, model: &str) -> Result<()> {
         let url = format!("{}/api/tags", self.base_url);
         
         let response = self.client
             .get(&url)
+            .timeout(Duration::from_secs(CHECK_TIMEOUT_SECS))
             .send()
             .context("Failed to connect to Ollama. Is it running? Start with: ollama serve")?;
 
@@ -42,13 +99,13 @@ impl OllamaBackend {
         let models: ModelsResponse = response.json()
             .context("Failed to parse Ollama models response")?;
 
-        let model_exists = models.models.iter().any(|m| m.name.starts_with(&self.model));
+        let model_exists = models.models.iter().any(|m| m.name.starts_with(model));
         
         if !model_exists {
             return Err(anyhow!(
                 "Model '{}' not found. Pull it with: ollama pull {}",
-                self.model,
-                self.model
+                model,
+                model
             ));
         }
 
@@ -56,35 +113,119 @@ impl OllamaBackend {
     }
 }
 
+impl OllamaBackend {
+    /// Embed a batch with one `/api/embed` request. Returns None if the
+    /// server doesn't have that endpoint (Ollama before 0.3).
+    fn embed_batch(&self, texts: &[String]) -> Result<Option<Vec<Vec<f32>>>> {
+        let url = format!("{}/api/embed", self.base_url);
+        let request = BatchEmbedRequest {
+            model: self.model.clone(),
+            input: texts.to_vec(),
+        };
+
+        let response = send_with_retry("Ollama embedding", || self.client.post(&url).timeout(self.timeout).json(&request))
+            .context("Failed to send embedding request to Ollama")?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!("Ollama embedding request failed: {}", response.status()));
+        }
+
+        let embed_response: BatchEmbedResponse = response.json()
+            .context("Failed to parse Ollama embedding response")?;
+
+        if embed_response.embeddings.len() != texts.len() {
+            return Err(anyhow!(
+                "Expected {} embeddings, Ollama returned {}",
+                texts.len(),
+                embed_response.embeddings.len()
+            ));
+        }
+
+        Ok(Some(embed_response.embeddings))
+    }
+
+    /// Embed a single text with the legacy `/api/embeddings` endpoint
+    fn embed_one(&self, text: &str) -> Result<Vec<f32>> {
+        let url = format!("{}/api/embeddings", self.base_url);
+        let request = EmbedRequest {
+            model: self.model.clone(),
+            prompt: text.to_string(),
+        };
+
+        let response = send_with_retry("Ollama embedding", || self.client.post(&url).timeout(self.timeout).json(&request))
+            .context("Failed to send embedding request to Ollama")?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Ollama embedding request failed: {}", response.status()));
+        }
+
+        let embed_response: EmbedResponse = response.json()
+            .context("Failed to parse Ollama embedding response")?;
+
+        Ok(embed_response.embedding)
+    }
+}
+
 impl EmbeddingBackend for OllamaBackend {
     fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
-        let url = format!("{}/api/embeddings", self.base_url);
-        
-        let mut embeddings = Vec::new();
-
-        for text in texts {
-            let request = EmbedRequest {
-                model: self.model.clone(),
-                prompt: text.clone(),
-            };
-
-            let response = self.client
-                .post(&url)
-                .json(&request)
-                .send()
-                .context("Failed to send embedding request to Ollama")?;
-
-            if !response.status().is_success() {
-                return Err(anyhow!("Ollama embedding request failed: {}", response.status()));
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        if !self.legacy_embeddings.load(Ordering::Relaxed) {
+            match self.embed_batch(texts)? {
+                Some(embeddings) => return Ok(embeddings),
+                None => {
+                    eprintln!("⚠ Warning: Ollama has no /api/embed, falling back to one request per pattern (upgrade Ollama for batching)");
+                    self.legacy_embeddings.store(true, Ordering::Relaxed);
+                }
             }
+        }
 
-            let embed_response: EmbedResponse = response.json()
-                .context("Failed to parse Ollama embedding response")?;
+        embed_concurrently(texts, self.concurrency, |text| self.embed_one(text))
+    }
+
+    fn model_id(&self) -> String {
+        format!("ollama:{}", self.model)
+    }
+
+    fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+}
+
+impl ReasoningBackend for OllamaBackend {
+    fn generate(&self, system: &str, prompt: &str) -> Result<String> {
+        let url = format!("{}/api/chat", self.base_url);
+
+        let request = ChatRequest {
+            model: self.llm_model.clone(),
+            messages: vec![
+                ChatMessage { role: "system".to_string(), content: system.to_string() },
+                ChatMessage { role: "user".to_string(), content: prompt.to_string() },
+            ],
+            stream: false,
+            format: "json".to_string(),
+        };
 
-            embeddings.push(embed_response.embedding);
+        let response = self.client
+            .post(&url)
+            .timeout(self.timeout)
+            .json(&request)
+            .send()
+            .context("Failed to send chat request to Ollama")?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Ollama chat request failed: {}", response.status()));
         }
 
-        Ok(embeddings)
+        let chat_response: ChatResponse = response.json()
+            .context("Failed to parse Ollama chat response")?;
+
+        Ok(chat_response.message.content)
     }
 }
 
@@ -100,6 +241,37 @@ struct EmbedResponse {
     embedding: Vec<f32>,
 }
 
+#[derive(Serialize)]
+struct BatchEmbedRequest {
+    model: String,
+    input: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct BatchEmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+#[derive(Serialize)]
+struct ChatRequest {
+    model: String,
+    messages: Vec<ChatMessage>,
+    stream: bool,
+    /// Constrain the reply to valid JSON
+    format: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChatMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    message: ChatMessage,
+}
+
 #[derive(Deserialize)]
 struct ModelsResponse {
     models: Vec<ModelInfo>,
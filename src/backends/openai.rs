@@ -0,0 +1,402 @@
+use super::{DEFAULT_BATCH_SIZE, EmbeddingBackend, ReasoningBackend, send_with_retry};
+use crate::config::OpenAiConfig;
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const DEFAULT_URL: &str = "http://localhost:8080/v1";
+const DEFAULT_MODEL: &str = "nomic-embed-text";
+const DEFAULT_LLM_MODEL: &str = "llama3.2";
+const DEFAULT_API_KEY_ENV: &str = "OPENAI_API_KEY";
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
+const CHECK_TIMEOUT_SECS: u64 = 5;
+
+/// Backend for servers speaking the OpenAI API (`/v1/embeddings` and
+/// `/v1/chat/completions`): llama.cpp server, vLLM, LocalAI and OpenAI itself
+pub struct OpenAiCompatibleBackend {
+    base_url: String,
+    model: String,
+    /// Model used for reasoning (chat completions)
+    llm_model: String,
+    /// Sent as a bearer token when set
+    api_key: Option<String>,
+    /// Extra headers sent with every request
+    headers: Vec<(String, String)>,
+    timeout: Duration,
+    /// Texts per `/embeddings` request
+    batch_size: usize,
+    client: reqwest::blocking::Client,
+}
+
+impl OpenAiCompatibleBackend {
+    pub fn new() -> Self {
+        Self {
+            base_url: DEFAULT_URL.to_string(),
+            model: DEFAULT_MODEL.to_string(),
+            llm_model: DEFAULT_LLM_MODEL.to_string(),
+            api_key: None,
+            headers: Vec::new(),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            batch_size: DEFAULT_BATCH_SIZE,
+            client: reqwest::blocking::Client::new(),
+        }
+    }
+
+    /// Apply the `[openai]` config section, reading the API key from the
+    /// configured environment variable
+    pub fn with_config(self, config: &OpenAiConfig) -> Self {
+        let mut backend = self;
+        for (name, value) in &config.headers {
+            backend = backend.with_header(name.clone(), value.clone());
+        }
+
+        let key_env = config.api_key_env.as_deref().unwrap_or(DEFAULT_API_KEY_ENV);
+        backend.with_api_key(std::env::var(key_env).ok().filter(|key| !key.is_empty()))
+    }
+
+    pub fn with_url(mut self, url: String) -> Self {
+        self.base_url = url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_model(mut self, model: String) -> Self {
+        self.model = model;
+        self
+    }
+
+    pub fn with_llm_model(mut self, model: String) -> Self {
+        self.llm_model = model;
+        self
+    }
+
+    pub fn with_api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    pub fn with_header(mut self, name: String, value: String) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Check if the server is reachable and accepts our credentials
+    pub fn check_available(&self) -> Result<()> {
+        let url = format!("{}/models", self.base_url);
+
+        let response = self
+            .request(self.client.get(&url))
+            .timeout(Duration::from_secs(CHECK_TIMEOUT_SECS))
+            .send()
+            .with_context(|| format!("Failed to connect to the OpenAI-compatible server at {}", self.base_url))?;
+
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => Err(anyhow!(
+                "The server at {} rejected the API key ({})",
+                self.base_url,
+                response.status()
+            )),
+            status => Err(anyhow!("The server at {} returned error status {}", self.base_url, status)),
+        }
+    }
+
+    /// Add the timeout, API key and custom headers to a request
+    fn request(&self, builder: reqwest::blocking::RequestBuilder) -> reqwest::blocking::RequestBuilder {
+        let mut builder = builder.timeout(self.timeout);
+        if let Some(ref key) = self.api_key {
+            builder = builder.bearer_auth(key);
+        }
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        builder
+    }
+
+    /// Error for a failed request, including the server's message if any
+    fn error(what: &str, response: reqwest::blocking::Response) -> anyhow::Error {
+        let status = response.status();
+        let body = response.text().unwrap_or_default();
+        let message = serde_json::from_str::<ErrorResponse>(&body)
+            .map(|e| e.error.message)
+            .unwrap_or(body);
+
+        if message.trim().is_empty() {
+            anyhow!("{} request failed: {}", what, status)
+        } else {
+            anyhow!("{} request failed: {}: {}", what, status, message.trim())
+        }
+    }
+}
+
+impl EmbeddingBackend for OpenAiCompatibleBackend {
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let url = format!("{}/embeddings", self.base_url);
+        let request = EmbeddingsRequest {
+            model: self.model.clone(),
+            input: texts.to_vec(),
+        };
+
+        let response = send_with_retry("Embedding", || self.request(self.client.post(&url)).json(&request))
+            .context("Failed to send embedding request")?;
+
+        if !response.status().is_success() {
+            return Err(Self::error("Embedding", response));
+        }
+
+        let mut embeddings: EmbeddingsResponse = response.json()
+            .context("Failed to parse embedding response")?;
+
+        if embeddings.data.len() != texts.len() {
+            return Err(anyhow!(
+                "Expected {} embeddings, the server returned {}",
+                texts.len(),
+                embeddings.data.len()
+            ));
+        }
+
+        // The API doesn't promise to return embeddings in input order
+        embeddings.data.sort_by_key(|e| e.index);
+        Ok(embeddings.data.into_iter().map(|e| e.embedding).collect())
+    }
+
+    fn model_id(&self) -> String {
+        format!("openai:{}", self.model)
+    }
+
+    fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+}
+
+impl ReasoningBackend for OpenAiCompatibleBackend {
+    fn generate(&self, system: &str, prompt: &str) -> Result<String> {
+        let url = format!("{}/chat/completions", self.base_url);
+
+        let request = ChatRequest {
+            model: self.llm_model.clone(),
+            messages: vec![
+                ChatMessage { role: "system".to_string(), content: system.to_string() },
+                ChatMessage { role: "user".to_string(), content: prompt.to_string() },
+            ],
+            response_format: ResponseFormat { kind: "json_object".to_string() },
+        };
+
+        let response = self
+            .request(self.client.post(&url))
+            .json(&request)
+            .send()
+            .context("Failed to send chat completion request")?;
+
+        if !response.status().is_success() {
+            return Err(Self::error("Chat completion", response));
+        }
+
+        let completion: ChatResponse = response.json()
+            .context("Failed to parse chat completion response")?;
+
+        completion
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content)
+            .ok_or_else(|| anyhow!("The chat completion response has no choices"))
+    }
+}
+
+// Request/Response structures for the OpenAI API
+#[derive(Serialize)]
+struct EmbeddingsRequest {
+    model: String,
+    input: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    #[serde(default)]
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Serialize)]
+struct ChatRequest {
+    model: String,
+    messages: Vec<ChatMessage>,
+    /// Ask for a reply that is valid JSON
+    response_format: ResponseFormat,
+}
+
+#[derive(Serialize)]
+struct ResponseFormat {
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChatMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: ErrorMessage,
+}
+
+#[derive(Deserialize)]
+struct ErrorMessage {
+    message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    /// Serve the canned (status, body) responses in order, one per connection;
+    /// the thread returns the raw requests it received
+    fn serve(responses: Vec<(u16, &'static str)>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/v1", listener.local_addr().unwrap());
+
+        let server = std::thread::spawn(move || {
+            responses
+                .into_iter()
+                .map(|(status, body)| {
+                    let (stream, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(stream);
+                    let mut request = String::new();
+                    let mut length = 0;
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                            length = value.trim().parse().unwrap();
+                        }
+                        request.push_str(&line);
+                        if line == "\r\n" {
+                            break;
+                        }
+                    }
+                    let mut content = vec![0; length];
+                    reader.read_exact(&mut content).unwrap();
+                    request.push_str(&String::from_utf8(content).unwrap());
+
+                    let response = format!(
+                        "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                    reader.get_mut().write_all(response.as_bytes()).unwrap();
+                    request
+                })
+                .collect()
+        });
+
+        (url, server)
+    }
+
+    fn texts(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn sends_the_api_key_and_custom_headers() {
+        let (url, server) = serve(vec![(200, r#"{"data": [{"index": 0, "embedding": [1.0]}]}"#)]);
+        let backend = OpenAiCompatibleBackend::new()
+            .with_url(url)
+            .with_api_key(Some("secret".to_string()))
+            .with_header("X-Team".to_string(), "payments".to_string());
+
+        backend.embed(&texts(&["db timeout"])).unwrap();
+
+        let requests = server.join().unwrap();
+        let request = requests[0].to_ascii_lowercase();
+        assert!(request.starts_with("post /v1/embeddings "));
+        assert!(request.contains("authorization: bearer secret\r\n"));
+        assert!(request.contains("x-team: payments\r\n"));
+        assert!(request.contains(r#""input":["db timeout"]"#));
+    }
+
+    #[test]
+    fn orders_embeddings_by_index() {
+        let (url, server) = serve(vec![(
+            200,
+            r#"{"data": [{"index": 2, "embedding": [3.0]}, {"index": 0, "embedding": [1.0]}, {"index": 1, "embedding": [2.0]}]}"#,
+        )]);
+        let backend = OpenAiCompatibleBackend::new().with_url(url);
+
+        let embeddings = backend.embed(&texts(&["a", "b", "c"])).unwrap();
+
+        assert_eq!(embeddings, vec![vec![1.0], vec![2.0], vec![3.0]]);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn reports_the_server_error_message() {
+        let (url, server) = serve(vec![(404, r#"{"error": {"message": "model 'nomic' not found"}}"#)]);
+        let backend = OpenAiCompatibleBackend::new().with_url(url);
+
+        let error = backend.embed(&texts(&["a"])).unwrap_err().to_string();
+
+        assert_eq!(error, "Embedding request failed: 404 Not Found: model 'nomic' not found");
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn reports_a_plain_error_body() {
+        let (url, server) = serve(vec![(400, "bad input")]);
+        let backend = OpenAiCompatibleBackend::new().with_url(url);
+
+        let error = backend.generate("system", "prompt").unwrap_err().to_string();
+
+        assert_eq!(error, "Chat completion request failed: 400 Bad Request: bad input");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn retries_rate_limits_and_server_errors() {
+        let (url, server) = serve(vec![
+            (429, r#"{"error": {"message": "slow down"}}"#),
+            (503, ""),
+            (200, r#"{"data": [{"index": 0, "embedding": [1.0]}]}"#),
+        ]);
+        let backend = OpenAiCompatibleBackend::new().with_url(url);
+
+        let embeddings = backend.embed(&texts(&["a"])).unwrap();
+
+        assert_eq!(embeddings, vec![vec![1.0]]);
+        assert_eq!(server.join().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,411 @@
+use anyhow::{Context, Result, anyhow};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// File in the cache directory holding the embeddings
+const CACHE_FILE: &str = "embeddings.bin";
+
+/// Identifies the file format; bumped when the layout changes
+const MAGIC: &[u8; 4] = b"LREC";
+const VERSION: u32 = 1;
+
+/// Default size limit of the cache file
+pub const DEFAULT_MAX_SIZE_MB: u64 = 100;
+
+/// Bytes per entry besides the vector: key, model index, last use and dimension
+const ENTRY_OVERHEAD: u64 = 8 + 4 + 8 + 4;
+
+/// 64-bit FNV-1a hash
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+struct Entry {
+    /// Index into the model list
+    model: u32,
+    /// Access clock at the last hit or insert, for LRU eviction
+    last_used: u64,
+    vector: Vec<f32>,
+}
+
+impl Entry {
+    fn size(&self) -> u64 {
+        ENTRY_OVERHEAD + 4 * self.vector.len() as u64
+    }
+}
+
+/// Summary of the cache contents and of this run's lookups
+#[derive(Debug, Clone)]
+pub struct CacheStats {
+    pub path: PathBuf,
+    pub entries: usize,
+    pub size_bytes: u64,
+    pub max_bytes: u64,
+    /// Entries per backend and model
+    pub models: Vec<(String, usize)>,
+    pub hits: usize,
+    pub misses: usize,
+}
+
+/// On-disk cache of pattern embeddings, keyed by a hash of the backend,
+/// model and pattern. The whole file is loaded on open and rewritten on save,
+/// evicting the least recently used entries beyond the size limit.
+pub struct EmbeddingCache {
+    path: PathBuf,
+    max_bytes: u64,
+    /// Backend and model names, e.g. "ollama:nomic-embed-text"
+    models: Vec<String>,
+    entries: HashMap<u64, Entry>,
+    /// Counts hits and inserts, so accesses within the same second still order
+    clock: u64,
+    hits: usize,
+    misses: usize,
+    dirty: bool,
+}
+
+impl EmbeddingCache {
+    /// Open the cache in a directory; a missing file yields an empty cache
+    pub fn open(dir: &Path) -> Result<Self> {
+        let mut cache = Self {
+            path: dir.join(CACHE_FILE),
+            max_bytes: DEFAULT_MAX_SIZE_MB * 1024 * 1024,
+            models: Vec::new(),
+            entries: HashMap::new(),
+            clock: 0,
+            hits: 0,
+            misses: 0,
+            dirty: false,
+        };
+
+        if cache.path.exists() {
+            let bytes = std::fs::read(&cache.path)
+                .with_context(|| format!("Failed to read embedding cache: {}", cache.path.display()))?;
+            cache
+                .decode(&bytes)
+                .with_context(|| format!("Corrupt embedding cache: {}", cache.path.display()))?;
+        }
+
+        Ok(cache)
+    }
+
+    pub fn with_max_size(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// `$XDG_CACHE_HOME/log-reasoner`, or `~/.cache/log-reasoner`
+    pub fn default_dir() -> Option<PathBuf> {
+        std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .map(|dir| dir.join("log-reasoner"))
+    }
+
+    /// Delete the cache file in a directory. Returns the number of bytes freed.
+    pub fn clear(dir: &Path) -> Result<u64> {
+        let path = dir.join(CACHE_FILE);
+        let size = match std::fs::metadata(&path) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => return Ok(0),
+        };
+
+        std::fs::remove_file(&path).with_context(|| format!("Failed to delete {}", path.display()))?;
+        Ok(size)
+    }
+
+    fn key(model_id: &str, pattern: &str) -> u64 {
+        fnv1a(format!("{}\0{}", model_id, pattern.trim()).as_bytes())
+    }
+
+    /// Look up the embedding of a pattern, marking it as recently used
+    pub fn get(&mut self, model_id: &str, pattern: &str) -> Option<Vec<f32>> {
+        match self.entries.get_mut(&Self::key(model_id, pattern)) {
+            Some(entry) => {
+                self.clock += 1;
+                entry.last_used = self.clock;
+                self.hits += 1;
+                self.dirty = true;
+                Some(entry.vector.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, model_id: &str, pattern: &str, vector: Vec<f32>) {
+        let model = match self.models.iter().position(|m| m == model_id) {
+            Some(index) => index,
+            None => {
+                self.models.push(model_id.to_string());
+                self.models.len() - 1
+            }
+        };
+
+        self.clock += 1;
+        self.entries.insert(
+            Self::key(model_id, pattern),
+            Entry {
+                model: model as u32,
+                last_used: self.clock,
+                vector,
+            },
+        );
+        self.dirty = true;
+    }
+
+    /// Evict beyond the size limit and write the cache file, if anything changed
+    pub fn save(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        self.evict();
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create cache directory: {}", dir.display()))?;
+        }
+
+        // Write to a temporary file first so an interrupted run can't leave a truncated cache
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, self.encode()).with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, &self.path).with_context(|| format!("Failed to write {}", self.path.display()))?;
+
+        self.dirty = false;
+        Ok(())
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let mut models: Vec<(String, usize)> = self.models.iter().map(|m| (m.clone(), 0)).collect();
+        for entry in self.entries.values() {
+            models[entry.model as usize].1 += 1;
+        }
+        models.retain(|(_, n)| *n > 0);
+
+        CacheStats {
+            path: self.path.clone(),
+            entries: self.entries.len(),
+            size_bytes: self.size(),
+            max_bytes: self.max_bytes,
+            models,
+            hits: self.hits,
+            misses: self.misses,
+        }
+    }
+
+    fn size(&self) -> u64 {
+        let header = MAGIC.len() as u64 + 4 + 4 + self.models.iter().map(|m| 4 + m.len() as u64).sum::<u64>() + 4;
+        header + self.entries.values().map(Entry::size).sum::<u64>()
+    }
+
+    /// Drop the least recently used entries until the cache fits its size limit
+    fn evict(&mut self) {
+        let mut size = self.size();
+        if size <= self.max_bytes {
+            return;
+        }
+
+        let mut by_age: Vec<(u64, u64)> = self.entries.iter().map(|(key, e)| (e.last_used, *key)).collect();
+        by_age.sort_unstable();
+
+        for (_, key) in by_age {
+            if size <= self.max_bytes {
+                break;
+            }
+            if let Some(entry) = self.entries.remove(&key) {
+                size -= entry.size();
+            }
+        }
+    }
+
+    /// Layout (little-endian): magic, version, model count, models as
+    /// length-prefixed UTF-8, entry count, then per entry the key, model
+    /// index, last use, dimension and the vector
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.size() as usize);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+
+        bytes.extend_from_slice(&(self.models.len() as u32).to_le_bytes());
+        for model in &self.models {
+            bytes.extend_from_slice(&(model.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(model.as_bytes());
+        }
+
+        bytes.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for (key, entry) in &self.entries {
+            bytes.extend_from_slice(&key.to_le_bytes());
+            bytes.extend_from_slice(&entry.model.to_le_bytes());
+            bytes.extend_from_slice(&entry.last_used.to_le_bytes());
+            bytes.extend_from_slice(&(entry.vector.len() as u32).to_le_bytes());
+            for value in &entry.vector {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    fn decode(&mut self, bytes: &[u8]) -> Result<()> {
+        let mut reader = ByteReader::new(bytes);
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(anyhow!("not an embedding cache file"));
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(anyhow!("unsupported cache version {}", version));
+        }
+
+        for _ in 0..reader.u32()? {
+            let len = reader.u32()? as usize;
+            let name = std::str::from_utf8(reader.take(len)?).context("invalid model name")?;
+            self.models.push(name.to_string());
+        }
+
+        for _ in 0..reader.u32()? {
+            let key = reader.u64()?;
+            let model = reader.u32()?;
+            if model as usize >= self.models.len() {
+                return Err(anyhow!("invalid model index {}", model));
+            }
+            let last_used = reader.u64()?;
+            let dim = reader.u32()? as usize;
+            let vector = reader.f32s(dim)?;
+            self.clock = self.clock.max(last_used);
+            self.entries.insert(key, Entry { model, last_used, vector });
+        }
+
+        Ok(())
+    }
+}
+
+/// Cursor over a binary file with bounds-checked little-endian reads
+pub(crate) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    pub(crate) fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(n).filter(|end| *end <= self.bytes.len());
+        let end = end.ok_or_else(|| anyhow!("unexpected end of file"))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64> {
+        let b = self.take(8)?;
+        Ok(u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+    }
+
+    pub(crate) fn f32s(&mut self, n: usize) -> Result<Vec<f32>> {
+        let bytes = self.take(n.checked_mul(4).ok_or_else(|| anyhow!("vector too large"))?)?;
+        Ok(bytes.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Scratch cache directory for one test, removed when dropped
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("log-reasoner-cache-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            Self(dir)
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn round_trips_through_the_cache_file() {
+        let dir = Scratch::new("round-trip");
+        let mut cache = EmbeddingCache::open(&dir.0).unwrap();
+        cache.insert("ollama:nomic-embed-text", "db timeout after <VAR>ms", vec![0.25, -1.5, 3.0]);
+        cache.insert("openai:bge-small-en", "db timeout after <VAR>ms", vec![1.0, 2.0]);
+        cache.save().unwrap();
+
+        let mut cache = EmbeddingCache::open(&dir.0).unwrap();
+        assert_eq!(cache.get("ollama:nomic-embed-text", "db timeout after <VAR>ms"), Some(vec![0.25, -1.5, 3.0]));
+        assert_eq!(cache.get("openai:bge-small-en", "db timeout after <VAR>ms"), Some(vec![1.0, 2.0]));
+        assert_eq!(cache.get("ollama:nomic-embed-text", "pool exhausted"), None);
+
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (2, 2, 1));
+        assert_eq!(stats.size_bytes, std::fs::metadata(dir.0.join(CACHE_FILE)).unwrap().len());
+    }
+
+    #[test]
+    fn evicts_down_to_the_size_limit() {
+        let dir = Scratch::new("evict");
+        let mut cache = EmbeddingCache::open(&dir.0).unwrap().with_max_size(1024);
+        for i in 0..20 {
+            cache.insert("mock", &format!("pattern {}", i), vec![0.0; 16]);
+        }
+        cache.save().unwrap();
+
+        let size = std::fs::metadata(dir.0.join(CACHE_FILE)).unwrap().len();
+        assert!(size <= 1024, "cache file is {} bytes", size);
+        assert!(EmbeddingCache::open(&dir.0).unwrap().stats().entries < 20);
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_first() {
+        let dir = Scratch::new("lru");
+        // Room for 11 entries of 16 dimensions
+        let mut cache = EmbeddingCache::open(&dir.0).unwrap().with_max_size(1024);
+        for i in 0..20 {
+            cache.insert("mock", &format!("pattern {}", i), vec![0.0; 16]);
+        }
+        assert!(cache.get("mock", "pattern 0").is_some());
+        cache.save().unwrap();
+
+        // The access order survives a reload
+        let mut cache = EmbeddingCache::open(&dir.0).unwrap().with_max_size(1024);
+        assert!(cache.get("mock", "pattern 10").is_some());
+        cache.insert("mock", "pattern 20", vec![0.0; 16]);
+        cache.save().unwrap();
+
+        let mut cache = EmbeddingCache::open(&dir.0).unwrap();
+        let kept: Vec<usize> = (0..=20).filter(|i| cache.get("mock", &format!("pattern {}", i)).is_some()).collect();
+        assert_eq!(kept, [0, 10, 12, 13, 14, 15, 16, 17, 18, 19, 20]);
+    }
+
+    #[test]
+    fn rejects_a_corrupt_file() {
+        let dir = Scratch::new("corrupt");
+        let mut cache = EmbeddingCache::open(&dir.0).unwrap();
+        cache.insert("mock", "pattern", vec![1.0; 8]);
+        cache.save().unwrap();
+
+        let path = dir.0.join(CACHE_FILE);
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 2]).unwrap();
+        assert!(EmbeddingCache::open(&dir.0).is_err());
+    }
+}
@@ -1,4 +1,8 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+
+/// Longest window or bucket accepted, in seconds (about 100 years); far
+/// larger values would overflow a `chrono::Duration`
+pub const MAX_SECS: i64 = 100 * 365 * 24 * 60 * 60;
 
 #[derive(Parser)]
 #[command(name = "log-reasoner")]
@@ -12,25 +16,286 @@ pub struct Cli {
 #[derive(Subcommand)]
 pub enum Commands {
     /// Analyze a log file and generate insights
-    Analyze {
-        /// Path to the log file
-        #[arg(value_name = "FILE")]
-        file: String,
-
-        /// Number of top patterns to display
-        #[arg(short = 't', long = "top", default_value = "5")]
-        top: usize,
-
-        /// Minimum occurrences to report a pattern
-        #[arg(short = 'm', long = "min-count", default_value = "1")]
-        min_count: usize,
-
-        /// Output format (text or json)
-        #[arg(short = 'o', long = "output", default_value = "text")]
-        output: String,
-
-        /// Show only ERROR level logs
-        #[arg(long = "errors-only")]
-        errors_only: bool,
-    },
-}
\ No newline at end of file
+    Analyze(Box<AnalyzeArgs>),
+
+    /// Write a Markdown incident report narrated by an LLM
+    Report(Box<ReportArgs>),
+
+    /// Answer a question about a log file, citing the patterns and lines used
+    Ask(Box<AskArgs>),
+
+    /// List the available log formats, including custom ones from the config
+    Formats(FormatsArgs),
+
+    /// Show statistics of the embedding cache or clear it
+    Cache(CacheArgs),
+}
+
+#[derive(Args)]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    pub action: CacheAction,
+
+    /// Config file (default: ./log-reasoner.toml, then ~/.config/log-reasoner/config.toml)
+    #[arg(long = "config", value_name = "PATH", global = true)]
+    pub config: Option<String>,
+}
+
+#[derive(Subcommand)]
+pub enum CacheAction {
+    /// Show the cache location, size and entries per model
+    Stats,
+
+    /// Delete all cached embeddings
+    Clear,
+}
+
+#[derive(Args)]
+pub struct FormatsArgs {
+    /// Config file (default: ./log-reasoner.toml, then ~/.config/log-reasoner/config.toml)
+    #[arg(long = "config", value_name = "PATH")]
+    pub config: Option<String>,
+}
+
+/// Options selecting and decoding the input, shared by the analysis commands
+#[derive(Args)]
+pub struct InputArgs {
+    /// Path to the log file
+    #[arg(value_name = "FILE")]
+    pub file: String,
+
+    /// Log format: auto, a name listed by `log-reasoner formats`, or several
+    /// comma-separated names tried in order
+    #[arg(short = 'f', long = "format", value_name = "FORMAT", default_value = "auto")]
+    pub format: String,
+
+    /// Config file (default: ./log-reasoner.toml, then ~/.config/log-reasoner/config.toml)
+    #[arg(long = "config", value_name = "PATH")]
+    pub config: Option<String>,
+
+    /// Parse lines with a regex using named captures (timestamp, level, message, ...)
+    #[arg(long = "pattern", value_name = "REGEX", conflicts_with_all = ["grok", "format"])]
+    pub pattern: Option<String>,
+
+    /// Parse lines with a grok pattern, e.g. "%{TIMESTAMP_ISO8601:ts} %{LOGLEVEL:level} %{GREEDYDATA:msg}"
+    #[arg(long = "grok", value_name = "PATTERN", conflicts_with = "format")]
+    pub grok: Option<String>,
+
+    /// strftime format of the timestamp captured by --pattern or --grok
+    #[arg(long = "time-format", value_name = "FMT")]
+    pub time_format: Option<String>,
+
+    /// Timezone of timestamps without an offset: UTC, local or e.g. +02:00 (default: UTC)
+    #[arg(long = "timezone", value_name = "TZ")]
+    pub timezone: Option<String>,
+
+    /// Year of the first syslog-style timestamp, which carries none (default: inferred
+    /// from the other timestamps or the file's modification time)
+    #[arg(long = "year", value_name = "YEAR", value_parser = clap::value_parser!(i32).range(1..=9999))]
+    pub year: Option<i32>,
+
+    /// Text encoding of the input: utf-8, latin1, utf-16le or utf-16be (a byte order mark takes precedence)
+    #[arg(long = "encoding", value_name = "ENC", default_value = "utf-8")]
+    pub encoding: String,
+}
+
+/// Options selecting the model server, shared by the commands that use one
+#[derive(Args)]
+pub struct BackendArgs {
+    /// Model server: none, ollama, openai (any OpenAI-compatible server), hashed (offline embeddings only),
+    /// mock (canned replies) or replay (from a fixture file) (default: ollama)
+    #[arg(long = "backend", value_name = "NAME", env = "LOG_REASONER_BACKEND")]
+    pub backend: Option<String>,
+
+    /// Base URL of the model server (default: http://localhost:11434 for ollama, http://localhost:8080/v1 for openai)
+    #[arg(long = "llm-url", value_name = "URL", env = "LOG_REASONER_LLM_URL")]
+    pub llm_url: Option<String>,
+
+    /// Model used for embeddings (default: nomic-embed-text)
+    #[arg(long = "embed-model", value_name = "MODEL", env = "LOG_REASONER_EMBED_MODEL")]
+    pub embed_model: Option<String>,
+
+    /// Model used for explanations, reports and questions (default: llama3.2)
+    #[arg(long = "llm-model", value_name = "MODEL", env = "LOG_REASONER_LLM_MODEL")]
+    pub llm_model: Option<String>,
+
+    /// Don't read or write the embedding cache
+    #[arg(long = "no-cache")]
+    pub no_cache: bool,
+
+    /// Fixture file of recorded embeddings and completions, for --backend replay and --record
+    #[arg(long = "fixture", value_name = "PATH", env = "LOG_REASONER_FIXTURE")]
+    pub fixture: Option<String>,
+
+    /// Record the backend's embeddings and completions to the --fixture file
+    #[arg(long = "record", requires = "fixture")]
+    pub record: bool,
+
+    /// Save the pattern index to PATH and reuse it while the patterns and model are unchanged
+    #[arg(long = "index", value_name = "PATH")]
+    pub index: Option<String>,
+}
+
+#[derive(Args)]
+pub struct AnalyzeArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    #[command(flatten)]
+    pub backend: BackendArgs,
+
+    /// Number of top patterns to display
+    #[arg(short = 't', long = "top", default_value = "5")]
+    pub top: usize,
+
+    /// Minimum occurrences to report a pattern
+    #[arg(short = 'm', long = "min-count", default_value = "1")]
+    pub min_count: usize,
+
+    /// Output format (text or json)
+    #[arg(short = 'o', long = "output", default_value = "text")]
+    pub output: String,
+
+    /// Show only ERROR level logs
+    #[arg(long = "errors-only")]
+    pub errors_only: bool,
+
+    /// Group patterns separately per value of this field (e.g. unit, pod, hostname)
+    #[arg(long = "split-by", value_name = "FIELD")]
+    pub split_by: Option<String>,
+
+    /// Mine "A precedes B" relationships between the top patterns
+    #[arg(long = "precedence")]
+    pub precedence: bool,
+
+    /// Maximum lag in seconds for one pattern to count as preceding another
+    #[arg(long = "precedence-window", value_name = "SECS", default_value = "5", value_parser = clap::value_parser!(i64).range(0..=MAX_SECS))]
+    pub precedence_window: i64,
+
+    /// Rank root-cause candidates for an incident at this time (e.g. 2024-01-05T12:03Z)
+    #[arg(long = "incident", value_name = "TIME")]
+    pub incident: Option<String>,
+
+    /// Seconds before and after the incident considered part of it
+    #[arg(long = "incident-window", value_name = "SECS", default_value = "300", value_parser = clap::value_parser!(i64).range(0..=MAX_SECS))]
+    pub incident_window: i64,
+
+    /// Reconstruct request sessions from correlation IDs
+    #[arg(long = "sessions")]
+    pub sessions: bool,
+
+    /// Keys holding correlation IDs, in priority order
+    #[arg(
+        long = "correlation-key",
+        value_name = "KEY",
+        value_delimiter = ',',
+        default_value = "request_id,trace_id,x-correlation-id"
+    )]
+    pub correlation_keys: Vec<String>,
+
+    /// Detect periodic patterns (health checks, cron jobs) and missed beats
+    #[arg(long = "periodicity")]
+    pub periodicity: bool,
+
+    /// Rank periodic patterns after all others (implies --periodicity)
+    #[arg(long = "demote-periodic")]
+    pub demote_periodic: bool,
+
+    /// Detect change points in overall log volume, error ratio and active patterns
+    #[arg(long = "change-points")]
+    pub change_points: bool,
+
+    /// Extract latency, size and count metrics from messages per pattern
+    #[arg(long = "metrics")]
+    pub metrics: bool,
+
+    /// Report HTTP status classes, error paths and top clients from access logs
+    #[arg(long = "access")]
+    pub access: bool,
+
+    /// Group patterns whose embeddings are similar (different wordings of the same problem)
+    #[arg(long = "clusters")]
+    pub clusters: bool,
+
+    /// Cosine similarity for two patterns to share a cluster
+    #[arg(long = "cluster-threshold", value_name = "SIM", default_value = "0.85")]
+    pub cluster_threshold: f32,
+
+    /// List the patterns most similar to pattern N (as numbered in the output)
+    #[arg(long = "similar-to", value_name = "N")]
+    pub similar_to: Option<usize>,
+
+    /// Ask the configured --backend for the probable cause and next steps of each top pattern
+    #[arg(long = "explain")]
+    pub explain: bool,
+
+    /// Width of time buckets in seconds (default: about 100 buckets over the log)
+    #[arg(long = "bucket", value_name = "SECS", value_parser = clap::value_parser!(i64).range(1..=MAX_SECS))]
+    pub bucket: Option<i64>,
+}
+
+#[derive(Args)]
+pub struct ReportArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    #[command(flatten)]
+    pub backend: BackendArgs,
+
+    /// Number of top patterns to include
+    #[arg(short = 't', long = "top", default_value = "10")]
+    pub top: usize,
+
+    /// Minimum occurrences to report a pattern
+    #[arg(short = 'm', long = "min-count", default_value = "1")]
+    pub min_count: usize,
+
+    /// Group patterns separately per value of this field (e.g. unit, pod, hostname)
+    #[arg(long = "split-by", value_name = "FIELD")]
+    pub split_by: Option<String>,
+
+    /// Width of time buckets in seconds (default: about 100 buckets over the log)
+    #[arg(long = "bucket", value_name = "SECS", value_parser = clap::value_parser!(i64).range(1..=MAX_SECS))]
+    pub bucket: Option<i64>,
+
+    /// Approximate number of tokens the statistics may take up in the prompt
+    #[arg(long = "max-tokens", value_name = "N", default_value = "3000")]
+    pub max_tokens: usize,
+
+    /// Write the report to this file instead of stdout
+    #[arg(long = "output-file", value_name = "PATH")]
+    pub output_file: Option<String>,
+}
+
+#[derive(Args)]
+pub struct AskArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    #[command(flatten)]
+    pub backend: BackendArgs,
+
+    /// Question about the logs, e.g. "why did checkout fail at 12:03?"
+    #[arg(value_name = "QUESTION")]
+    pub question: String,
+
+    /// Number of most similar patterns retrieved as context
+    #[arg(short = 'k', long = "top-k", default_value = "5")]
+    pub top_k: usize,
+
+    /// Number of sample lines shown per retrieved pattern
+    #[arg(long = "samples", default_value = "3")]
+    pub samples: usize,
+
+    /// Group patterns separately per value of this field (e.g. unit, pod, hostname)
+    #[arg(long = "split-by", value_name = "FIELD")]
+    pub split_by: Option<String>,
+
+    /// Approximate number of tokens the retrieved context may take up in the prompt
+    #[arg(long = "max-tokens", value_name = "N", default_value = "3000")]
+    pub max_tokens: usize,
+
+    /// Output format (text or json)
+    #[arg(short = 'o', long = "output", default_value = "text")]
+    pub output: String,
+}
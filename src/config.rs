@@ -0,0 +1,139 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Config file looked up in the working directory when `--config` is not given
+const LOCAL_CONFIG: &str = "log-reasoner.toml";
+
+/// Settings loaded from `log-reasoner.toml`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// User-defined line formats
+    #[serde(default)]
+    pub formats: Vec<FormatConfig>,
+    /// Timestamp parsing settings
+    #[serde(default)]
+    pub timestamps: TimestampConfig,
+    /// Model server selection
+    #[serde(default)]
+    pub backend: BackendConfig,
+    /// OpenAI-compatible server settings
+    #[serde(default)]
+    pub openai: OpenAiConfig,
+    /// Embedding cache settings
+    #[serde(default)]
+    pub cache: CacheConfig,
+}
+
+/// `[backend]`: the model server and models; command-line options and
+/// environment variables take precedence
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BackendConfig {
+    /// none, ollama, openai, hashed, mock or replay
+    pub name: Option<String>,
+    /// Base URL of the server
+    pub url: Option<String>,
+    /// Model used for embeddings
+    pub embed_model: Option<String>,
+    /// Model used for reasoning
+    pub llm_model: Option<String>,
+    /// Request timeout in seconds
+    pub timeout_secs: Option<u64>,
+    /// Texts per embedding request
+    pub batch_size: Option<usize>,
+    /// Parallel embedding requests when the server can't batch
+    pub concurrency: Option<usize>,
+    /// Word-vector file (GloVe / word2vec text format) for the hashed backend
+    pub vectors: Option<String>,
+    /// Fixture file replayed by the replay backend, or written when recording
+    pub fixture: Option<String>,
+    /// Record the backend's embeddings and completions to the fixture file
+    pub record: Option<bool>,
+}
+
+/// `[openai]`: credentials and headers for an OpenAI-compatible server
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OpenAiConfig {
+    /// Environment variable holding the API key (default: OPENAI_API_KEY)
+    pub api_key_env: Option<String>,
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// `[cache]`: where embeddings are cached and how large the cache may grow
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Cache directory (default: ~/.cache/log-reasoner)
+    pub dir: Option<String>,
+    /// Size limit in megabytes (default: 100)
+    pub max_size_mb: Option<u64>,
+    /// Set to false to never read or write the cache
+    pub enabled: Option<bool>,
+}
+
+/// `[timestamps]`: extra formats, the timezone of naive timestamps and the year of year-less ones
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimestampConfig {
+    /// strftime formats tried before the built-in ones
+    #[serde(default)]
+    pub formats: Vec<String>,
+    /// UTC, local or an offset such as +02:00
+    pub timezone: Option<String>,
+    /// Year of the first timestamp without one (default: inferred)
+    pub year: Option<i32>,
+}
+
+/// A custom line format described by a named-capture regex or a grok pattern
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FormatConfig {
+    pub name: String,
+    pub regex: Option<String>,
+    pub grok: Option<String>,
+    /// strftime format of the `timestamp` capture
+    pub time_format: Option<String>,
+}
+
+impl Config {
+    /// Load the config from an explicit path, `./log-reasoner.toml` or
+    /// `$XDG_CONFIG_HOME/log-reasoner/config.toml`, whichever exists first.
+    /// A missing config yields the defaults.
+    pub fn load(path: Option<&str>) -> Result<Self> {
+        let path = match path {
+            Some(p) => PathBuf::from(p),
+            None => match Self::default_paths().into_iter().find(|p| p.exists()) {
+                Some(p) => p,
+                None => return Ok(Self::default()),
+            },
+        };
+
+        Self::from_file(&path)
+    }
+
+    fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+
+        toml::from_str(&text).with_context(|| format!("Invalid config file: {}", path.display()))
+    }
+
+    fn default_paths() -> Vec<PathBuf> {
+        let mut paths = vec![PathBuf::from(LOCAL_CONFIG)];
+
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+        if let Some(dir) = config_home {
+            paths.push(dir.join("log-reasoner").join("config.toml"));
+        }
+
+        paths
+    }
+}
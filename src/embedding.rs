@@ -1,14 +1,51 @@
 use crate::backends::EmbeddingBackend;
+use crate::cache::{CacheStats, EmbeddingCache};
+use crate::index::{self, HnswIndex};
 use crate::models::LogGroup;
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use std::cell::RefCell;
+use std::io::IsTerminal;
+use std::path::PathBuf;
 
 pub struct EmbeddingGenerator<B: EmbeddingBackend> {
     backend: B,
+    /// Pattern embeddings from earlier runs
+    cache: Option<RefCell<EmbeddingCache>>,
+    /// Where the pattern index is saved and reused from
+    index_file: Option<PathBuf>,
+    /// Whether a saved index may stand in for embedding the patterns
+    reuse_index: bool,
 }
 
 impl<B: EmbeddingBackend> EmbeddingGenerator<B> {
     pub fn new(backend: B) -> Self {
-        Self { backend }
+        Self {
+            backend,
+            cache: None,
+            index_file: None,
+            reuse_index: true,
+        }
+    }
+
+    pub fn with_cache(mut self, cache: EmbeddingCache) -> Self {
+        self.cache = Some(RefCell::new(cache));
+        self
+    }
+
+    pub fn with_index_file(mut self, path: PathBuf) -> Self {
+        self.index_file = Some(path);
+        self
+    }
+
+    /// Whether to load a matching saved index (default) or always rebuild it
+    pub fn with_index_reuse(mut self, reuse: bool) -> Self {
+        self.reuse_index = reuse;
+        self
+    }
+
+    /// Cache contents and this run's hits and misses, if caching
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(|cache| cache.borrow().stats())
     }
 
     /// Generate embeddings for log groups
@@ -16,28 +53,118 @@ impl<B: EmbeddingBackend> EmbeddingGenerator<B> {
     pub fn embed_groups(&self, groups: &[LogGroup]) -> Result<Vec<(usize, Vec<f32>)>> {
         println!("Generating embeddings for {} patterns...", groups.len());
 
-        // Extract unique patterns
-        let texts: Vec<String> = groups
-            .iter()
-            .map(|g| g.pattern.clone())
-            .collect();
+        // Look up cached patterns first
+        let model_id = self.backend.model_id();
+        let mut embeddings: Vec<Option<Vec<f32>>> = match self.cache {
+            Some(ref cache) => {
+                let mut cache = cache.borrow_mut();
+                groups.iter().map(|g| cache.get(&model_id, &g.pattern)).collect()
+            }
+            None => vec![None; groups.len()],
+        };
 
-        // Generate embeddings
-        let embeddings = self.backend.embed(&texts)?;
+        // Generate embeddings for the rest
+        let missing: Vec<usize> = (0..groups.len()).filter(|&i| embeddings[i].is_none()).collect();
+        let texts: Vec<String> = missing.iter().map(|&i| groups[i].pattern.clone()).collect();
+        let generated = self.embed_batches(&texts, true)?;
+
+        if let Some(ref cache) = self.cache {
+            let mut cache = cache.borrow_mut();
+            for (&i, embedding) in missing.iter().zip(&generated) {
+                cache.insert(&model_id, &groups[i].pattern, embedding.clone());
+            }
+            if let Err(e) = cache.save() {
+                eprintln!("⚠ Warning: Failed to save the embedding cache: {:#}", e);
+            }
+        }
+        for (i, embedding) in missing.into_iter().zip(generated) {
+            embeddings[i] = Some(embedding);
+        }
 
         // Pair with indices
         let result: Vec<(usize, Vec<f32>)> = embeddings
             .into_iter()
             .enumerate()
+            .filter_map(|(i, e)| e.map(|e| (i, e)))
             .collect();
 
-        println!("✓ Generated {} embeddings", result.len());
+        if self.cache.is_some() {
+            println!("✓ Generated {} embeddings ({} from cache)", result.len(), result.len() - texts.len());
+        } else {
+            println!("✓ Generated {} embeddings", result.len());
+        }
 
         Ok(result)
     }
 
+    /// Build a nearest-neighbor index over the groups' embeddings, keyed by
+    /// group index. A saved index built by the same model from the same
+    /// patterns is reused without embedding anything.
+    pub fn index_groups(&self, groups: &[LogGroup]) -> Result<HnswIndex> {
+        let fingerprint = index::fingerprint(&self.backend.model_id(), groups.iter().map(|g| g.pattern.as_str()));
+
+        if let Some(ref path) = self.index_file
+            && self.reuse_index
+            && path.exists()
+        {
+            match HnswIndex::load(path) {
+                Ok(index) if index.fingerprint() == fingerprint => {
+                    println!("✓ Loaded the pattern index from {}", path.display());
+                    return Ok(index);
+                }
+                Ok(_) => println!("Patterns or model changed, rebuilding the pattern index..."),
+                Err(e) => eprintln!("⚠ Warning: Rebuilding the pattern index: {:#}", e),
+            }
+        }
+
+        let embeddings = self.embed_groups(groups)?;
+        let dimensions = embeddings.first().map(|(_, e)| e.len()).unwrap_or(0);
+        let mut index = HnswIndex::new(dimensions).with_fingerprint(fingerprint);
+        for (i, embedding) in &embeddings {
+            index.insert(*i, embedding)?;
+        }
+
+        if let Some(ref path) = self.index_file {
+            match index.save(path) {
+                Ok(_) => println!("✓ Saved the pattern index to {}", path.display()),
+                Err(e) => eprintln!("⚠ Warning: Failed to save the pattern index: {:#}", e),
+            }
+        }
+
+        Ok(index)
+    }
+
+    /// Generate embeddings for arbitrary texts, in order
+    pub fn embed_texts(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.embed_batches(texts, false)
+    }
+
+    /// Embed in batches of the backend's batch size, keeping input order.
+    /// Progress is shown on stderr when it is a terminal.
+    fn embed_batches(&self, texts: &[String], progress: bool) -> Result<Vec<Vec<f32>>> {
+        let batch_size = self.backend.batch_size().max(1);
+        let show_progress = progress && texts.len() > batch_size && std::io::stderr().is_terminal();
+
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(batch_size) {
+            let batch_embeddings = self.backend.embed(batch)?;
+            if batch_embeddings.len() != batch.len() {
+                return Err(anyhow!("Expected {} embeddings, the backend returned {}", batch.len(), batch_embeddings.len()));
+            }
+            embeddings.extend(batch_embeddings);
+
+            if show_progress {
+                eprint!("\r  Embedded {}/{} patterns", embeddings.len(), texts.len());
+            }
+        }
+        if show_progress {
+            eprintln!();
+        }
+
+        Ok(embeddings)
+    }
+
     /// Calculate cosine similarity between two vectors
-    #[allow(dead_code)]
     pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
         if a.len() != b.len() {
             return 0.0;
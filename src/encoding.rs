@@ -0,0 +1,254 @@
+use anyhow::{Result, anyhow};
+use std::io::BufRead;
+
+/// Text encodings accepted with `--encoding`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Latin1,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Encoding {
+    pub const NAMES: [&str; 5] = ["utf-8", "latin1", "utf-16", "utf-16le", "utf-16be"];
+
+    /// Parse an encoding name; `utf-16` without a byte order means little-endian
+    /// unless the input starts with a big-endian BOM
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Ok(Encoding::Utf8),
+            "latin1" | "latin-1" | "iso-8859-1" => Ok(Encoding::Latin1),
+            "utf-16" | "utf16" | "utf-16le" | "utf16le" => Ok(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" => Ok(Encoding::Utf16Be),
+            _ => Err(anyhow!("Unknown encoding '{}'. Available: {}", name, Self::NAMES.join(", "))),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Latin1 => "latin1",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+        }
+    }
+}
+
+/// Reads lines as bytes and decodes them, replacing invalid sequences
+/// instead of failing. A byte order mark overrides the configured encoding.
+pub struct LineReader<R: BufRead> {
+    reader: R,
+    encoding: Encoding,
+    /// Whether the start of the input has been checked for a BOM
+    checked_bom: bool,
+    buf: Vec<u8>,
+    /// Lines that contained byte sequences invalid in the encoding
+    pub invalid_lines: usize,
+}
+
+impl<R: BufRead> LineReader<R> {
+    pub fn new(reader: R, encoding: Encoding) -> Self {
+        Self {
+            reader,
+            encoding,
+            checked_bom: false,
+            buf: Vec::new(),
+            invalid_lines: 0,
+        }
+    }
+
+    /// The encoding in use, after BOM detection
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Read the next line without its line terminator, or None at the end of the input
+    pub fn next_line(&mut self) -> Result<Option<String>> {
+        if !self.checked_bom {
+            self.checked_bom = true;
+            self.skip_bom()?;
+        }
+
+        self.buf.clear();
+        let found = match self.encoding {
+            Encoding::Utf8 | Encoding::Latin1 => self.reader.read_until(b'\n', &mut self.buf)? > 0,
+            Encoding::Utf16Le | Encoding::Utf16Be => self.read_utf16_line()?,
+        };
+        if !found {
+            return Ok(None);
+        }
+
+        let (line, valid) = self.decode();
+        if !valid {
+            self.invalid_lines += 1;
+        }
+
+        Ok(Some(line.trim_end_matches(['\n', '\r']).to_string()))
+    }
+
+    /// Detect and skip a UTF-8 or UTF-16 byte order mark
+    fn skip_bom(&mut self) -> Result<()> {
+        let head = self.reader.fill_buf()?;
+
+        let (encoding, len) = if head.starts_with(&[0xEF, 0xBB, 0xBF]) {
+            (Encoding::Utf8, 3)
+        } else if head.starts_with(&[0xFF, 0xFE]) {
+            (Encoding::Utf16Le, 2)
+        } else if head.starts_with(&[0xFE, 0xFF]) {
+            (Encoding::Utf16Be, 2)
+        } else {
+            return Ok(());
+        };
+
+        self.encoding = encoding;
+        self.reader.consume(len);
+        Ok(())
+    }
+
+    /// Read up to and including a UTF-16 newline, keeping code units aligned
+    fn read_utf16_line(&mut self) -> Result<bool> {
+        loop {
+            if self.reader.read_until(b'\n', &mut self.buf)? == 0 {
+                return Ok(!self.buf.is_empty());
+            }
+
+            // Make sure the buffer holds whole code units
+            if self.buf.len() % 2 == 1 {
+                let mut byte = [0u8; 1];
+                if self.reader.read(&mut byte)? == 0 {
+                    return Ok(true);
+                }
+                self.buf.push(byte[0]);
+            }
+
+            let unit = self.units().last();
+            if unit == Some(u16::from(b'\n')) {
+                return Ok(true);
+            }
+        }
+    }
+
+    fn units(&self) -> impl Iterator<Item = u16> + '_ {
+        let encoding = self.encoding;
+        self.buf.chunks(2).map(move |pair| {
+            let pair = [pair[0], *pair.get(1).unwrap_or(&0)];
+            match encoding {
+                Encoding::Utf16Be => u16::from_be_bytes(pair),
+                _ => u16::from_le_bytes(pair),
+            }
+        })
+    }
+
+    /// Decode the buffered line, reporting whether it was valid
+    fn decode(&self) -> (String, bool) {
+        match self.encoding {
+            Encoding::Utf8 => match std::str::from_utf8(&self.buf) {
+                Ok(line) => (line.to_string(), true),
+                Err(_) => (String::from_utf8_lossy(&self.buf).into_owned(), false),
+            },
+            // Every byte is a valid Latin-1 character
+            Encoding::Latin1 => (self.buf.iter().map(|&b| b as char).collect(), true),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let mut valid = self.buf.len().is_multiple_of(2);
+                let line = char::decode_utf16(self.units())
+                    .map(|c| {
+                        c.unwrap_or_else(|_| {
+                            valid = false;
+                            char::REPLACEMENT_CHARACTER
+                        })
+                    })
+                    .collect();
+                (line, valid)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Read every line of `bytes`, returning them with the final encoding and invalid-line count
+    fn read(bytes: &[u8], encoding: Encoding) -> (Vec<String>, Encoding, usize) {
+        let mut reader = LineReader::new(bytes, encoding);
+        let mut lines = Vec::new();
+        while let Some(line) = reader.next_line().unwrap() {
+            lines.push(line);
+        }
+        (lines, reader.encoding(), reader.invalid_lines)
+    }
+
+    fn utf16(text: &str, encode: fn(u16) -> [u8; 2]) -> Vec<u8> {
+        text.encode_utf16().flat_map(encode).collect()
+    }
+
+    #[test]
+    fn reads_utf16_in_either_byte_order() {
+        let text = "ERROR café\r\nINFO ok\n";
+
+        let (lines, _, invalid) = read(&utf16(text, u16::to_le_bytes), Encoding::Utf16Le);
+        assert_eq!(lines, ["ERROR café", "INFO ok"]);
+        assert_eq!(invalid, 0);
+
+        let (lines, _, invalid) = read(&utf16(text, u16::to_be_bytes), Encoding::Utf16Be);
+        assert_eq!(lines, ["ERROR café", "INFO ok"]);
+        assert_eq!(invalid, 0);
+    }
+
+    #[test]
+    fn keeps_code_units_aligned_across_newline_bytes() {
+        // U+0A41 is 0x41 0x0A in little-endian, which contains a `\n` byte
+        let (lines, _, invalid) = read(&utf16("\u{0a41}x\ny\n", u16::to_le_bytes), Encoding::Utf16Le);
+        assert_eq!(lines, ["\u{0a41}x", "y"]);
+        assert_eq!(invalid, 0);
+    }
+
+    #[test]
+    fn a_bom_overrides_the_configured_encoding() {
+        let mut bytes = vec![0xFE, 0xFF];
+        bytes.extend(utf16("big\n", u16::to_be_bytes));
+        assert_eq!(read(&bytes, Encoding::Utf8), (vec!["big".to_string()], Encoding::Utf16Be, 0));
+
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(utf16("little\n", u16::to_le_bytes));
+        assert_eq!(read(&bytes, Encoding::Utf16Be), (vec!["little".to_string()], Encoding::Utf16Le, 0));
+
+        let (lines, encoding, _) = read(b"\xEF\xBB\xBFplain\n", Encoding::Latin1);
+        assert_eq!((lines, encoding), (vec!["plain".to_string()], Encoding::Utf8));
+    }
+
+    #[test]
+    fn decodes_latin1_bytes_as_code_points() {
+        let (lines, _, invalid) = read(b"caf\xE9 \xB5s\n", Encoding::Latin1);
+        assert_eq!(lines, ["café µs"]);
+        assert_eq!(invalid, 0);
+    }
+
+    #[test]
+    fn counts_lines_with_invalid_utf8() {
+        let (lines, _, invalid) = read(b"ok\ncaf\xE9\n\xFF\xFE\xFDbad\nok again", Encoding::Utf8);
+        assert_eq!(lines, ["ok", "caf\u{fffd}", "\u{fffd}\u{fffd}\u{fffd}bad", "ok again"]);
+        assert_eq!(invalid, 2);
+    }
+
+    #[test]
+    fn an_odd_trailing_byte_is_invalid() {
+        let mut bytes = utf16("a\n", u16::to_le_bytes);
+        bytes.push(b'b');
+
+        let (lines, _, invalid) = read(&bytes, Encoding::Utf16Le);
+        assert_eq!(lines, ["a", "b"]);
+        assert_eq!(invalid, 1);
+    }
+
+    #[test]
+    fn a_lone_surrogate_is_replaced() {
+        let mut bytes = 0xD800u16.to_be_bytes().to_vec();
+        bytes.extend(utf16("x\ny\n", u16::to_be_bytes));
+
+        let (lines, _, invalid) = read(&bytes, Encoding::Utf16Be);
+        assert_eq!(lines, ["\u{fffd}x", "y"]);
+        assert_eq!(invalid, 1);
+    }
+}
@@ -0,0 +1,188 @@
+use super::LineParser;
+use super::timestamp::TimestampParser;
+use crate::models::{LogEvent, LogLevel};
+use chrono::NaiveDateTime;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+/// Parses Apache httpd `error_log` lines (2.2 and 2.4 formats)
+pub struct ApacheErrorParser {
+    /// Regex for the line header (timestamp, module, level, pid:tid)
+    line_regex: Regex,
+    /// Regex for bracketed `[client ...]`, `[remote ...]`, `[C:...]` and `[R:...]` context
+    context_regex: Regex,
+    /// Regex for the trailing `, referer: ...`
+    referer_regex: Regex,
+    /// Regex for the `AH01114:` message code
+    code_regex: Regex,
+    /// Regex for the backend named in mod_proxy messages
+    upstream_regex: Regex,
+    timestamps: TimestampParser,
+}
+
+impl ApacheErrorParser {
+    pub fn new(timestamps: TimestampParser) -> Self {
+        Self {
+            // Matches: [Fri Jan 05 12:01:03.123 2024] [proxy:error] [pid 123:tid 456] msg
+            //      or: [Fri Jan 05 12:01:03 2024] [error] msg
+            line_regex: Regex::new(
+                r"^\[(\w{3} \w{3} \d{1,2} \d{2}:\d{2}:\d{2}(?:\.\d+)? \d{4})\] \[(?:([\w-]+):)?(\w+)\] (?:\[pid (\d+)(?::tid (\d+))?\] )?(.*)$"
+            ).unwrap(),
+
+            // Matches: [client 10.0.0.1:5678], [remote 127.0.0.1:8080], [C:Zb2s] (connection log ID), [R:Zb2t] (request log ID)
+            context_regex: Regex::new(
+                r"\[(client|remote|C|R)[ :]([^\]]+)\] ?"
+            ).unwrap(),
+
+            // Matches: , referer: http://example.com/page
+            referer_regex: Regex::new(
+                r", referer: (\S+)$"
+            ).unwrap(),
+
+            code_regex: Regex::new(
+                r"\b(AH\d{5}):"
+            ).unwrap(),
+
+            // Matches: attempt to connect to 127.0.0.1:8080, failed to make connection to backend: app1
+            upstream_regex: Regex::new(
+                r"(?:attempt to connect to|connection to backend:|connect to remote machine) ?([^\s,()]+)"
+            ).unwrap(),
+
+            timestamps,
+        }
+    }
+
+    /// Split `10.0.0.1:5678` or `::1:5678` into address and port; 2.2 logs
+    /// the address alone
+    fn split_port(address: &str) -> (&str, Option<&str>) {
+        match address.rsplit_once(':') {
+            Some((host, port)) if port.parse::<u16>().is_ok() && host.parse::<IpAddr>().is_ok() => (host, Some(port)),
+            _ => (address, None),
+        }
+    }
+}
+
+impl LineParser for ApacheErrorParser {
+    fn name(&self) -> &str {
+        "apache-error"
+    }
+
+    fn description(&self) -> &str {
+        "Apache error_log"
+    }
+
+    /// Parse a line, or return None if it is not an Apache error-log line
+    fn parse(&self, line: &str, line_number: usize) -> Option<LogEvent> {
+        let cap = self.line_regex.captures(line)?;
+
+        let timestamp = NaiveDateTime::parse_from_str(&cap[1], "%a %b %d %H:%M:%S%.f %Y")
+            .ok()
+            .and_then(|dt| self.timestamps.localize(dt));
+
+        let mut fields = BTreeMap::new();
+        fields.insert("severity".to_string(), cap[3].to_string());
+        for (key, index) in [("module", 2), ("pid", 4), ("tid", 5)] {
+            if let Some(m) = cap.get(index) {
+                fields.insert(key.to_string(), m.as_str().to_string());
+            }
+        }
+
+        // Context moves into fields so it doesn't split the pattern
+        for ctx in self.context_regex.captures_iter(&cap[6]) {
+            match &ctx[1] {
+                "client" => {
+                    let (client, port) = Self::split_port(&ctx[2]);
+                    fields.insert("client".to_string(), client.to_string());
+                    if let Some(port) = port {
+                        fields.insert("client_port".to_string(), port.to_string());
+                    }
+                }
+                "remote" => {
+                    fields.insert("remote".to_string(), ctx[2].to_string());
+                }
+                "C" => {
+                    fields.insert("connection".to_string(), ctx[2].to_string());
+                }
+                _ => {
+                    fields.insert("request_id".to_string(), ctx[2].to_string());
+                }
+            }
+        }
+        let mut body = self.context_regex.replace_all(&cap[6], "").into_owned();
+
+        if let Some(referer) = self.referer_regex.captures(&body) {
+            fields.insert("referrer".to_string(), referer[1].to_string());
+            body.truncate(referer.get(0).unwrap().start());
+        }
+
+        if let Some(code) = self.code_regex.captures(&body) {
+            fields.insert("code".to_string(), code[1].to_string());
+        }
+        if let Some(upstream) = self.upstream_regex.captures(&body) {
+            fields.insert("upstream".to_string(), upstream[1].to_string());
+        }
+
+        Some(LogEvent {
+            timestamp,
+            level: LogLevel::from_str(&cap[3]),
+            message: body.trim().to_string(),
+            raw: line.to_string(),
+            line_number,
+            fields,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> LogEvent {
+        ApacheErrorParser::new(TimestampParser::new()).parse(line, 1).unwrap()
+    }
+
+    fn field<'a>(event: &'a LogEvent, key: &str) -> Option<&'a str> {
+        event.fields.get(key).map(String::as_str)
+    }
+
+    #[test]
+    fn extracts_the_client_and_upstream() {
+        let event = parse(
+            "[Fri Jan 05 12:01:03.123456 2024] [proxy_http:error] [pid 123:tid 456] (111)Connection refused: [client 10.0.0.1:5678] AH01114: HTTP: failed to make connection to backend: app1, referer: http://example.com/cart",
+        );
+
+        assert_eq!(event.timestamp.unwrap().to_rfc3339(), "2024-01-05T12:01:03.123456+00:00");
+        assert_eq!(event.level, Some(LogLevel::Error));
+        assert_eq!(event.message, "(111)Connection refused: AH01114: HTTP: failed to make connection to backend: app1");
+        assert_eq!(field(&event, "module"), Some("proxy_http"));
+        assert_eq!((field(&event, "pid"), field(&event, "tid")), (Some("123"), Some("456")));
+        assert_eq!((field(&event, "client"), field(&event, "client_port")), (Some("10.0.0.1"), Some("5678")));
+        assert_eq!(field(&event, "code"), Some("AH01114"));
+        assert_eq!(field(&event, "upstream"), Some("app1"));
+        assert_eq!(field(&event, "referrer"), Some("http://example.com/cart"));
+    }
+
+    #[test]
+    fn extracts_connection_and_request_log_ids() {
+        let event = parse(
+            "[Fri Jan 05 12:01:03 2024] [proxy:error] [C:ZZfa1] [R:ZZfa2] [remote 127.0.0.1:8080] AH00957: HTTP: attempt to connect to 127.0.0.1:8080 (localhost) failed",
+        );
+
+        assert_eq!(event.message, "AH00957: HTTP: attempt to connect to 127.0.0.1:8080 (localhost) failed");
+        assert_eq!(field(&event, "connection"), Some("ZZfa1"));
+        assert_eq!(field(&event, "request_id"), Some("ZZfa2"));
+        assert_eq!(field(&event, "remote"), Some("127.0.0.1:8080"));
+        assert_eq!(field(&event, "upstream"), Some("127.0.0.1:8080"));
+    }
+
+    #[test]
+    fn parses_apache_2_2_lines() {
+        let event = parse("[Fri Jan 05 12:01:03 2024] [error] [client ::1] File does not exist: /var/www/favicon.ico");
+
+        assert_eq!(event.message, "File does not exist: /var/www/favicon.ico");
+        assert_eq!((field(&event, "client"), field(&event, "client_port")), (Some("::1"), None));
+        assert_eq!(field(&event, "module"), None);
+        assert!(ApacheErrorParser::new(TimestampParser::new()).parse("2024/01/05 12:01:03 [error] 1#0: boom", 1).is_none());
+    }
+}
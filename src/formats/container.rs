@@ -0,0 +1,298 @@
+use super::LineParser;
+use super::detect::sample_share;
+use super::json::JsonLineParser;
+use super::logfmt::LogfmtParser;
+use super::plain::PlainParser;
+use super::registry::CompositeParser;
+use super::timestamp::TimestampParser;
+use crate::models::LogEvent;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// One physical line of a container runtime log, unwrapped
+#[derive(Debug, Clone)]
+pub struct ContainerLine {
+    pub timestamp: Option<DateTime<Utc>>,
+    /// stdout or stderr
+    pub stream: String,
+    /// The line continues on the next physical line
+    pub partial: bool,
+    pub payload: String,
+}
+
+/// Unwraps Kubernetes CRI and Docker json-file log lines
+pub struct ContainerLogDecoder {
+    /// Regex for CRI lines
+    cri_regex: Regex,
+}
+
+impl ContainerLogDecoder {
+    pub fn new() -> Self {
+        Self {
+            // Matches: 2024-01-05T12:01:03.123456789Z stderr F message
+            cri_regex: Regex::new(
+                r"^(\d{4}-\d{2}-\d{2}T\S+) (stdout|stderr) ([PF]) ?(.*)$"
+            ).unwrap(),
+        }
+    }
+
+    /// Unwrap a CRI or Docker json-file line, or return None for other formats
+    pub fn decode(&self, line: &str) -> Option<ContainerLine> {
+        self.decode_cri(line).or_else(|| self.decode_docker(line))
+    }
+
+    /// Unwrap a Kubernetes CRI line
+    pub fn decode_cri(&self, line: &str) -> Option<ContainerLine> {
+        let cap = self.cri_regex.captures(line)?;
+
+        Some(ContainerLine {
+            timestamp: DateTime::parse_from_rfc3339(&cap[1]).ok().map(|dt| dt.with_timezone(&Utc)),
+            stream: cap[2].to_string(),
+            partial: &cap[3] == "P",
+            payload: cap[4].to_string(),
+        })
+    }
+
+    /// Unwrap a Docker json-file line
+    pub fn decode_docker(&self, line: &str) -> Option<ContainerLine> {
+        if !line.starts_with('{') {
+            return None;
+        }
+
+        // Docker splits lines over 16KB; only the last chunk ends with a newline
+        let entry: DockerEntry = serde_json::from_str(line).ok()?;
+        let partial = !entry.log.ends_with('\n');
+        Some(ContainerLine {
+            timestamp: DateTime::parse_from_rfc3339(&entry.time).ok().map(|dt| dt.with_timezone(&Utc)),
+            stream: entry.stream,
+            partial,
+            payload: entry.log.trim_end_matches(['\n', '\r']).to_string(),
+        })
+    }
+}
+
+/// Container runtime log layouts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerRuntime {
+    Cri,
+    Docker,
+}
+
+/// Parses container runtime logs: lines are unwrapped, partial lines
+/// reassembled and the payload parsed as JSON, logfmt or plain text
+pub struct ContainerLogParser {
+    runtime: ContainerRuntime,
+    decoder: ContainerLogDecoder,
+    /// The payload's own format is not known up front: structured formats are tried first
+    payload: CompositeParser,
+}
+
+impl ContainerLogParser {
+    pub fn new(runtime: ContainerRuntime, timestamps: &TimestampParser) -> Self {
+        let payload: Vec<Arc<dyn LineParser>> = vec![
+            Arc::new(JsonLineParser::new(timestamps.clone())),
+            Arc::new(LogfmtParser::new(timestamps.clone())),
+            Arc::new(PlainParser::new(timestamps.clone())),
+        ];
+
+        Self {
+            runtime,
+            decoder: ContainerLogDecoder::new(),
+            payload: CompositeParser::new("payload", payload),
+        }
+    }
+}
+
+impl LineParser for ContainerLogParser {
+    fn name(&self) -> &str {
+        match self.runtime {
+            ContainerRuntime::Cri => "cri",
+            ContainerRuntime::Docker => "docker",
+        }
+    }
+
+    fn description(&self) -> &str {
+        match self.runtime {
+            ContainerRuntime::Cri => "Kubernetes CRI container logs",
+            ContainerRuntime::Docker => "Docker json-file container logs",
+        }
+    }
+
+    fn parse(&self, line: &str, line_number: usize) -> Option<LogEvent> {
+        self.parse_record(&[(line_number, line.to_string())])
+    }
+
+    fn confidence(&self, sample: &[String]) -> f64 {
+        sample_share(sample, |line| match self.runtime {
+            ContainerRuntime::Cri => self.decoder.decode_cri(line).is_some(),
+            ContainerRuntime::Docker => self.decoder.decode_docker(line).is_some(),
+        })
+    }
+
+    fn is_partial(&self, line: &str) -> bool {
+        self.decoder.decode(line).is_some_and(|entry| entry.partial)
+    }
+
+    fn record_key(&self, line: &str) -> Option<String> {
+        self.decoder.decode(line).map(|entry| entry.stream)
+    }
+
+    /// Join the payloads of one stream's partial lines, which ingest groups
+    /// by `record_key`, and parse the result
+    fn parse_record(&self, lines: &[(usize, String)]) -> Option<LogEvent> {
+        let (line_number, _) = lines.first()?;
+        let entries: Vec<ContainerLine> = lines.iter().map(|(_, line)| self.decoder.decode(line)).collect::<Option<_>>()?;
+        let payload: String = entries.iter().map(|entry| entry.payload.as_str()).collect();
+        let raw: Vec<&str> = lines.iter().map(|(_, line)| line.as_str()).collect();
+
+        let mut event = self.payload.parse(&payload, *line_number)?;
+        event.raw = raw.join("\n");
+        event.timestamp = event.timestamp.or(entries[0].timestamp);
+        event.fields.insert("stream".to_string(), entries[0].stream.clone());
+
+        Some(event)
+    }
+}
+
+/// Derive pod, namespace and container names from the kubelet's file layout:
+/// `/var/log/containers/<pod>_<namespace>_<container>-<id>.log` or
+/// `/var/log/pods/<namespace>_<pod>_<uid>/<container>/<n>.log`
+pub fn kubernetes_metadata(path: &str) -> BTreeMap<String, String> {
+    let mut fields = BTreeMap::new();
+    let parts: Vec<&str> = path.split('/').collect();
+
+    // .../containers/<pod>_<namespace>_<container>-<id>.log
+    if let Some(i) = parts.iter().rposition(|p| *p == "containers")
+        && let Some(stem) = parts.get(i + 1).and_then(|f| f.strip_suffix(".log"))
+    {
+        let segments: Vec<&str> = stem.splitn(3, '_').collect();
+        if let [pod, namespace, rest] = segments[..]
+            && let Some((container, id)) = rest.rsplit_once('-')
+        {
+            fields.insert("pod".to_string(), pod.to_string());
+            fields.insert("namespace".to_string(), namespace.to_string());
+            fields.insert("container".to_string(), container.to_string());
+            fields.insert("container_id".to_string(), id.to_string());
+            return fields;
+        }
+    }
+
+    // .../pods/<namespace>_<pod>_<uid>/<container>/<n>.log
+    if let Some(i) = parts.iter().rposition(|p| *p == "pods")
+        && let [dir, container, _file] = parts[i + 1..]
+    {
+        let segments: Vec<&str> = dir.splitn(3, '_').collect();
+        if let [namespace, pod, _uid] = segments[..] {
+            fields.insert("namespace".to_string(), namespace.to_string());
+            fields.insert("pod".to_string(), pod.to_string());
+            fields.insert("container".to_string(), container.to_string());
+        }
+    }
+
+    fields
+}
+
+/// Docker json-file log entry
+#[derive(Deserialize)]
+struct DockerEntry {
+    log: String,
+    stream: String,
+    time: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::LogLevel;
+
+    fn parser(runtime: ContainerRuntime) -> ContainerLogParser {
+        ContainerLogParser::new(runtime, &TimestampParser::new())
+    }
+
+    fn numbered(lines: &[(usize, &str)]) -> Vec<(usize, String)> {
+        lines.iter().map(|(n, line)| (*n, line.to_string())).collect()
+    }
+
+    #[test]
+    fn unwraps_docker_json_lines() {
+        let decoder = ContainerLogDecoder::new();
+
+        let line = decoder
+            .decode(r#"{"log":"db timeout\n","stream":"stderr","time":"2024-01-05T12:01:03.123Z"}"#)
+            .unwrap();
+        assert_eq!((line.payload.as_str(), line.stream.as_str(), line.partial), ("db timeout", "stderr", false));
+        assert_eq!(line.timestamp.unwrap().to_rfc3339(), "2024-01-05T12:01:03.123+00:00");
+
+        // Chunks of a long line lack the trailing newline
+        let chunk = decoder.decode(r#"{"log":"first 16KB","stream":"stdout","time":"2024-01-05T12:01:03Z"}"#).unwrap();
+        assert!(chunk.partial);
+
+        assert!(decoder.decode(r#"{"msg":"not docker"}"#).is_none());
+        assert!(decoder.decode_docker("2024-01-05T12:01:03Z stdout F text").is_none());
+    }
+
+    #[test]
+    fn reassembles_cri_partial_lines() {
+        let cri = parser(ContainerRuntime::Cri);
+        assert!(cri.is_partial("2024-01-05T12:01:03.000000000Z stdout P first half"));
+        assert!(!cri.is_partial("2024-01-05T12:01:04.000000000Z stdout F  second half"));
+
+        let lines = numbered(&[
+            (2, "2024-01-05T12:01:03.000000000Z stdout P ERROR first half"),
+            (4, "2024-01-05T12:01:04.000000000Z stdout F  second half"),
+        ]);
+        let event = cri.parse_record(&lines).unwrap();
+
+        assert_eq!((event.level, event.message.as_str()), (Some(LogLevel::Error), "first half second half"));
+        assert_eq!(event.line_number, 2);
+        assert_eq!(event.raw, format!("{}\n{}", lines[0].1, lines[1].1));
+        assert_eq!(event.timestamp.unwrap().to_rfc3339(), "2024-01-05T12:01:03+00:00");
+        assert_eq!(event.fields.get("stream").map(String::as_str), Some("stdout"));
+    }
+
+    #[test]
+    fn keys_records_by_stream() {
+        let cri = parser(ContainerRuntime::Cri);
+
+        assert_eq!(cri.record_key("2024-01-05T12:01:03Z stdout P a").as_deref(), Some("stdout"));
+        assert_eq!(cri.record_key("2024-01-05T12:01:03Z stderr F b").as_deref(), Some("stderr"));
+        assert_eq!(cri.record_key("not a container line"), None);
+    }
+
+    #[test]
+    fn parses_json_and_logfmt_payloads() {
+        let cri = parser(ContainerRuntime::Cri);
+
+        let json = cri
+            .parse(r#"2024-01-05T12:01:03Z stderr F {"level":"error","msg":"db timeout","user":"u1"}"#, 1)
+            .unwrap();
+        assert_eq!((json.level, json.message.as_str()), (Some(LogLevel::Error), "db timeout"));
+        assert_eq!(json.fields.get("user").map(String::as_str), Some("u1"));
+        assert_eq!(json.fields.get("stream").map(String::as_str), Some("stderr"));
+
+        let docker = parser(ContainerRuntime::Docker);
+        let logfmt = docker
+            .parse(r#"{"log":"time=2024-01-05T12:01:09Z level=warn msg=\"disk low\" free=5%\n","stream":"stdout","time":"2024-01-05T12:01:10Z"}"#, 1)
+            .unwrap();
+        assert_eq!((logfmt.level, logfmt.message.as_str()), (Some(LogLevel::Warn), "disk low"));
+        assert_eq!(logfmt.fields.get("free").map(String::as_str), Some("5%"));
+        // The payload's own timestamp wins over the runtime's
+        assert_eq!(logfmt.timestamp.unwrap().to_rfc3339(), "2024-01-05T12:01:09+00:00");
+    }
+
+    #[test]
+    fn reads_kubernetes_metadata_from_the_path() {
+        let fields = kubernetes_metadata("/var/log/containers/web-7d9f_shop_nginx-0123abcd.log");
+        assert_eq!(fields.get("pod").map(String::as_str), Some("web-7d9f"));
+        assert_eq!(fields.get("namespace").map(String::as_str), Some("shop"));
+        assert_eq!(fields.get("container").map(String::as_str), Some("nginx"));
+        assert_eq!(fields.get("container_id").map(String::as_str), Some("0123abcd"));
+
+        let fields = kubernetes_metadata("/var/log/pods/shop_web-7d9f_5f1e/nginx/0.log");
+        assert_eq!(fields.get("pod").map(String::as_str), Some("web-7d9f"));
+        assert!(kubernetes_metadata("/var/log/app.log").is_empty());
+    }
+}
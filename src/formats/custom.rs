@@ -0,0 +1,291 @@
+use super::timestamp::TimestampParser;
+use super::{LineParser, parse_level_value};
+use crate::config::FormatConfig;
+use crate::models::LogEvent;
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+/// Capture names holding the timestamp, level and message
+const TIMESTAMP_CAPTURES: [&str; 4] = ["timestamp", "ts", "time", "datetime"];
+const LEVEL_CAPTURES: [&str; 3] = ["level", "severity", "loglevel"];
+const MESSAGE_CAPTURES: [&str; 2] = ["message", "msg"];
+
+/// Maximum nesting of grok pattern references
+const MAX_GROK_DEPTH: usize = 16;
+
+/// A `%{PATTERN}` or `%{PATTERN:field}` reference
+static GROK_REFERENCE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"%\{(\w+)(?::([\w.@-]+))?\}").unwrap());
+
+/// Built-in grok patterns (a subset of Logstash's grok-patterns)
+const GROK_PATTERNS: &[(&str, &str)] = &[
+    ("USERNAME", r"[a-zA-Z0-9._-]+"),
+    ("USER", r"%{USERNAME}"),
+    ("INT", r"(?:[+-]?(?:[0-9]+))"),
+    ("BASE10NUM", r"(?:[+-]?(?:[0-9]+(?:\.[0-9]+)?|\.[0-9]+))"),
+    ("NUMBER", r"(?:%{BASE10NUM})"),
+    ("POSINT", r"\b(?:[1-9][0-9]*)\b"),
+    ("NONNEGINT", r"\b(?:[0-9]+)\b"),
+    ("WORD", r"\b\w+\b"),
+    ("NOTSPACE", r"\S+"),
+    ("SPACE", r"\s*"),
+    ("DATA", r".*?"),
+    ("GREEDYDATA", r".*"),
+    ("QUOTEDSTRING", r#"(?:"(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*')"#),
+    ("UUID", r"[A-Fa-f0-9]{8}-(?:[A-Fa-f0-9]{4}-){3}[A-Fa-f0-9]{12}"),
+    ("IPV4", r"(?:(?:25[0-5]|2[0-4][0-9]|1?[0-9]?[0-9])\.){3}(?:25[0-5]|2[0-4][0-9]|1?[0-9]?[0-9])"),
+    ("IPV6", r"(?:[0-9A-Fa-f]{0,4}:){2,7}[0-9A-Fa-f.]{0,15}"),
+    ("IP", r"(?:%{IPV6}|%{IPV4})"),
+    ("HOSTNAME", r"\b(?:[0-9A-Za-z][0-9A-Za-z-]{0,62})(?:\.(?:[0-9A-Za-z][0-9A-Za-z-]{0,62}))*\.?"),
+    ("IPORHOST", r"(?:%{IP}|%{HOSTNAME})"),
+    ("HOSTPORT", r"%{IPORHOST}:%{POSINT}"),
+    ("PATH", r"(?:/[^\s]*)+"),
+    ("URIPATH", r"(?:/[A-Za-z0-9$.+!*'(){},~:;=@#%&_\-]*)+"),
+    ("MONTH", r"\b(?:Jan(?:uary)?|Feb(?:ruary)?|Mar(?:ch)?|Apr(?:il)?|May|Jun(?:e)?|Jul(?:y)?|Aug(?:ust)?|Sep(?:tember)?|Oct(?:ober)?|Nov(?:ember)?|Dec(?:ember)?)\b"),
+    ("MONTHNUM", r"(?:0?[1-9]|1[0-2])"),
+    ("MONTHDAY", r"(?:(?:0[1-9])|(?:[12][0-9])|(?:3[01])|[1-9])"),
+    ("DAY", r"(?:Mon(?:day)?|Tue(?:sday)?|Wed(?:nesday)?|Thu(?:rsday)?|Fri(?:day)?|Sat(?:urday)?|Sun(?:day)?)"),
+    ("YEAR", r"(?:\d\d){1,2}"),
+    ("HOUR", r"(?:2[0123]|[01]?[0-9])"),
+    ("MINUTE", r"(?:[0-5][0-9])"),
+    ("SECOND", r"(?:(?:[0-5]?[0-9]|60)(?:[:.,][0-9]+)?)"),
+    ("TIME", r"%{HOUR}:%{MINUTE}(?::%{SECOND})?"),
+    ("ISO8601_TIMEZONE", r"(?:Z|[+-]%{HOUR}(?::?%{MINUTE}))"),
+    ("TIMESTAMP_ISO8601", r"%{YEAR}-%{MONTHNUM}-%{MONTHDAY}[T ]%{HOUR}:?%{MINUTE}(?::?%{SECOND})?%{ISO8601_TIMEZONE}?"),
+    ("DATE_US", r"%{MONTHNUM}[/-]%{MONTHDAY}[/-]%{YEAR}"),
+    ("DATE_EU", r"%{MONTHDAY}[./-]%{MONTHNUM}[./-]%{YEAR}"),
+    ("DATE", r"(?:%{DATE_US}|%{DATE_EU})"),
+    ("DATESTAMP", r"%{DATE}[- ]%{TIME}"),
+    ("SYSLOGTIMESTAMP", r"%{MONTH} +%{MONTHDAY} %{TIME}"),
+    ("HTTPDATE", r"%{MONTHDAY}/%{MONTH}/%{YEAR}:%{TIME} %{INT}"),
+    ("LOGLEVEL", r"(?:[Aa]lert|ALERT|[Tt]race|TRACE|[Dd]ebug|DEBUG|[Nn]otice|NOTICE|[Ii]nfo|INFO|[Ww]arn?(?:ing)?|WARN?(?:ING)?|[Ee]rr?(?:or)?|ERR?(?:OR)?|[Cc]rit?(?:ical)?|CRIT?(?:ICAL)?|[Ff]atal|FATAL|[Ss]evere|SEVERE|EMERG(?:ENCY)?|[Ee]merg(?:ency)?)"),
+];
+
+/// A user-defined line format: a regex with named captures
+pub struct CustomFormat {
+    pub name: String,
+    regex: Regex,
+    /// strftime format of the timestamp capture
+    time_format: Option<String>,
+    timestamps: TimestampParser,
+}
+
+impl CustomFormat {
+    /// Build from a config entry holding either `regex` or `grok`
+    pub fn from_config(config: &FormatConfig, timestamps: &TimestampParser) -> Result<Self> {
+        let time_format = config.time_format.clone();
+        match (&config.regex, &config.grok) {
+            (Some(regex), None) => Self::from_regex(&config.name, regex, time_format, timestamps),
+            (None, Some(grok)) => Self::from_grok(&config.name, grok, time_format, timestamps),
+            _ => Err(anyhow!("Format '{}' needs exactly one of `regex` or `grok`", config.name)),
+        }
+    }
+
+    /// Build from a regex with named captures (`timestamp`, `level`, `message`, ...)
+    pub fn from_regex(
+        name: &str,
+        pattern: &str,
+        time_format: Option<String>,
+        timestamps: &TimestampParser,
+    ) -> Result<Self> {
+        let regex = Regex::new(pattern)
+            .with_context(|| format!("Invalid regex for format '{}'", name))?;
+
+        Ok(Self {
+            name: name.to_string(),
+            regex,
+            time_format,
+            timestamps: timestamps.clone(),
+        })
+    }
+
+    /// Build from a grok pattern such as `%{TIMESTAMP_ISO8601:ts} %{LOGLEVEL:level} %{GREEDYDATA:msg}`
+    pub fn from_grok(
+        name: &str,
+        grok: &str,
+        time_format: Option<String>,
+        timestamps: &TimestampParser,
+    ) -> Result<Self> {
+        let pattern = expand_grok(grok, 0)
+            .with_context(|| format!("Invalid grok pattern for format '{}'", name))?;
+
+        Self::from_regex(name, &pattern, time_format, timestamps)
+    }
+
+    fn parse_timestamp(&self, value: &str) -> Option<DateTime<Utc>> {
+        match self.time_format {
+            Some(ref format) => self.timestamps.parse_with_format(value, format),
+            None => self.timestamps.parse(value),
+        }
+    }
+}
+
+impl LineParser for CustomFormat {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        "Custom format from the config or the command line"
+    }
+
+    /// Parse a line, or return None if the regex doesn't match
+    fn parse(&self, line: &str, line_number: usize) -> Option<LogEvent> {
+        let cap = self.regex.captures(line)?;
+
+        let mut timestamp = None;
+        let mut level = None;
+        let mut message = None;
+        let mut fields = BTreeMap::new();
+
+        for name in self.regex.capture_names().flatten() {
+            let Some(value) = cap.name(name).map(|m| m.as_str()) else {
+                continue;
+            };
+
+            if TIMESTAMP_CAPTURES.contains(&name) {
+                timestamp = self.parse_timestamp(value);
+            } else if LEVEL_CAPTURES.contains(&name) {
+                level = parse_level_value(value);
+            } else if MESSAGE_CAPTURES.contains(&name) {
+                message = Some(value.trim().to_string());
+            } else {
+                fields.insert(name.to_string(), value.to_string());
+            }
+        }
+
+        Some(LogEvent {
+            timestamp,
+            level,
+            message: message.unwrap_or_else(|| line.to_string()),
+            raw: line.to_string(),
+            line_number,
+            fields,
+        })
+    }
+}
+
+/// Expand `%{PATTERN}` and `%{PATTERN:field}` references into a regex
+fn expand_grok(grok: &str, depth: usize) -> Result<String> {
+    if depth > MAX_GROK_DEPTH {
+        return Err(anyhow!("Grok patterns nested too deeply"));
+    }
+
+    let mut result = String::new();
+    let mut last = 0;
+
+    for cap in GROK_REFERENCE.captures_iter(grok) {
+        let whole = cap.get(0).unwrap();
+        result.push_str(&grok[last..whole.start()]);
+        last = whole.end();
+
+        let definition = GROK_PATTERNS
+            .iter()
+            .find(|(name, _)| *name == &cap[1])
+            .map(|(_, pattern)| *pattern)
+            .ok_or_else(|| anyhow!("Unknown grok pattern %{{{}}}", &cap[1]))?;
+        let expanded = expand_grok(definition, depth + 1)?;
+
+        match cap.get(2) {
+            Some(field) => {
+                let group: String = field
+                    .as_str()
+                    .chars()
+                    .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
+                    .collect();
+                result.push_str(&format!("(?P<{}>{})", group, expanded));
+            }
+            None => result.push_str(&format!("(?:{})", expanded)),
+        }
+    }
+
+    result.push_str(&grok[last..]);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::models::LogLevel;
+
+    fn grok(pattern: &str) -> CustomFormat {
+        CustomFormat::from_grok("test", pattern, None, &TimestampParser::new()).unwrap()
+    }
+
+    #[test]
+    fn expands_nested_grok_references() {
+        // IPORHOST refers to IP, which refers to IPV6 and IPV4
+        let expanded = expand_grok("%{IPORHOST:client} %{INT}", 0).unwrap();
+        assert!(expanded.starts_with("(?P<client>(?:(?:"), "{}", expanded);
+        assert!(!expanded.contains("%{"));
+
+        let format = grok("%{TIMESTAMP_ISO8601:ts} %{LOGLEVEL:level} %{IPORHOST:client.ip} %{GREEDYDATA:msg}");
+        let event = format.parse("2024-01-05T12:01:03Z WARN 10.0.0.1 slow request", 7).unwrap();
+
+        assert_eq!(event.timestamp.unwrap().to_rfc3339(), "2024-01-05T12:01:03+00:00");
+        assert_eq!(event.level, Some(LogLevel::Warn));
+        assert_eq!(event.message, "slow request");
+        assert_eq!(event.line_number, 7);
+        // Dots aren't allowed in capture names
+        assert_eq!(event.fields.get("client_ip").map(String::as_str), Some("10.0.0.1"));
+    }
+
+    #[test]
+    fn rejects_unknown_grok_patterns() {
+        let error = CustomFormat::from_grok("test", "%{NOPE:x} %{INT}", None, &TimestampParser::new())
+            .err()
+            .unwrap();
+        assert!(format!("{:#}", error).contains("Unknown grok pattern %{NOPE}"), "{:#}", error);
+    }
+
+    #[test]
+    fn tolerates_missing_captures() {
+        // No message capture: the whole line is the message
+        let format = grok(r"%{TIMESTAMP_ISO8601:ts} job=%{WORD:job}(?: took %{INT:ms}ms)?");
+
+        let event = format.parse("2024-01-05T12:01:03Z job=backup", 1).unwrap();
+        assert_eq!(event.message, "2024-01-05T12:01:03Z job=backup");
+        assert_eq!(event.level, None);
+        assert_eq!(event.fields.get("job").map(String::as_str), Some("backup"));
+        assert_eq!(event.fields.get("ms"), None);
+
+        assert!(format.parse("no timestamp here", 2).is_none());
+    }
+
+    #[test]
+    fn builds_formats_from_the_config() {
+        let config: Config = toml::from_str(
+            r#"
+            [[formats]]
+            name = "worker"
+            grok = "%{DATESTAMP:ts} \\[%{WORD:thread}\\] %{LOGLEVEL:level}: %{GREEDYDATA:msg}"
+            time_format = "%d.%m.%Y %H:%M:%S"
+
+            [[formats]]
+            name = "legacy"
+            regex = '^(?P<level>\w+)\|(?P<msg>.*)$'
+
+            [[formats]]
+            name = "broken"
+            regex = "x"
+            grok = "%{INT}"
+            "#,
+        )
+        .unwrap();
+        let timestamps = TimestampParser::new();
+
+        let worker = CustomFormat::from_config(&config.formats[0], &timestamps).unwrap();
+        let event = worker.parse("05.01.2024 12:01:03 [main] ERROR: queue full", 1).unwrap();
+        assert_eq!(event.timestamp.unwrap().to_rfc3339(), "2024-01-05T12:01:03+00:00");
+        assert_eq!(event.level, Some(LogLevel::Error));
+        assert_eq!(event.fields.get("thread").map(String::as_str), Some("main"));
+
+        let legacy = CustomFormat::from_config(&config.formats[1], &timestamps).unwrap();
+        assert_eq!(legacy.parse("warn|disk low", 1).unwrap().message, "disk low");
+
+        assert!(CustomFormat::from_config(&config.formats[2], &timestamps).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+use super::LineParser;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Lines sampled from the start of an input to detect its format
+pub const SAMPLE_LINES: usize = 50;
+/// Minimum share of sampled lines a dedicated format must parse to be chosen
+pub const MIN_CONFIDENCE: f64 = 0.5;
+
+/// The format chosen for an input and how it was chosen
+#[derive(Debug, Clone, Serialize)]
+pub struct FormatDetection {
+    /// Name of the parser used
+    pub format: String,
+    /// Share of sampled lines the format parsed (1.0 when given explicitly)
+    pub confidence: f64,
+    /// Whether the format was detected rather than given with `--format`
+    pub detected: bool,
+}
+
+impl FormatDetection {
+    pub fn explicit(format: &str) -> Self {
+        Self {
+            format: format.to_string(),
+            confidence: 1.0,
+            detected: false,
+        }
+    }
+
+    /// Choose the best candidate from per-format scores, in priority order,
+    /// falling back to `fallback` when no format parses enough of the sample
+    pub fn from_scores(scores: &[(&str, f64)], fallback: (&str, f64)) -> Self {
+        let mut best = fallback;
+        let mut best_dedicated = 0.0;
+
+        for &(format, score) in scores {
+            if score > best_dedicated {
+                best_dedicated = score;
+                if score >= MIN_CONFIDENCE {
+                    best = (format, score);
+                }
+            }
+        }
+
+        Self {
+            format: best.0.to_string(),
+            confidence: best.1,
+            detected: true,
+        }
+    }
+}
+
+/// Score every parser on a sample of lines and choose a format. The
+/// fallback parser is only used when no other parser is confident enough.
+pub fn detect_sample(parsers: &[Arc<dyn LineParser>], fallback: &dyn LineParser, sample: &[String]) -> FormatDetection {
+    // Parsers are scored in priority order, so earlier ones win ties
+    let scores: Vec<(&str, f64)> = parsers
+        .iter()
+        .filter(|p| p.name() != fallback.name())
+        .map(|p| (p.name(), p.confidence(sample)))
+        .collect();
+
+    FormatDetection::from_scores(&scores, (fallback.name(), fallback.confidence(sample)))
+}
+
+/// Share of sampled lines matching a predicate
+pub fn sample_share(sample: &[String], accepts: impl Fn(&str) -> bool) -> f64 {
+    if sample.is_empty() {
+        return 0.0;
+    }
+
+    sample.iter().filter(|line| accepts(line)).count() as f64 / sample.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::plain::PlainParser;
+    use crate::formats::registry::ParserRegistry;
+    use crate::formats::timestamp::TimestampParser;
+
+    fn detect(lines: &[&str]) -> FormatDetection {
+        let registry = ParserRegistry::new().with_builtins(&TimestampParser::new());
+        let sample: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        detect_sample(registry.parsers(), &PlainParser::new(TimestampParser::new()), &sample)
+    }
+
+    #[test]
+    fn detects_each_builtin_format() {
+        let samples: [(&str, &[&str]); 9] = [
+            ("journal-json", &[r#"{"__REALTIME_TIMESTAMP":"1704456063000000","PRIORITY":"3","MESSAGE":"boom"}"#]),
+            ("docker", &[r#"{"log":"db timeout\n","stream":"stderr","time":"2024-01-05T12:01:03.123Z"}"#]),
+            ("cri", &["2024-01-05T12:01:03.123456789Z stderr F db timeout", "2024-01-05T12:01:04.5Z stdout P half"]),
+            ("json", &[r#"{"time":"2024-01-05T12:01:03Z","level":"error","msg":"db timeout"}"#]),
+            ("nginx-error", &["2024/01/05 12:01:03 [error] 1234#0: *5 connect() failed, client: 10.0.0.1"]),
+            ("apache-error", &["[Fri Jan 05 12:01:03 2024] [error] [client 10.0.0.1] File does not exist: /x"]),
+            ("syslog", &["<34>Jan  5 12:01:03 web-1 sshd[42]: Failed password for root", "Jan  5 12:01:04 web-1 cron[7]: job done"]),
+            ("clf", &[r#"10.0.0.1 - - [05/Jan/2024:12:01:03 +0000] "GET /cart HTTP/1.1" 500 12 "-" "curl/8.0""#]),
+            ("logfmt", &[r#"time=2024-01-05T12:01:03Z level=error msg="db timeout" user=u1"#]),
+        ];
+
+        for (format, lines) in samples {
+            let detection = detect(lines);
+            assert_eq!(detection.format, format, "{:?}", lines);
+            assert_eq!(detection.confidence, 1.0);
+            assert!(detection.detected);
+        }
+    }
+
+    #[test]
+    fn falls_back_to_plain_text() {
+        let detection = detect(&["2024-01-05 12:01:03 ERROR db timeout", "something happened", "key=value"]);
+        assert_eq!(detection.format, "plain");
+
+        // A minority of structured lines isn't enough
+        let detection = detect(&[r#"{"msg":"a"}"#, "free text", "more free text"]);
+        assert_eq!(detection.format, "plain");
+        assert_eq!(detect(&[]).format, "plain");
+    }
+
+    #[test]
+    fn prefers_earlier_formats_on_ties() {
+        let detection = FormatDetection::from_scores(&[("a", 0.4), ("b", 0.8), ("c", 0.8)], ("plain", 0.2));
+        assert_eq!((detection.format.as_str(), detection.confidence), ("b", 0.8));
+
+        let detection = FormatDetection::from_scores(&[("a", 0.4)], ("plain", 0.9));
+        assert_eq!(detection.format, "plain");
+
+        let explicit = FormatDetection::explicit("logfmt");
+        assert_eq!((explicit.format.as_str(), explicit.confidence, explicit.detected), ("logfmt", 1.0, false));
+    }
+}
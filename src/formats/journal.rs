@@ -0,0 +1,276 @@
+use super::LineParser;
+use crate::models::{LogEvent, LogLevel};
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::{BufRead, Read};
+
+/// Journal fields mapped onto structured event fields
+const FIELD_MAP: [(&str, &str); 5] = [
+    ("_SYSTEMD_UNIT", "unit"),
+    ("_HOSTNAME", "hostname"),
+    ("_PID", "pid"),
+    ("SYSLOG_IDENTIFIER", "identifier"),
+    ("_TRANSPORT", "transport"),
+];
+
+/// Largest binary field accepted from `journalctl -o export`, far above any
+/// real log message
+const MAX_BINARY_FIELD: u64 = 64 * 1024 * 1024;
+
+/// Whether the start of a file looks like `journalctl -o export` output
+pub fn is_export_format(head: &[u8]) -> bool {
+    head.starts_with(b"__CURSOR=") || head.starts_with(b"__REALTIME_TIMESTAMP=")
+}
+
+/// Build an event from decoded journal fields
+pub fn journal_event(fields: &BTreeMap<String, String>, line_number: usize) -> LogEvent {
+    let timestamp = fields
+        .get("__REALTIME_TIMESTAMP")
+        .and_then(|us| us.parse::<i64>().ok())
+        .and_then(DateTime::<Utc>::from_timestamp_micros);
+
+    let level = fields
+        .get("PRIORITY")
+        .and_then(|p| p.parse::<u8>().ok())
+        .map(LogLevel::from_syslog_severity);
+
+    let message = fields.get("MESSAGE").cloned().unwrap_or_default();
+
+    let mut event_fields = BTreeMap::new();
+    for (journal_key, key) in FIELD_MAP {
+        if let Some(value) = fields.get(journal_key) {
+            event_fields.insert(key.to_string(), value.clone());
+        }
+    }
+
+    // Render like `journalctl -o short` for display and prompts
+    let source = event_fields
+        .get("unit")
+        .or_else(|| event_fields.get("identifier"))
+        .cloned()
+        .unwrap_or_default();
+    let raw = format!(
+        "{} {} {}{}: {}",
+        timestamp.map(|t| t.to_rfc3339()).unwrap_or_default(),
+        event_fields.get("hostname").map(String::as_str).unwrap_or("-"),
+        source,
+        event_fields.get("pid").map(|p| format!("[{}]", p)).unwrap_or_default(),
+        message
+    );
+
+    LogEvent {
+        timestamp,
+        level,
+        message,
+        raw,
+        line_number,
+        fields: event_fields,
+    }
+}
+
+/// Parses `journalctl -o json` lines
+pub struct JournalJsonParser;
+
+impl JournalJsonParser {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Strings stay as-is, binary fields arrive as byte arrays and repeated
+    /// fields as arrays of values (the first one wins)
+    fn decode_value(value: Value) -> Option<String> {
+        match value {
+            Value::String(s) => Some(s),
+            Value::Array(items) if items.iter().all(|v| v.is_u64()) => {
+                let bytes: Vec<u8> = items.iter().filter_map(|v| v.as_u64()).map(|b| b as u8).collect();
+                Some(String::from_utf8_lossy(&bytes).into_owned())
+            }
+            Value::Array(items) => items.into_iter().find_map(Self::decode_value),
+            Value::Null => None,
+            other => Some(other.to_string()),
+        }
+    }
+}
+
+impl LineParser for JournalJsonParser {
+    fn name(&self) -> &str {
+        "journal-json"
+    }
+
+    fn description(&self) -> &str {
+        "journalctl -o json dumps"
+    }
+
+    /// Parse a line, or return None if it is not a journal JSON record
+    fn parse(&self, line: &str, line_number: usize) -> Option<LogEvent> {
+        if !line.starts_with('{') || !line.contains("\"__REALTIME_TIMESTAMP\"") {
+            return None;
+        }
+
+        let Value::Object(object) = serde_json::from_str(line).ok()? else {
+            return None;
+        };
+
+        let fields = object
+            .into_iter()
+            .filter_map(|(key, value)| Self::decode_value(value).map(|v| (key, v)))
+            .collect();
+
+        Some(journal_event(&fields, line_number))
+    }
+}
+
+/// Reads records from `journalctl -o export` output, including binary fields
+pub struct JournalExportReader<R: BufRead> {
+    reader: R,
+    line_number: usize,
+}
+
+impl<R: BufRead> JournalExportReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader, line_number: 0 }
+    }
+
+    /// Read the next record as (fields, line number of its first field)
+    pub fn next_record(&mut self) -> Result<Option<(BTreeMap<String, String>, usize)>> {
+        let mut fields = BTreeMap::new();
+        let mut start = 0;
+        let mut line = Vec::new();
+
+        loop {
+            line.clear();
+            if self.reader.read_until(b'\n', &mut line)? == 0 {
+                return Ok((!fields.is_empty()).then_some((fields, start)));
+            }
+            self.line_number += 1;
+
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            }
+
+            // An empty line terminates the record
+            if line.is_empty() {
+                if fields.is_empty() {
+                    continue;
+                }
+                return Ok(Some((fields, start)));
+            }
+
+            if fields.is_empty() {
+                start = self.line_number;
+            }
+
+            match line.iter().position(|b| *b == b'=') {
+                Some(eq) => {
+                    let key = String::from_utf8_lossy(&line[..eq]).into_owned();
+                    let value = String::from_utf8_lossy(&line[eq + 1..]).into_owned();
+                    fields.insert(key, value);
+                }
+                None => {
+                    // Binary-safe field: NAME\n, little-endian u64 length, data, \n
+                    let key = String::from_utf8_lossy(&line).into_owned();
+                    let mut len_bytes = [0u8; 8];
+                    self.reader
+                        .read_exact(&mut len_bytes)
+                        .with_context(|| format!("Truncated binary journal field '{}'", key))?;
+                    let len = u64::from_le_bytes(len_bytes);
+                    if len > MAX_BINARY_FIELD {
+                        return Err(anyhow!("Binary journal field '{}' of {} bytes exceeds the {} byte limit", key, len, MAX_BINARY_FIELD));
+                    }
+
+                    // The length comes from the file, so read what is there rather than allocating it up front
+                    let mut data = Vec::new();
+                    (&mut self.reader).take(len + 1).read_to_end(&mut data)?;
+                    if data.len() as u64 != len + 1 {
+                        return Err(anyhow!("Truncated binary journal field '{}'", key));
+                    }
+                    if data.pop() != Some(b'\n') {
+                        return Err(anyhow!("Malformed binary journal field '{}'", key));
+                    }
+
+                    self.line_number += len_bytes.iter().chain(&data).filter(|b| **b == b'\n').count() + 1;
+                    fields.insert(key, String::from_utf8_lossy(&data).into_owned());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A binary-safe export field: NAME, newline, little-endian length, data, newline
+    fn binary_field(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut field = format!("{}\n", name).into_bytes();
+        field.extend((data.len() as u64).to_le_bytes());
+        field.extend(data);
+        field.push(b'\n');
+        field
+    }
+
+    fn records(export: Vec<u8>) -> Result<Vec<(BTreeMap<String, String>, usize)>> {
+        let mut reader = JournalExportReader::new(Cursor::new(export));
+        let mut records = Vec::new();
+        while let Some(record) = reader.next_record()? {
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    #[test]
+    fn reads_binary_export_fields() {
+        let mut export = b"__REALTIME_TIMESTAMP=1704456063000000\nPRIORITY=3\n".to_vec();
+        export.extend(binary_field("MESSAGE", b"first line\nsecond line"));
+        export.extend(b"_SYSTEMD_UNIT=web.service\n\n");
+        export.extend(b"__REALTIME_TIMESTAMP=1704456064000000\nMESSAGE=next\n\n");
+
+        let records = records(export).unwrap();
+
+        assert_eq!(records.len(), 2);
+        let (fields, line_number) = &records[0];
+        assert_eq!(*line_number, 1);
+        assert_eq!(fields["MESSAGE"], "first line\nsecond line");
+        assert_eq!(fields["_SYSTEMD_UNIT"], "web.service");
+        // The binary field spans the name, length and data lines
+        assert_eq!(records[1].1, 8);
+
+        let event = journal_event(fields, *line_number);
+        assert_eq!(event.level, Some(LogLevel::Error));
+        assert_eq!(event.timestamp.unwrap().to_rfc3339(), "2024-01-05T12:01:03+00:00");
+        assert_eq!(event.fields["unit"], "web.service");
+    }
+
+    #[test]
+    fn rejects_oversized_and_truncated_binary_fields() {
+        let mut huge = b"MESSAGE\n".to_vec();
+        huge.extend(u64::MAX.to_le_bytes());
+        let error = records(huge).unwrap_err().to_string();
+        assert!(error.contains("exceeds"), "{}", error);
+
+        let mut truncated = binary_field("MESSAGE", b"0123456789");
+        truncated.truncate(truncated.len() - 4);
+        assert!(records(truncated).unwrap_err().to_string().contains("Truncated"));
+
+        let mut unterminated = binary_field("MESSAGE", b"data");
+        *unterminated.last_mut().unwrap() = b'x';
+        assert!(records(unterminated).unwrap_err().to_string().contains("Malformed"));
+    }
+
+    #[test]
+    fn parses_json_dumps() {
+        let parser = JournalJsonParser::new();
+        let line = r#"{"__REALTIME_TIMESTAMP":"1704456063000000","PRIORITY":"4","MESSAGE":[100,105,115,107,32,108,111,119],"_HOSTNAME":["web-1","web-2"],"SYSLOG_IDENTIFIER":"kernel","_PID":null}"#;
+
+        let event = parser.parse(line, 3).unwrap();
+
+        assert_eq!((event.level, event.message.as_str()), (Some(LogLevel::Warn), "disk low"));
+        assert_eq!(event.fields["hostname"], "web-1");
+        assert_eq!(event.fields.get("pid"), None);
+        assert_eq!(event.raw, "2024-01-05T12:01:03+00:00 web-1 kernel: disk low");
+        assert!(parser.parse(r#"{"msg":"not a journal record"}"#, 1).is_none());
+    }
+}
@@ -0,0 +1,95 @@
+use super::timestamp::TimestampParser;
+use super::{LineParser, event_from_fields};
+use crate::models::LogEvent;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Parses JSON-lines logs (one JSON object per line)
+pub struct JsonLineParser {
+    timestamps: TimestampParser,
+}
+
+impl JsonLineParser {
+    pub fn new(timestamps: TimestampParser) -> Self {
+        Self { timestamps }
+    }
+
+    /// Flatten nested objects into dotted keys, e.g. `http.status`
+    fn flatten(prefix: &str, value: Value, fields: &mut BTreeMap<String, String>) {
+        match value {
+            Value::Object(object) => {
+                for (key, inner) in object {
+                    Self::flatten(&format!("{}.{}", prefix, key), inner, fields);
+                }
+            }
+            Value::String(s) => {
+                fields.insert(prefix.to_string(), s);
+            }
+            Value::Null => {}
+            other => {
+                fields.insert(prefix.to_string(), other.to_string());
+            }
+        }
+    }
+}
+
+impl LineParser for JsonLineParser {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn description(&self) -> &str {
+        "JSON lines"
+    }
+
+    /// Parse a line, or return None if it is not a JSON object
+    fn parse(&self, line: &str, line_number: usize) -> Option<LogEvent> {
+        let trimmed = line.trim();
+        if !trimmed.starts_with('{') {
+            return None;
+        }
+
+        let Value::Object(object) = serde_json::from_str(trimmed).ok()? else {
+            return None;
+        };
+
+        let mut fields = BTreeMap::new();
+        for (key, value) in object {
+            Self::flatten(&key, value, &mut fields);
+        }
+
+        Some(event_from_fields(fields, line, line_number, &self.timestamps))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::LogLevel;
+
+    #[test]
+    fn maps_well_known_keys_and_flattens_the_rest() {
+        let parser = JsonLineParser::new(TimestampParser::new());
+        let line = r#"{"ts":"2024-01-05T12:01:03Z","level":50,"msg":"db timeout","http":{"status":503,"path":"/cart"},"retry":null}"#;
+
+        let event = parser.parse(line, 4).unwrap();
+
+        assert_eq!(event.timestamp.unwrap().to_rfc3339(), "2024-01-05T12:01:03+00:00");
+        assert_eq!(event.level, Some(LogLevel::Error));
+        assert_eq!(event.message, "db timeout");
+        assert_eq!((event.raw.as_str(), event.line_number), (line, 4));
+        let fields: Vec<(&str, &str)> = event.fields.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        assert_eq!(fields, vec![("http.path", "/cart"), ("http.status", "503")]);
+    }
+
+    #[test]
+    fn rejects_lines_that_are_not_objects() {
+        let parser = JsonLineParser::new(TimestampParser::new());
+
+        assert!(parser.parse("[1, 2, 3]", 1).is_none());
+        assert!(parser.parse("{not json", 1).is_none());
+        assert!(parser.parse("2024-01-05 ERROR {\"a\": 1}", 1).is_none());
+        // Without a message key the whole line is the message
+        assert_eq!(parser.parse(r#"{"user":"u1"}"#, 1).unwrap().message, r#"{"user":"u1"}"#);
+    }
+}
@@ -0,0 +1,93 @@
+use super::timestamp::TimestampParser;
+use super::{LineParser, event_from_fields};
+use crate::models::LogEvent;
+use regex::Regex;
+use std::collections::BTreeMap;
+
+/// Keys at least one of which must be present for a line to count as logfmt
+const MARKER_KEYS: [&str; 6] = ["msg", "message", "level", "lvl", "time", "ts"];
+
+/// Parses logfmt lines (`time=... level=info msg="..." key=value`)
+pub struct LogfmtParser {
+    pair_regex: Regex,
+    timestamps: TimestampParser,
+}
+
+impl LogfmtParser {
+    pub fn new(timestamps: TimestampParser) -> Self {
+        Self {
+            // Matches: key=value or key="quoted \"value\""
+            pair_regex: Regex::new(
+                r#"(?:^|\s)([\w.\-@]+)=("(?:[^"\\]|\\.)*"|\S*)"#
+            ).unwrap(),
+            timestamps,
+        }
+    }
+}
+
+impl LineParser for LogfmtParser {
+    fn name(&self) -> &str {
+        "logfmt"
+    }
+
+    fn description(&self) -> &str {
+        "logfmt key=value lines"
+    }
+
+    /// Parse a line, or return None if it doesn't look like logfmt
+    fn parse(&self, line: &str, line_number: usize) -> Option<LogEvent> {
+        // logfmt lines start with a key=value pair
+        if self.pair_regex.find(line.trim_start()).is_none_or(|m| m.start() != 0) {
+            return None;
+        }
+
+        let mut fields = BTreeMap::new();
+
+        for cap in self.pair_regex.captures_iter(line) {
+            let value = cap[2].strip_prefix('"').and_then(|v| v.strip_suffix('"'));
+            let value = match value {
+                Some(quoted) => quoted.replace("\\\"", "\"").replace("\\\\", "\\"),
+                None => cap[2].to_string(),
+            };
+            fields.insert(cap[1].to_string(), value);
+        }
+
+        let has_marker = fields.keys().any(|k| MARKER_KEYS.contains(&k.to_lowercase().as_str()));
+        if fields.len() < 2 || !has_marker {
+            return None;
+        }
+
+        Some(event_from_fields(fields, line, line_number, &self.timestamps))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::LogLevel;
+
+    #[test]
+    fn parses_quoted_and_bare_values() {
+        let parser = LogfmtParser::new(TimestampParser::new());
+
+        let event = parser
+            .parse(r#"time=2024-01-05T12:01:03Z level=warn msg="disk \"data\" low" free=5% path=/var"#, 2)
+            .unwrap();
+
+        assert_eq!(event.timestamp.unwrap().to_rfc3339(), "2024-01-05T12:01:03+00:00");
+        assert_eq!(event.level, Some(LogLevel::Warn));
+        assert_eq!(event.message, r#"disk "data" low"#);
+        assert_eq!(event.fields.get("free").map(String::as_str), Some("5%"));
+        assert_eq!(event.fields.get("path").map(String::as_str), Some("/var"));
+    }
+
+    #[test]
+    fn needs_a_leading_pair_and_a_marker_key() {
+        let parser = LogfmtParser::new(TimestampParser::new());
+
+        assert!(parser.parse("starting worker id=3 level=info", 1).is_none());
+        assert!(parser.parse("user=u1 action=login", 1).is_none());
+        assert!(parser.parse("level=info", 1).is_none());
+        assert!(parser.parse("level=info user=u1", 1).is_some());
+    }
+}
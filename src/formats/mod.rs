@@ -0,0 +1,100 @@
+pub mod apache;
+pub mod container;
+pub mod custom;
+pub mod detect;
+pub mod journal;
+pub mod json;
+pub mod logfmt;
+pub mod nginx;
+pub mod plain;
+pub mod registry;
+pub mod syslog;
+pub mod timestamp;
+
+use crate::models::{LogEvent, LogLevel};
+use timestamp::TimestampParser;
+use std::collections::BTreeMap;
+
+/// A parser for one log format, registered under a name in the `ParserRegistry`
+pub trait LineParser: Send + Sync {
+    /// Name used with `--format`
+    fn name(&self) -> &str;
+
+    /// Short description shown by `log-reasoner formats`
+    fn description(&self) -> &str;
+
+    /// Parse a line, or return None if it isn't in this format
+    fn parse(&self, line: &str, line_number: usize) -> Option<LogEvent>;
+
+    /// Share of sampled lines in this format (0.0 - 1.0), used for detection
+    fn confidence(&self, sample: &[String]) -> f64 {
+        detect::sample_share(sample, |line| self.parse(line, 0).is_some())
+    }
+
+    /// Whether a line is incomplete and its record continues on the next line
+    fn is_partial(&self, _line: &str) -> bool {
+        false
+    }
+
+    /// Which record a line belongs to when several are interleaved, such as
+    /// a container's stdout and stderr; lines with different keys never share a record
+    fn record_key(&self, _line: &str) -> Option<String> {
+        None
+    }
+
+    /// Parse a record spanning several lines, the last of which is complete.
+    /// Lines come with their line numbers, which need not be consecutive.
+    fn parse_record(&self, lines: &[(usize, String)]) -> Option<LogEvent> {
+        let (line_number, _) = lines.first()?;
+        let text: Vec<&str> = lines.iter().map(|(_, line)| line.as_str()).collect();
+        self.parse(&text.join("\n"), *line_number)
+    }
+}
+
+/// Keys commonly holding the timestamp, level and message in structured logs
+const TIMESTAMP_KEYS: [&str; 5] = ["time", "timestamp", "ts", "@timestamp", "t"];
+const LEVEL_KEYS: [&str; 5] = ["level", "severity", "lvl", "log.level", "loglevel"];
+const MESSAGE_KEYS: [&str; 4] = ["msg", "message", "log", "@message"];
+
+/// Build an event from structured key/value pairs (JSON, logfmt).
+/// Well-known keys become the timestamp, level and message; the rest stay fields.
+pub fn event_from_fields(
+    mut fields: BTreeMap<String, String>,
+    raw: &str,
+    line_number: usize,
+    timestamps: &TimestampParser,
+) -> LogEvent {
+    let mut take = |keys: &[&str]| {
+        let key = fields.keys().find(|k| keys.iter().any(|c| k.eq_ignore_ascii_case(c)))?.clone();
+        fields.remove(&key)
+    };
+
+    let timestamp = take(&TIMESTAMP_KEYS).and_then(|v| timestamps.parse(&v));
+    let level = take(&LEVEL_KEYS).and_then(|v| parse_level_value(&v));
+    let message = take(&MESSAGE_KEYS).unwrap_or_else(|| raw.to_string());
+
+    LogEvent {
+        timestamp,
+        level,
+        message,
+        raw: raw.to_string(),
+        line_number,
+        fields,
+    }
+}
+
+/// Parse a level from a structured field, including bunyan/pino numeric levels
+pub fn parse_level_value(value: &str) -> Option<LogLevel> {
+    if let Ok(number) = value.parse::<u32>() {
+        return Some(match number {
+            60.. => LogLevel::Critical,
+            50..=59 => LogLevel::Error,
+            40..=49 => LogLevel::Warn,
+            30..=39 => LogLevel::Info,
+            20..=29 => LogLevel::Debug,
+            _ => LogLevel::Trace,
+        });
+    }
+
+    LogLevel::from_str(value)
+}
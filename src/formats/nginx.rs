@@ -0,0 +1,111 @@
+use super::LineParser;
+use super::timestamp::TimestampParser;
+use crate::models::{LogEvent, LogLevel};
+use chrono::NaiveDateTime;
+use regex::Regex;
+use std::collections::BTreeMap;
+
+/// Parses nginx `error.log` lines
+pub struct NginxErrorParser {
+    /// Regex for the line header (timestamp, level, pid#tid, connection)
+    line_regex: Regex,
+    /// Regex for trailing `, key: value` context pairs
+    context_regex: Regex,
+    timestamps: TimestampParser,
+}
+
+impl NginxErrorParser {
+    pub fn new(timestamps: TimestampParser) -> Self {
+        Self {
+            // Matches: 2024/01/05 12:01:03 [error] 1234#0: *5 connect() failed ...
+            line_regex: Regex::new(
+                r"^(\d{4}/\d{2}/\d{2} \d{2}:\d{2}:\d{2}) \[(\w+)\] (\d+)#(\d+): (?:\*(\d+) )?(.*)$"
+            ).unwrap(),
+
+            // Matches: , client: 10.0.0.1, upstream: "http://127.0.0.1:8080/"
+            context_regex: Regex::new(
+                r#", (client|server|request|upstream|host|referrer): ("[^"]*"|[^,]*)"#
+            ).unwrap(),
+
+            timestamps,
+        }
+    }
+}
+
+impl LineParser for NginxErrorParser {
+    fn name(&self) -> &str {
+        "nginx-error"
+    }
+
+    fn description(&self) -> &str {
+        "nginx error.log"
+    }
+
+    /// Parse a line, or return None if it is not an nginx error-log line
+    fn parse(&self, line: &str, line_number: usize) -> Option<LogEvent> {
+        let cap = self.line_regex.captures(line)?;
+
+        let timestamp = NaiveDateTime::parse_from_str(&cap[1], "%Y/%m/%d %H:%M:%S")
+            .ok()
+            .and_then(|dt| self.timestamps.localize(dt));
+
+        let mut fields = BTreeMap::new();
+        fields.insert("severity".to_string(), cap[2].to_string());
+        fields.insert("pid".to_string(), cap[3].to_string());
+        fields.insert("tid".to_string(), cap[4].to_string());
+        if let Some(conn) = cap.get(5) {
+            fields.insert("connection".to_string(), conn.as_str().to_string());
+        }
+
+        // Context pairs move into fields so they don't split the pattern
+        let body = &cap[6];
+        let mut message_end = body.len();
+        for ctx in self.context_regex.captures_iter(body) {
+            let whole = ctx.get(0).unwrap();
+            message_end = message_end.min(whole.start());
+            fields.insert(ctx[1].to_string(), ctx[2].trim_matches('"').to_string());
+        }
+
+        Some(LogEvent {
+            timestamp,
+            level: LogLevel::from_str(&cap[2]),
+            message: body[..message_end].trim().to_string(),
+            raw: line.to_string(),
+            line_number,
+            fields,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_connection_and_context_into_fields() {
+        let parser = NginxErrorParser::new(TimestampParser::new());
+        let line = r#"2024/01/05 12:01:03 [error] 1234#7: *5 connect() failed (111: Connection refused) while connecting to upstream, client: 10.0.0.1, server: shop, request: "GET /cart HTTP/1.1", upstream: "http://127.0.0.1:8080/cart", host: "shop.example""#;
+
+        let event = parser.parse(line, 1).unwrap();
+
+        assert_eq!(event.timestamp.unwrap().to_rfc3339(), "2024-01-05T12:01:03+00:00");
+        assert_eq!(event.level, Some(LogLevel::Error));
+        assert_eq!(event.message, "connect() failed (111: Connection refused) while connecting to upstream");
+        let field = |key: &str| event.fields.get(key).map(String::as_str);
+        assert_eq!((field("pid"), field("tid"), field("connection")), (Some("1234"), Some("7"), Some("5")));
+        assert_eq!(field("client"), Some("10.0.0.1"));
+        assert_eq!(field("request"), Some("GET /cart HTTP/1.1"));
+        assert_eq!(field("upstream"), Some("http://127.0.0.1:8080/cart"));
+        assert_eq!(field("host"), Some("shop.example"));
+    }
+
+    #[test]
+    fn parses_lines_without_a_connection() {
+        let parser = NginxErrorParser::new(TimestampParser::new());
+
+        let event = parser.parse("2024/01/05 12:01:03 [notice] 1#1: signal process started", 1).unwrap();
+        assert_eq!(event.message, "signal process started");
+        assert_eq!(event.fields.get("connection"), None);
+        assert!(parser.parse("[Fri Jan 05 12:01:03 2024] [error] boom", 1).is_none());
+    }
+}
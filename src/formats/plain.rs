@@ -0,0 +1,256 @@
+use super::LineParser;
+use super::detect::sample_share;
+use super::timestamp::TimestampParser;
+use crate::models::{LogEvent, LogLevel};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+/// A timestamp found in a line (None if unparseable) and its span, if removable
+type TimestampMatch = (Option<DateTime<Utc>>, Option<Range<usize>>);
+
+/// Generic extraction of timestamps, level keywords and CLF access
+/// fields from free text; the fallback for lines no other parser understands
+pub struct PlainParser {
+    /// Timestamp formats and timezone
+    timestamps: TimestampParser,
+    /// Regex for log levels
+    level_regex: Regex,
+    /// Regex for CLF (Common Log Format) and Combined Log Format access logs
+    clf_regex: Regex,
+    /// Regex splitting a CLF request line into method, path and protocol
+    request_regex: Regex,
+}
+
+impl PlainParser {
+    pub fn new(timestamps: TimestampParser) -> Self {
+        Self {
+            // Matches: ERROR, WARN, INFO, etc. (case-insensitive)
+            level_regex: Regex::new(
+                r"(?i)\b(ERROR|ERR|WARN|WARNING|INFO|DEBUG|TRACE)\b"
+            ).unwrap(),
+
+            // Matches: 127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET /index.html HTTP/1.0" 200 2326
+            // optionally followed by the Combined format's "referer" "user-agent"
+            clf_regex: Regex::new(
+                r#"^(\S+) \S+ (\S+) \[([\w:/]+\s[+\-]\d{4})\] "((?:[^"\\]|\\.)*)" (\d{3}) (\d+|-)(?: "((?:[^"\\]|\\.)*)" "((?:[^"\\]|\\.)*)")?"#
+            ).unwrap(),
+
+            // Matches: GET /index.html HTTP/1.0
+            request_regex: Regex::new(
+                r"^(\S+) (\S+)(?: (\S+))?$"
+            ).unwrap(),
+
+            timestamps,
+        }
+    }
+
+    /// Parse any line; fields that can't be found are left empty
+    pub fn parse_line(&self, line: &str, line_number: usize) -> LogEvent {
+        let (timestamp, span) = self.find_timestamp(line).unwrap_or((None, None));
+        // An unparseable timestamp is left in the message
+        let span = span.filter(|_| timestamp.is_some());
+        let level = self.extract_level(line);
+        let message = self.extract_message(line, span, &level);
+        let fields = self.extract_access_fields(line);
+
+        LogEvent {
+            timestamp,
+            level,
+            message,
+            raw: line.to_string(),
+            line_number,
+            fields,
+        }
+    }
+
+    /// Whether a line is a CLF/Combined access log line
+    pub fn is_access_line(&self, line: &str) -> bool {
+        self.clf_regex.is_match(line)
+    }
+
+    /// Extract access-log fields (client, method, path, status, ...) from CLF/Combined lines
+    fn extract_access_fields(&self, line: &str) -> BTreeMap<String, String> {
+        let mut fields = BTreeMap::new();
+
+        let Some(cap) = self.clf_regex.captures(line) else {
+            return fields;
+        };
+
+        let mut insert = |key: &str, value: Option<&str>| {
+            if let Some(v) = value.filter(|v| !v.is_empty() && *v != "-") {
+                fields.insert(key.to_string(), v.to_string());
+            }
+        };
+
+        insert("client", cap.get(1).map(|m| m.as_str()));
+        insert("user", cap.get(2).map(|m| m.as_str()));
+        insert("status", cap.get(5).map(|m| m.as_str()));
+        insert("bytes", cap.get(6).map(|m| m.as_str()));
+        insert("referer", cap.get(7).map(|m| m.as_str()));
+        insert("user_agent", cap.get(8).map(|m| m.as_str()));
+
+        if let Some(request) = cap.get(4)
+            && let Some(req) = self.request_regex.captures(request.as_str())
+        {
+            insert("method", req.get(1).map(|m| m.as_str()));
+            insert("path", req.get(2).map(|m| m.as_str()));
+            insert("protocol", req.get(3).map(|m| m.as_str()));
+        }
+
+        fields
+    }
+
+    /// Extract timestamp if present
+    pub fn extract_timestamp(&self, line: &str) -> Option<DateTime<Utc>> {
+        self.find_timestamp(line).and_then(|(timestamp, _)| timestamp)
+    }
+
+    /// Find the timestamp and where it is in the line (no span for CLF, whose
+    /// timestamp stays part of the message)
+    fn find_timestamp(&self, line: &str) -> Option<TimestampMatch> {
+        // Try CLF format first as it's more specific
+        if let Some(cap) = self.clf_regex.captures(line)
+            && let Some(m) = cap.get(3)
+            && let Ok(dt) = DateTime::parse_from_str(m.as_str(), "%d/%b/%Y:%H:%M:%S %z")
+        {
+            return Some((Some(dt.with_timezone(&Utc)), None));
+        }
+
+        // Fallback to the configured and built-in timestamp formats
+        self.timestamps
+            .find(line)
+            .map(|(timestamp, span)| (timestamp, Some(span)))
+    }
+
+    /// Extract the actual message (remove timestamp and level)
+    fn extract_message(&self, line: &str, timestamp_span: Option<Range<usize>>, level: &Option<LogLevel>) -> String {
+        let mut msg = line.to_string();
+
+        // Remove timestamp if found
+        if let Some(span) = timestamp_span {
+            msg.replace_range(span, "");
+        }
+
+        // Remove level if found
+        if level.is_some() {
+            msg = self.level_regex.replace(&msg, "").to_string();
+        }
+
+        // Clean up whitespace
+        msg.trim().to_string()
+    }
+
+    /// Extract log level if present
+    pub fn extract_level(&self, line: &str) -> Option<LogLevel> {
+        // Try to find explicit log level first
+        if let Some(cap) = self.level_regex.captures(line)
+            && let Some(m) = cap.get(1)
+        {
+            return LogLevel::from_str(m.as_str());
+        }
+
+        // If no explicit level, check if it's a CLF log and infer from status code
+        if let Some(cap) = self.clf_regex.captures(line)
+            && let Some(m) = cap.get(5)
+            && let Ok(status) = m.as_str().parse::<u16>()
+        {
+            return match status {
+                500..=599 => Some(LogLevel::Error),
+                400..=499 => Some(LogLevel::Warn),
+                _ => Some(LogLevel::Info),
+            };
+        }
+
+        None
+    }
+}
+
+impl LineParser for PlainParser {
+    fn name(&self) -> &str {
+        "plain"
+    }
+
+    fn description(&self) -> &str {
+        "Free text with ISO8601 timestamps and level keywords"
+    }
+
+    fn parse(&self, line: &str, line_number: usize) -> Option<LogEvent> {
+        Some(self.parse_line(line, line_number))
+    }
+
+    /// Share of lines with a timestamp or a level keyword
+    fn confidence(&self, sample: &[String]) -> f64 {
+        sample_share(sample, |line| self.extract_timestamp(line).is_some() || self.extract_level(line).is_some())
+    }
+}
+
+/// Parses CLF and Combined access log lines only
+pub struct ClfParser {
+    plain: PlainParser,
+}
+
+impl ClfParser {
+    pub fn new(timestamps: TimestampParser) -> Self {
+        Self {
+            plain: PlainParser::new(timestamps),
+        }
+    }
+}
+
+impl LineParser for ClfParser {
+    fn name(&self) -> &str {
+        "clf"
+    }
+
+    fn description(&self) -> &str {
+        "Common / Combined Log Format access logs"
+    }
+
+    fn parse(&self, line: &str, line_number: usize) -> Option<LogEvent> {
+        self.plain
+            .is_access_line(line)
+            .then(|| self.plain.parse_line(line, line_number))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_the_timestamp_and_level() {
+        let parser = PlainParser::new(TimestampParser::new());
+
+        let event = parser.parse_line("2024-01-05 12:01:03,250 WARN [pool-2] retrying request", 9);
+
+        assert_eq!(event.timestamp.unwrap().to_rfc3339(), "2024-01-05T12:01:03.250+00:00");
+        assert_eq!(event.level, Some(LogLevel::Warn));
+        assert_eq!(event.message, "[pool-2] retrying request");
+        assert!(event.fields.is_empty());
+
+        let bare = parser.parse_line("something happened", 1);
+        assert_eq!((bare.timestamp, bare.level, bare.message.as_str()), (None, None, "something happened"));
+    }
+
+    #[test]
+    fn extracts_access_fields_and_levels_from_status() {
+        let clf = ClfParser::new(TimestampParser::new());
+        let line = r#"10.0.0.1 - alice [05/Jan/2024:12:01:03 +0100] "POST /cart HTTP/1.1" 503 12 "https://shop/" "curl/8.0""#;
+
+        let event = clf.parse(line, 1).unwrap();
+
+        assert_eq!(event.timestamp.unwrap().to_rfc3339(), "2024-01-05T11:01:03+00:00");
+        assert_eq!(event.level, Some(LogLevel::Error));
+        let field = |key: &str| event.fields.get(key).map(String::as_str);
+        assert_eq!((field("client"), field("user")), (Some("10.0.0.1"), Some("alice")));
+        assert_eq!((field("method"), field("path"), field("protocol")), (Some("POST"), Some("/cart"), Some("HTTP/1.1")));
+        assert_eq!((field("status"), field("bytes")), (Some("503"), Some("12")));
+        assert_eq!((field("referer"), field("user_agent")), (Some("https://shop/"), Some("curl/8.0")));
+
+        let not_found = clf.parse(r#"10.0.0.1 - - [05/Jan/2024:12:01:03 +0000] "GET /x HTTP/1.1" 404 -"#, 2).unwrap();
+        assert_eq!((not_found.level, not_found.fields.get("bytes")), (Some(LogLevel::Warn), None));
+        assert!(clf.parse("2024-01-05 12:01:03 ERROR boom", 3).is_none());
+    }
+}
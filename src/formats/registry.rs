@@ -0,0 +1,165 @@
+use super::LineParser;
+use super::apache::ApacheErrorParser;
+use super::container::{ContainerLogParser, ContainerRuntime};
+use super::journal::JournalJsonParser;
+use super::json::JsonLineParser;
+use super::logfmt::LogfmtParser;
+use super::nginx::NginxErrorParser;
+use super::plain::{ClfParser, PlainParser};
+use super::syslog::SyslogParser;
+use super::timestamp::TimestampParser;
+use crate::models::LogEvent;
+use std::sync::Arc;
+
+/// Line parsers by name, in detection priority order (most specific first)
+pub struct ParserRegistry {
+    parsers: Vec<Arc<dyn LineParser>>,
+}
+
+impl ParserRegistry {
+    /// A registry with no parsers
+    pub fn new() -> Self {
+        Self { parsers: Vec::new() }
+    }
+
+    /// Append the built-in parsers, ending with the `plain` fallback
+    pub fn with_builtins(mut self, timestamps: &TimestampParser) -> Self {
+        self.register(Arc::new(JournalJsonParser::new()));
+        self.register(Arc::new(ContainerLogParser::new(ContainerRuntime::Docker, timestamps)));
+        self.register(Arc::new(ContainerLogParser::new(ContainerRuntime::Cri, timestamps)));
+        self.register(Arc::new(JsonLineParser::new(timestamps.clone())));
+        self.register(Arc::new(NginxErrorParser::new(timestamps.clone())));
+        self.register(Arc::new(ApacheErrorParser::new(timestamps.clone())));
+        self.register(Arc::new(SyslogParser::new(timestamps.clone())));
+        self.register(Arc::new(ClfParser::new(timestamps.clone())));
+        self.register(Arc::new(LogfmtParser::new(timestamps.clone())));
+        self.register(Arc::new(PlainParser::new(timestamps.clone())));
+        self
+    }
+
+    /// Add a parser after the ones already registered
+    pub fn register(&mut self, parser: Arc<dyn LineParser>) {
+        self.parsers.push(parser);
+    }
+
+    pub fn parsers(&self) -> &[Arc<dyn LineParser>] {
+        &self.parsers
+    }
+
+    /// Look up a parser by name (case-insensitive); the first registered wins
+    pub fn get(&self, name: &str) -> Option<Arc<dyn LineParser>> {
+        self.parsers
+            .iter()
+            .find(|p| p.name().eq_ignore_ascii_case(name))
+            .cloned()
+    }
+
+    /// Resolve a `--format` value: a name, or a comma-separated list of
+    /// names tried in order
+    pub fn resolve(&self, spec: &str) -> Option<Arc<dyn LineParser>> {
+        let names: Vec<&str> = spec.split(',').map(str::trim).filter(|n| !n.is_empty()).collect();
+
+        match names[..] {
+            [] => None,
+            [name] => self.get(name),
+            _ => {
+                let parsers = names.iter().map(|name| self.get(name)).collect::<Option<Vec<_>>>()?;
+                Some(Arc::new(CompositeParser::new(&names.join(","), parsers)))
+            }
+        }
+    }
+}
+
+/// Tries several parsers in order and uses the first that understands a line
+pub struct CompositeParser {
+    name: String,
+    parsers: Vec<Arc<dyn LineParser>>,
+}
+
+impl CompositeParser {
+    pub fn new(name: &str, parsers: Vec<Arc<dyn LineParser>>) -> Self {
+        Self {
+            name: name.to_string(),
+            parsers,
+        }
+    }
+}
+
+impl LineParser for CompositeParser {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        "First of several formats that parses each line"
+    }
+
+    fn parse(&self, line: &str, line_number: usize) -> Option<LogEvent> {
+        self.parsers.iter().find_map(|p| p.parse(line, line_number))
+    }
+
+    fn is_partial(&self, line: &str) -> bool {
+        self.parsers.iter().any(|p| p.is_partial(line))
+    }
+
+    fn record_key(&self, line: &str) -> Option<String> {
+        self.parsers.iter().find_map(|p| p.record_key(line))
+    }
+
+    fn parse_record(&self, lines: &[(usize, String)]) -> Option<LogEvent> {
+        self.parsers.iter().find_map(|p| p.parse_record(lines))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::custom::CustomFormat;
+
+    fn registry() -> ParserRegistry {
+        ParserRegistry::new().with_builtins(&TimestampParser::new())
+    }
+
+    #[test]
+    fn resolves_names_and_lists() {
+        let registry = registry();
+
+        assert_eq!(registry.parsers().last().unwrap().name(), "plain");
+        assert_eq!(registry.get("NGINX-Error").unwrap().name(), "nginx-error");
+        assert!(registry.get("nope").is_none());
+
+        assert_eq!(registry.resolve(" logfmt ").unwrap().name(), "logfmt");
+        assert_eq!(registry.resolve("json, logfmt").unwrap().name(), "json,logfmt");
+        assert!(registry.resolve("json,nope").is_none());
+        assert!(registry.resolve(" , ").is_none());
+    }
+
+    #[test]
+    fn composite_falls_back_in_order() {
+        let composite = registry().resolve("json,logfmt,plain").unwrap();
+
+        let json = composite.parse(r#"{"msg":"from json","level":"info"}"#, 1).unwrap();
+        assert_eq!(json.message, "from json");
+        let logfmt = composite.parse("level=warn msg=\"from logfmt\"", 2).unwrap();
+        assert_eq!(logfmt.message, "from logfmt");
+        let plain = composite.parse("ERROR from plain text", 3).unwrap();
+        assert_eq!(plain.message, "from plain text");
+
+        // Without the plain fallback, unknown lines are rejected
+        assert!(registry().resolve("json,logfmt").unwrap().parse("free text", 4).is_none());
+    }
+
+    #[test]
+    fn registered_parsers_take_priority() {
+        let timestamps = TimestampParser::new();
+        let custom = CustomFormat::from_regex("json", r"^(?P<msg>\{.*)$", None, &timestamps).unwrap();
+        let mut registry = ParserRegistry::new();
+        registry.register(Arc::new(custom));
+        let registry = registry.with_builtins(&timestamps);
+
+        // The custom parser shadows the built-in one of the same name
+        let event = registry.get("json").unwrap().parse(r#"{"msg":"x"}"#, 1).unwrap();
+        assert_eq!(event.message, r#"{"msg":"x"}"#);
+        assert_eq!(registry.parsers().iter().filter(|p| p.name() == "json").count(), 2);
+    }
+}
@@ -0,0 +1,147 @@
+use super::LineParser;
+use super::timestamp::TimestampParser;
+use crate::models::{LogEvent, LogLevel};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::collections::BTreeMap;
+
+/// Parses syslog lines in RFC 3164 (BSD) and RFC 5424 formats
+pub struct SyslogParser {
+    /// Regex for RFC 3164 lines
+    bsd_regex: Regex,
+    /// Regex for RFC 5424 lines
+    rfc5424_regex: Regex,
+    timestamps: TimestampParser,
+}
+
+impl SyslogParser {
+    pub fn new(timestamps: TimestampParser) -> Self {
+        Self {
+            // Matches: <34>Jan  5 12:01:03 web1 sshd[1234]: Failed password for root
+            bsd_regex: Regex::new(
+                r"^(?:<(\d{1,3})>)?([A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}) (\S+) ([^:\[\s]+)(?:\[(\d+)\])?: ?(.*)$"
+            ).unwrap(),
+
+            // Matches: <165>1 2024-01-05T12:01:03.003Z web1 app 1234 ID47 [sd@1 k="v"] message
+            rfc5424_regex: Regex::new(
+                r"^<(\d{1,3})>1 (\S+) (\S+) (\S+) (\S+) (\S+) (-|(?:\[(?:[^\]\\]|\\.)*\])+) ?(.*)$"
+            ).unwrap(),
+
+            timestamps,
+        }
+    }
+
+    fn event(
+        line: &str,
+        line_number: usize,
+        priority: Option<&str>,
+        timestamp: Option<DateTime<Utc>>,
+        message: &str,
+        mut fields: BTreeMap<String, String>,
+    ) -> LogEvent {
+        // PRI = facility * 8 + severity
+        let priority = priority.and_then(|p| p.parse::<u8>().ok());
+        if let Some(pri) = priority {
+            fields.insert("facility".to_string(), (pri / 8).to_string());
+        }
+
+        LogEvent {
+            timestamp,
+            level: priority.map(|pri| LogLevel::from_syslog_severity(pri % 8)),
+            message: message.trim().to_string(),
+            raw: line.to_string(),
+            line_number,
+            fields,
+        }
+    }
+}
+
+impl LineParser for SyslogParser {
+    fn name(&self) -> &str {
+        "syslog"
+    }
+
+    fn description(&self) -> &str {
+        "RFC 3164 and RFC 5424 syslog"
+    }
+
+    /// Parse a line, or return None if it is not a syslog line
+    fn parse(&self, line: &str, line_number: usize) -> Option<LogEvent> {
+        if let Some(cap) = self.rfc5424_regex.captures(line) {
+            let mut fields = BTreeMap::new();
+            for (key, index) in [("hostname", 3), ("identifier", 4), ("pid", 5), ("msgid", 6)] {
+                if &cap[index] != "-" {
+                    fields.insert(key.to_string(), cap[index].to_string());
+                }
+            }
+
+            return Some(Self::event(
+                line,
+                line_number,
+                cap.get(1).map(|m| m.as_str()),
+                self.timestamps.parse(&cap[2]),
+                &cap[8],
+                fields,
+            ));
+        }
+
+        let cap = self.bsd_regex.captures(line)?;
+
+        // BSD timestamps carry no year or zone; the current year is assumed
+        let timestamp = self.timestamps.parse(&cap[2]);
+
+        let mut fields = BTreeMap::new();
+        fields.insert("hostname".to_string(), cap[3].to_string());
+        fields.insert("identifier".to_string(), cap[4].to_string());
+        if let Some(pid) = cap.get(5) {
+            fields.insert("pid".to_string(), pid.as_str().to_string());
+        }
+
+        Some(Self::event(line, line_number, cap.get(1).map(|m| m.as_str()), timestamp, &cap[6], fields))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, Timelike};
+
+    #[test]
+    fn parses_rfc5424_lines() {
+        let parser = SyslogParser::new(TimestampParser::new());
+        let line = r#"<165>1 2024-01-05T12:01:03.003Z web-1 app 1234 ID47 [exampleSDID@32473 iut="3"] queue backlog growing"#;
+
+        let event = parser.parse(line, 1).unwrap();
+
+        assert_eq!(event.timestamp.unwrap().to_rfc3339(), "2024-01-05T12:01:03.003+00:00");
+        // 165 = facility 20 (local4), severity 5 (notice)
+        assert_eq!(event.level, Some(LogLevel::from_syslog_severity(5)));
+        assert_eq!(event.message, "queue backlog growing");
+        let field = |key: &str| event.fields.get(key).map(String::as_str);
+        assert_eq!((field("hostname"), field("identifier"), field("pid"), field("msgid")), (Some("web-1"), Some("app"), Some("1234"), Some("ID47")));
+        assert_eq!(field("facility"), Some("20"));
+
+        let nil = parser.parse("<11>1 2024-01-05T12:01:03Z - - - - - boom", 1).unwrap();
+        assert_eq!(nil.level, Some(LogLevel::Error));
+        assert_eq!(nil.fields.keys().collect::<Vec<_>>(), vec!["facility"]);
+    }
+
+    #[test]
+    fn parses_bsd_lines() {
+        let parser = SyslogParser::new(TimestampParser::new());
+
+        let event = parser.parse("<34>Jan  5 12:01:03 web-1 sshd[42]: Failed password for root", 1).unwrap();
+
+        let timestamp = event.timestamp.unwrap();
+        assert_eq!((timestamp.month(), timestamp.day(), timestamp.hour(), timestamp.second()), (1, 5, 12, 3));
+        assert_eq!(event.level, Some(LogLevel::Critical));
+        assert_eq!(event.message, "Failed password for root");
+        assert_eq!(event.fields.get("identifier").map(String::as_str), Some("sshd"));
+        assert_eq!(event.fields.get("pid").map(String::as_str), Some("42"));
+
+        // No priority, no level
+        let bare = parser.parse("Jan 15 12:01:03 web-1 kernel: eth0 link up", 1).unwrap();
+        assert_eq!((bare.level, bare.fields.get("pid")), (None, None));
+        assert!(parser.parse("2024-01-05 12:01:03 ERROR boom", 1).is_none());
+    }
+}
@@ -0,0 +1,484 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Datelike, FixedOffset, Local, LocalResult, Months, NaiveDateTime, Offset, TimeDelta, TimeZone, Utc};
+use regex::Regex;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+/// Formats tried in order for timestamps without an offset, after epoch values.
+/// Comma fractions (`12:01:03,123` from log4j and Python) are normalized to a dot first.
+const NAIVE_FORMATS: [&str; 10] = [
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+    "%Y/%m/%d %H:%M:%S%.f",
+    "%Y/%m/%d %H:%M",
+    "%d/%b/%Y:%H:%M:%S%.f",
+    "%a %b %d %H:%M:%S%.f %Y",
+    "%b %d %H:%M:%S%.f %Y",
+    "%d-%b-%Y %H:%M:%S%.f",
+];
+
+/// Epoch seconds (10 digits), milliseconds (13), microseconds (16) or nanoseconds (19)
+const EPOCH_PATTERN: &str = r"(?:\d{10}|\d{13}|\d{16})(?:\.\d+)?|\d{19}";
+
+/// Timezone applied to timestamps that carry no offset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timezone {
+    Utc,
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl Timezone {
+    /// Parse `UTC`, `local` or an offset such as `+02:00`, `-0530` or `+01`
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "utc" | "z" => Ok(Timezone::Utc),
+            "local" => Ok(Timezone::Local),
+            _ => parse_offset(name)
+                .map(Timezone::Fixed)
+                .ok_or_else(|| anyhow!("Invalid timezone '{}'. Expected UTC, local or an offset such as +02:00", name)),
+        }
+    }
+
+    /// Interpret a naive timestamp in this timezone
+    pub fn localize(self, naive: NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            Timezone::Utc => Some(naive.and_utc()),
+            Timezone::Local => match Local.from_local_datetime(&naive) {
+                // Ambiguous local times (DST fall-back) take the earlier instant
+                LocalResult::Single(dt) => Some(dt.with_timezone(&Utc)),
+                // chrono doesn't promise which of the two comes first
+                LocalResult::Ambiguous(a, b) => Some(a.min(b).with_timezone(&Utc)),
+                // Times skipped by DST spring-forward are read with the offset from
+                // before the gap, which shifts them forward by its length
+                LocalResult::None => {
+                    let before = Local.from_local_datetime(&(naive - TimeDelta::days(1))).earliest()?;
+                    let offset = before.offset().fix().local_minus_utc();
+                    Some((naive - TimeDelta::seconds(offset.into())).and_utc())
+                }
+            },
+            Timezone::Fixed(offset) => offset.from_local_datetime(&naive).single().map(|dt| dt.with_timezone(&Utc)),
+        }
+    }
+}
+
+/// Chooses the year of timestamps that carry none, such as syslog's `Jan  5 12:01:03`.
+/// Each is placed in the year nearest the timestamp parsed before it, so `Dec 31` followed
+/// by `Jan  1` moves on to the next year.
+#[derive(Debug, Default)]
+struct YearResolver {
+    /// Year of the first year-less timestamp, from `--year` or the config
+    fixed: Option<i32>,
+    /// When the input was last written (default: now); a first year-less timestamp
+    /// without an earlier one to go by is placed in the latest year not after it
+    modified: Option<DateTime<Utc>>,
+    /// The previous timestamp of the input, with or without a year
+    previous: Option<NaiveDateTime>,
+    /// Whether the input had timestamps with a year
+    anchored: bool,
+    /// The last year-less timestamp of the input
+    last_yearless: Option<NaiveDateTime>,
+}
+
+impl YearResolver {
+    /// Latest time the input's timestamps may reasonably have, allowing for timezones
+    fn limit(&self) -> NaiveDateTime {
+        self.modified.unwrap_or_else(Utc::now).naive_utc() + TimeDelta::days(1)
+    }
+
+    /// Record a timestamp that has a year; with a fixed year only year-less ones count
+    fn observe(&mut self, timestamp: DateTime<Utc>) {
+        if self.fixed.is_none() {
+            self.previous = Some(timestamp.naive_utc());
+            self.anchored = true;
+        }
+    }
+
+    /// Resolve a `Mon DD HH:MM:SS[.f]` timestamp
+    fn resolve(&mut self, stamp: &str) -> Option<NaiveDateTime> {
+        let in_year = |year: i32| NaiveDateTime::parse_from_str(&format!("{} {}", year, stamp), "%Y %b %d %H:%M:%S%.f").ok();
+
+        let resolved = match (self.previous, self.fixed) {
+            (Some(previous), _) => (previous.year() - 1..=previous.year() + 1)
+                .filter_map(in_year)
+                .min_by_key(|dt| (*dt - previous).abs())?,
+            (None, Some(year)) => in_year(year)?,
+            // Going back far enough to find a leap year for Feb 29
+            (None, None) => {
+                let limit = self.limit();
+                (limit.year() - 8..=limit.year()).rev().filter_map(in_year).find(|dt| *dt <= limit)?
+            }
+        };
+
+        self.previous = Some(resolved);
+        self.last_yearless = Some(resolved);
+        Some(resolved)
+    }
+
+    /// Years by which an input with only year-less timestamps must be moved back
+    /// so that it doesn't end after it was written, after rolling over into a new year
+    fn correction(&self) -> u32 {
+        let Some(last) = self.last_yearless.filter(|_| self.fixed.is_none() && !self.anchored) else {
+            return 0;
+        };
+
+        let limit = self.limit();
+        (0..=8)
+            .find(|years| last.checked_sub_months(Months::new(12 * years)).is_some_and(|dt| dt <= limit))
+            .unwrap_or(0)
+    }
+}
+
+/// Parses timestamps in structured values and finds them in free text
+#[derive(Debug, Clone)]
+pub struct TimestampParser {
+    /// Timezone for timestamps without an offset
+    timezone: Timezone,
+    /// User-configured strftime formats, tried before the built-in ones
+    formats: Vec<String>,
+    /// Regex for timestamps embedded in free text
+    text_regex: Regex,
+    /// Regex for an epoch timestamp at the start of a line
+    epoch_regex: Regex,
+    /// Regex for a value that is an epoch timestamp
+    epoch_value_regex: Regex,
+    /// Regex for a trailing UTC offset
+    offset_regex: Regex,
+    /// Regex for comma-separated fractional seconds
+    comma_regex: Regex,
+    /// Years of year-less timestamps, shared by all clones parsing the same input
+    years: Arc<Mutex<YearResolver>>,
+}
+
+impl TimestampParser {
+    pub fn new() -> Self {
+        Self {
+            timezone: Timezone::Utc,
+            formats: Vec::new(),
+
+            // Matches: 2024-01-05T12:01:03Z, 2024-01-05 12:01:03,123, 2024/01/05 12:01:03,
+            // Jan  5 12:01:03 or Fri Jan 05 12:01:03 2024
+            text_regex: Regex::new(
+                r"\d{4}[-/]\d{2}[-/]\d{2}[T ]\d{2}:\d{2}:\d{2}(?:[.,]\d+)?(?:Z|[+-]\d{2}:?\d{2})?|\b(?:(?:Mon|Tue|Wed|Thu|Fri|Sat|Sun) )?(?:Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Oct|Nov|Dec) +\d{1,2} \d{2}:\d{2}:\d{2}(?:[.,]\d+)?(?: \d{4}\b)?"
+            ).unwrap(),
+
+            // Matches: 1704456063, 1704456063.123, 1704456063123 or [1704456063]
+            epoch_regex: Regex::new(
+                &format!(r"^\[?({})\]?(?:\s|$)", EPOCH_PATTERN)
+            ).unwrap(),
+
+            epoch_value_regex: Regex::new(
+                &format!("^(?:{})$", EPOCH_PATTERN)
+            ).unwrap(),
+
+            // Matches: Z, +02:00, -0530 at the end of a value
+            offset_regex: Regex::new(
+                r" ?(Z|[+-]\d{2}(?::?\d{2})?)$"
+            ).unwrap(),
+
+            // Matches: 12:01:03,123
+            comma_regex: Regex::new(
+                r"(:\d{2}),(\d+)"
+            ).unwrap(),
+
+            years: Arc::new(Mutex::new(YearResolver::default())),
+        }
+    }
+
+    /// Interpret timestamps without an offset in this timezone (default: UTC)
+    pub fn with_timezone(mut self, timezone: Timezone) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// Give the first year-less timestamp of each input this year instead of inferring it
+    pub fn with_year(mut self, year: Option<i32>) -> Self {
+        self.years = Arc::new(Mutex::new(YearResolver {
+            fixed: year,
+            ..YearResolver::default()
+        }));
+        self
+    }
+
+    /// Start a new input, last written at `modified`: years of year-less
+    /// timestamps are inferred afresh
+    pub fn begin_input(&self, modified: Option<DateTime<Utc>>) {
+        let mut years = self.years.lock().unwrap();
+        *years = YearResolver {
+            fixed: years.fixed,
+            modified,
+            ..YearResolver::default()
+        };
+    }
+
+    /// Finish an input: the number of years its timestamps must be moved back
+    /// because year-less timestamps rolled over past the time it was written
+    pub fn end_input(&self) -> u32 {
+        self.years.lock().unwrap().correction()
+    }
+
+    /// Record a timestamp with a year as the reference for year-less ones
+    fn observed(&self, timestamp: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+        if let Some(dt) = timestamp {
+            self.years.lock().unwrap().observe(dt);
+        }
+        timestamp
+    }
+
+    /// Try these strftime formats before the built-in ones
+    pub fn with_formats(mut self, formats: Vec<String>) -> Self {
+        self.formats = formats;
+        self
+    }
+
+    /// Parse a complete timestamp value: epoch seconds/millis/micros/nanos,
+    /// RFC 3339, a configured format or one of the built-in formats
+    pub fn parse(&self, value: &str) -> Option<DateTime<Utc>> {
+        let value = value.trim();
+
+        if let Some(dt) = self.formats.iter().find_map(|format| self.parse_with_format(value, format)) {
+            return Some(dt);
+        }
+
+        if self.epoch_value_regex.is_match(value) {
+            return self.observed(Self::from_epoch(value));
+        }
+
+        if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+            return self.observed(Some(dt.with_timezone(&Utc)));
+        }
+
+        let value = self.comma_regex.replace(value, "$1.$2");
+        let (local, offset) = match self.offset_regex.captures(&value) {
+            Some(cap) => (&value[..cap.get(0).unwrap().start()], parse_offset(&cap[1])),
+            None => (&value[..], None),
+        };
+
+        let (naive, yearless) = match NAIVE_FORMATS.iter().find_map(|format| NaiveDateTime::parse_from_str(local, format).ok()) {
+            Some(naive) => (naive, false),
+            None => (self.parse_yearless(local)?, true),
+        };
+        let timestamp = match offset {
+            Some(offset) => offset.from_local_datetime(&naive).single().map(|dt| dt.with_timezone(&Utc)),
+            None => self.timezone.localize(naive),
+        };
+
+        if yearless {
+            timestamp
+        } else {
+            self.observed(timestamp)
+        }
+    }
+
+    /// Parse a value with a strftime format; formats without `%z` use the timezone
+    pub fn parse_with_format(&self, value: &str, format: &str) -> Option<DateTime<Utc>> {
+        if let Ok(dt) = DateTime::parse_from_str(value, format) {
+            return self.observed(Some(dt.with_timezone(&Utc)));
+        }
+
+        let naive = NaiveDateTime::parse_from_str(value, format).ok()?;
+        self.observed(self.timezone.localize(naive))
+    }
+
+    /// Interpret a naive timestamp in the configured timezone
+    pub fn localize(&self, naive: NaiveDateTime) -> Option<DateTime<Utc>> {
+        self.observed(self.timezone.localize(naive))
+    }
+
+    /// Find the first timestamp in a line of free text. Returns the parsed
+    /// timestamp (None if it couldn't be parsed) and where it was found.
+    pub fn find(&self, line: &str) -> Option<(Option<DateTime<Utc>>, Range<usize>)> {
+        for format in &self.formats {
+            if let Ok((dt, rest)) = DateTime::parse_and_remainder(line, format) {
+                return Some((self.observed(Some(dt.with_timezone(&Utc))), 0..line.len() - rest.len()));
+            }
+            if let Ok((naive, rest)) = NaiveDateTime::parse_and_remainder(line, format) {
+                return Some((self.localize(naive), 0..line.len() - rest.len()));
+            }
+        }
+
+        if let Some(cap) = self.epoch_regex.captures(line) {
+            let m = cap.get(1).unwrap();
+            let end = cap.get(0).unwrap().end();
+            return Some((self.observed(Self::from_epoch(m.as_str())), 0..end));
+        }
+
+        let m = self.text_regex.find(line)?;
+        Some((self.parse(m.as_str()), m.range()))
+    }
+
+    /// Epoch timestamps, with the unit told apart by magnitude
+    pub fn from_epoch(value: &str) -> Option<DateTime<Utc>> {
+        let (int, fraction) = value.split_once('.').unwrap_or((value, ""));
+        let int: i64 = int.parse().ok()?;
+        if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        // Nanoseconds per unit
+        let unit: i64 = match int.unsigned_abs() {
+            100_000_000_000_000_000.. => 1,
+            100_000_000_000_000.. => 1_000,
+            100_000_000_000.. => 1_000_000,
+            _ => 1_000_000_000,
+        };
+        // The fraction in nanoseconds, read as digits so `.25` stays exact
+        let digits = unit.ilog10() as usize;
+        let padded = format!("{:0<digits$}", &fraction[..fraction.len().min(digits)]);
+        let sub: i64 = if padded.is_empty() { 0 } else { padded.parse().ok()? };
+
+        let nanos = int.checked_mul(unit)?.checked_add(if value.starts_with('-') { -sub } else { sub })?;
+        Some(DateTime::from_timestamp_nanos(nanos))
+    }
+
+    /// Parse a syslog-style `Mon DD HH:MM:SS`, which carries no year
+    fn parse_yearless(&self, value: &str) -> Option<NaiveDateTime> {
+        let mut words: Vec<&str> = value.split_whitespace().collect();
+        if words.len() == 4 {
+            // A weekday doesn't necessarily match the inferred year
+            words.remove(0);
+        }
+        self.years.lock().unwrap().resolve(&words.join(" "))
+    }
+}
+
+/// Parse `Z`, `+02:00`, `-0530` or `+01` as a UTC offset
+fn parse_offset(s: &str) -> Option<FixedOffset> {
+    if s.eq_ignore_ascii_case("z") {
+        return FixedOffset::east_opt(0);
+    }
+
+    let sign = match s.as_bytes().first()? {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return None,
+    };
+    let digits: String = s[1..].chars().filter(|c| *c != ':').collect();
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let (hours, minutes) = match digits.len() {
+        2 => (digits.parse::<i32>().ok()?, 0),
+        4 => (digits[..2].parse::<i32>().ok()?, digits[2..].parse::<i32>().ok()?),
+        _ => return None,
+    };
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn tells_epoch_units_apart() {
+        let parser = TimestampParser::new();
+
+        assert_eq!(parser.parse("1704456063"), Some(utc("2024-01-05T12:01:03Z")));
+        assert_eq!(parser.parse("1704456063.25"), Some(utc("2024-01-05T12:01:03.25Z")));
+        assert_eq!(parser.parse("1704456063123"), Some(utc("2024-01-05T12:01:03.123Z")));
+        assert_eq!(parser.parse("1704456063123456"), Some(utc("2024-01-05T12:01:03.123456Z")));
+        assert_eq!(parser.parse("1704456063123456789"), Some(utc("2024-01-05T12:01:03.123456789Z")));
+
+        // Values and line prefixes follow the same rules
+        assert_eq!(parser.find("[1704456063123] GET /").unwrap(), (Some(utc("2024-01-05T12:01:03.123Z")), 0..16));
+        assert_eq!(parser.parse("170445606"), None);
+        assert_eq!(parser.find("170445606 retries left"), None);
+        assert_eq!(parser.parse("17044560631"), None);
+    }
+
+    #[test]
+    fn reads_comma_fractions_and_offsets() {
+        let parser = TimestampParser::new();
+
+        assert_eq!(parser.parse("2024-01-05 12:01:03,123"), Some(utc("2024-01-05T12:01:03.123Z")));
+        assert_eq!(parser.parse("2024-01-05T12:01:03+02:00"), Some(utc("2024-01-05T10:01:03Z")));
+        assert_eq!(parser.parse("2024-01-05 12:01:03,5 -0530"), Some(utc("2024-01-05T17:31:03.5Z")));
+        assert_eq!(parser.parse("2024/01/05 12:01:03+01"), Some(utc("2024-01-05T11:01:03Z")));
+        assert_eq!(parser.parse("05/Jan/2024:12:01:03 +0100"), Some(utc("2024-01-05T11:01:03Z")));
+    }
+
+    #[test]
+    fn applies_the_timezone_to_naive_timestamps() {
+        let parser = TimestampParser::new().with_timezone(Timezone::from_name("+02:00").unwrap());
+
+        assert_eq!(parser.parse("2024-01-05 12:01:03"), Some(utc("2024-01-05T10:01:03Z")));
+        assert_eq!(parser.find("Fri Jan 05 12:01:03 2024 boot").unwrap().0, Some(utc("2024-01-05T10:01:03Z")));
+        // An explicit offset wins
+        assert_eq!(parser.parse("2024-01-05T12:01:03Z"), Some(utc("2024-01-05T12:01:03Z")));
+
+        assert_eq!(Timezone::from_name("utc").unwrap(), Timezone::Utc);
+        assert!(Timezone::from_name("Mars/Olympus").is_err());
+    }
+
+    #[test]
+    fn moves_times_in_a_dst_gap_forward() {
+        // Central European time as a POSIX rule, so no tz database is needed;
+        // no other test depends on the local timezone
+        unsafe { std::env::set_var("TZ", "CET-1CEST,M3.5.0,M10.5.0/3") };
+        let parser = TimestampParser::new().with_timezone(Timezone::Local);
+
+        // 02:30 doesn't exist on 2024-03-31: read with the winter offset
+        assert_eq!(parser.parse("2024-03-31 02:30:00"), Some(utc("2024-03-31T01:30:00Z")));
+        assert_eq!(parser.parse("2024-03-31 03:30:00"), Some(utc("2024-03-31T01:30:00Z")));
+        // 02:30 happens twice on 2024-10-27: the earlier one is taken
+        assert_eq!(parser.parse("2024-10-27 02:30:00"), Some(utc("2024-10-27T00:30:00Z")));
+    }
+
+    #[test]
+    fn rolls_year_less_timestamps_over_into_the_next_year() {
+        let parser = TimestampParser::new().with_year(Some(2023));
+        parser.begin_input(None);
+
+        assert_eq!(parser.parse("Dec 31 23:59:58"), Some(utc("2023-12-31T23:59:58Z")));
+        assert_eq!(parser.parse("Jan  1 00:00:01"), Some(utc("2024-01-01T00:00:01Z")));
+        // Timestamps going back a little don't change the year
+        assert_eq!(parser.parse("Dec 31 23:59:59"), Some(utc("2023-12-31T23:59:59Z")));
+        assert_eq!(parser.parse("Mon Jan  1 00:00:02"), Some(utc("2024-01-01T00:00:02Z")));
+        assert_eq!(parser.end_input(), 0);
+    }
+
+    #[test]
+    fn infers_the_year_from_timestamps_with_one() {
+        let parser = TimestampParser::new();
+        parser.begin_input(Some(utc("2026-10-18T00:00:00Z")));
+
+        assert_eq!(parser.parse("2023-12-31T22:00:00Z"), Some(utc("2023-12-31T22:00:00Z")));
+        assert_eq!(parser.parse("Jan  1 00:10:00"), Some(utc("2024-01-01T00:10:00Z")));
+        assert_eq!(parser.end_input(), 0);
+    }
+
+    #[test]
+    fn infers_the_year_from_the_modification_time() {
+        let parser = TimestampParser::new();
+
+        // The first timestamp is placed no later than the file was written
+        parser.begin_input(Some(utc("2024-06-01T00:00:00Z")));
+        assert_eq!(parser.parse("Mar  5 10:00:00"), Some(utc("2024-03-05T10:00:00Z")));
+        assert_eq!(parser.parse("Jun  1 00:00:00"), Some(utc("2024-06-01T00:00:00Z")));
+        assert_eq!(parser.end_input(), 0);
+
+        parser.begin_input(Some(utc("2024-06-01T00:00:00Z")));
+        assert_eq!(parser.parse("Jul  5 10:00:00"), Some(utc("2023-07-05T10:00:00Z")));
+        assert_eq!(parser.parse("Feb 29 10:00:00"), Some(utc("2024-02-29T10:00:00Z")));
+
+        // A file written on Jan 2 that rolled over from December ends a year too late
+        parser.begin_input(Some(utc("2025-01-02T00:00:05Z")));
+        assert_eq!(parser.parse("Jan  2 00:00:00"), Some(utc("2025-01-02T00:00:00Z")));
+        assert_eq!(parser.end_input(), 0);
+        parser.begin_input(Some(utc("2025-01-02T00:00:05Z")));
+        assert_eq!(parser.parse("Dec 30 23:59:58"), Some(utc("2024-12-30T23:59:58Z")));
+        assert_eq!(parser.parse("Jan  2 00:00:00"), Some(utc("2025-01-02T00:00:00Z")));
+        assert_eq!(parser.end_input(), 0);
+    }
+}
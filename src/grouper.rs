@@ -5,6 +5,8 @@ use std::collections::HashMap;
 pub struct LogGrouper {
     /// Regex to replace variable parts (numbers, IDs, timestamps)
     normalizer: Regex,
+    /// Field whose value splits groups (e.g. `unit`), if any
+    split_field: Option<String>,
 }
 
 impl LogGrouper {
@@ -14,25 +16,41 @@ impl LogGrouper {
             normalizer: Regex::new(
                 r"(\d+\.?\d*|[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}|\b\d{1,3}\.\d{1,3}\.\d{1,3}\.\d{1,3}\b)"
             ).unwrap(),
+            split_field: None,
         }
     }
 
-    /// Group log events by pattern
+    /// Keep events with different values of `field` in separate groups
+    pub fn with_split_field(mut self, field: Option<String>) -> Self {
+        self.split_field = field;
+        self
+    }
+
+    /// Group log events by pattern (and split field, if set)
     pub fn group_events(&self, events: Vec<LogEvent>) -> Vec<LogGroup> {
-        let mut groups: HashMap<String, LogGroup> = HashMap::new();
+        let mut groups: HashMap<(Option<String>, String), LogGroup> = HashMap::new();
 
         for event in events {
             let pattern = self.normalize_message(&event.message);
+            let partition = self.split_field.as_ref().map(|field| {
+                let value = event.fields.get(field).map(String::as_str).unwrap_or("-");
+                format!("{}={}", field, value)
+            });
             
             groups
-                .entry(pattern.clone())
-                .or_insert_with(|| LogGroup::new(pattern))
+                .entry((partition.clone(), pattern.clone()))
+                .or_insert_with(|| {
+                    let mut group = LogGroup::new(pattern);
+                    group.partition = partition;
+                    group
+                })
                 .add_event(event);
         }
 
-        // Convert to sorted vector (most frequent first)
+        // Convert to sorted vector (most frequent first, ties in order of
+        // first appearance so runs over the same input are reproducible)
         let mut result: Vec<LogGroup> = groups.into_values().collect();
-        result.sort_by(|a, b| b.count.cmp(&a.count));
+        result.sort_by_key(|g| (std::cmp::Reverse(g.count), g.events.first().map(|e| e.line_number)));
 
         result
     }
@@ -46,11 +64,32 @@ impl LogGrouper {
         normalized.split_whitespace().collect::<Vec<_>>().join(" ")
     }
 
+    /// Template a URL path by replacing ID-like segments with `<ID>`,
+    /// e.g. `/users/42/orders?page=2` becomes `/users/<ID>/orders`
+    pub fn template_path(&self, path: &str) -> String {
+        let path = path.split(['?', '#']).next().unwrap_or(path);
+
+        path.split('/')
+            .map(|segment| {
+                let normalized = self.normalizer.replace_all(segment, "");
+                let only_ids = normalized.chars().all(|c| !c.is_alphanumeric());
+                let hex_id = segment.len() >= 16 && segment.chars().all(|c| c.is_ascii_hexdigit());
+
+                if !segment.is_empty() && (only_ids || hex_id) {
+                    "<ID>"
+                } else {
+                    segment
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
     /// Get statistics about grouping
     pub fn get_stats(groups: &[LogGroup]) -> GroupStats {
         let total_events: usize = groups.iter().map(|g| g.count).sum();
         let unique_patterns = groups.len();
-        let largest_group = groups.first().map(|g| g.count).unwrap_or(0);
+        let largest_group = groups.iter().map(|g| g.count).max().unwrap_or(0);
 
         GroupStats {
             total_events,
@@ -0,0 +1,499 @@
+use crate::cache::{ByteReader, fnv1a};
+use anyhow::{Context, Result, anyhow};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+use std::path::Path;
+
+/// Identifies the file format; bumped when the layout changes
+const MAGIC: &[u8; 4] = b"LRIX";
+const VERSION: u32 = 1;
+
+/// Links per node on the upper layers; the bottom layer gets twice as many
+const M: usize = 16;
+
+/// Candidates considered when linking a new node
+const EF_CONSTRUCTION: usize = 64;
+
+/// Candidates considered per query; raised to k when k is larger
+const EF_SEARCH: usize = 64;
+
+/// Fixed seed for layer assignment, so the same inserts build the same index
+const SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Marks a missing entry point in the index file
+const NO_ENTRY: u32 = u32::MAX;
+
+/// Fingerprint of the model and patterns an index was built from
+pub fn fingerprint<'a>(model_id: &str, patterns: impl IntoIterator<Item = &'a str>) -> u64 {
+    let mut text = model_id.to_string();
+    for pattern in patterns {
+        text.push('\n');
+        text.push_str(pattern);
+    }
+    fnv1a(text.as_bytes())
+}
+
+/// A node and its similarity to a query, ordered by similarity
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Scored {
+    similarity: f32,
+    node: u32,
+}
+
+impl Eq for Scored {}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.similarity.total_cmp(&other.similarity).then(other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+struct Node {
+    /// Caller's id, e.g. the pattern's index
+    id: usize,
+    /// Unit-length vector, so the dot product is the cosine similarity
+    vector: Vec<f32>,
+    /// Neighbors on each layer the node is part of, bottom layer first
+    links: Vec<Vec<u32>>,
+}
+
+/// Approximate nearest-neighbor index over embeddings (HNSW: a hierarchy of
+/// proximity graphs searched greedily from the sparse top layer down), with
+/// cosine similarity. Queries take roughly logarithmic time instead of a
+/// scan over every vector.
+pub struct HnswIndex {
+    dimensions: usize,
+    /// Fingerprint of the indexed data, checked before a saved index is reused
+    fingerprint: u64,
+    nodes: Vec<Node>,
+    by_id: HashMap<usize, u32>,
+    /// Node on the top layer where every search starts
+    entry: Option<u32>,
+    rng: u64,
+}
+
+impl HnswIndex {
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            dimensions,
+            fingerprint: 0,
+            nodes: Vec::new(),
+            by_id: HashMap::new(),
+            entry: None,
+            rng: SEED,
+        }
+    }
+
+    pub fn with_fingerprint(mut self, fingerprint: u64) -> Self {
+        self.fingerprint = fingerprint;
+        self
+    }
+
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    /// The stored (unit-length) vector of an id
+    pub fn vector(&self, id: usize) -> Option<&[f32]> {
+        self.by_id.get(&id).map(|&node| self.nodes[node as usize].vector.as_slice())
+    }
+
+    /// Add a vector under an id
+    pub fn insert(&mut self, id: usize, vector: &[f32]) -> Result<()> {
+        if vector.len() != self.dimensions {
+            return Err(anyhow!("Expected a vector of {} dimensions, got {}", self.dimensions, vector.len()));
+        }
+        if self.by_id.contains_key(&id) {
+            return Err(anyhow!("Id {} is already in the index", id));
+        }
+
+        let level = self.random_level();
+        let node = self.nodes.len() as u32;
+        self.nodes.push(Node {
+            id,
+            vector: normalized(vector),
+            links: vec![Vec::new(); level + 1],
+        });
+        self.by_id.insert(id, node);
+
+        let Some(entry) = self.entry else {
+            self.entry = Some(node);
+            return Ok(());
+        };
+
+        // Descend greedily to the node's top layer, then link it on every
+        // layer from there down
+        let query = self.nodes[node as usize].vector.clone();
+        let top = self.nodes[entry as usize].links.len() - 1;
+        let mut current = entry;
+        for layer in (level + 1..=top).rev() {
+            current = self.search_layer(&query, current, 1, layer)[0].node;
+        }
+
+        for layer in (0..=level.min(top)).rev() {
+            let candidates = self.search_layer(&query, current, EF_CONSTRUCTION, layer);
+            let max_links = if layer == 0 { 2 * M } else { M };
+
+            let neighbors = self.select_neighbors(&candidates, M);
+            for &neighbor in &neighbors {
+                let links = &mut self.nodes[neighbor as usize].links[layer];
+                links.push(node);
+                if links.len() > max_links {
+                    self.prune(neighbor, layer, max_links);
+                }
+            }
+            self.nodes[node as usize].links[layer] = neighbors;
+            current = candidates[0].node;
+        }
+
+        if level > top {
+            self.entry = Some(node);
+        }
+        Ok(())
+    }
+
+    /// The k ids most similar to the query, most similar first
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<(usize, f32)> {
+        let Some(entry) = self.entry else {
+            return Vec::new();
+        };
+        if k == 0 || query.len() != self.dimensions {
+            return Vec::new();
+        }
+
+        let query = normalized(query);
+        let mut current = entry;
+        for layer in (1..self.nodes[entry as usize].links.len()).rev() {
+            current = self.search_layer(&query, current, 1, layer)[0].node;
+        }
+
+        self.search_layer(&query, current, EF_SEARCH.max(k), 0)
+            .into_iter()
+            .take(k)
+            .map(|c| (self.nodes[c.node as usize].id, c.similarity))
+            .collect()
+    }
+
+    /// Best-first search of one layer, returning up to `ef` nodes, most similar first
+    fn search_layer(&self, query: &[f32], entry: u32, ef: usize, layer: usize) -> Vec<Scored> {
+        let start = Scored {
+            similarity: dot(query, &self.nodes[entry as usize].vector),
+            node: entry,
+        };
+        let mut visited = vec![false; self.nodes.len()];
+        visited[entry as usize] = true;
+        let mut candidates = BinaryHeap::from([start]);
+        let mut results = BinaryHeap::from([Reverse(start)]);
+
+        while let Some(candidate) = candidates.pop() {
+            let worst = results.peek().map(|r| r.0.similarity).unwrap_or(f32::MIN);
+            if candidate.similarity < worst && results.len() >= ef {
+                break;
+            }
+
+            for &neighbor in &self.nodes[candidate.node as usize].links[layer] {
+                if std::mem::replace(&mut visited[neighbor as usize], true) {
+                    continue;
+                }
+                let scored = Scored {
+                    similarity: dot(query, &self.nodes[neighbor as usize].vector),
+                    node: neighbor,
+                };
+                let worst = results.peek().map(|r| r.0.similarity).unwrap_or(f32::MIN);
+                if results.len() < ef || scored.similarity > worst {
+                    candidates.push(scored);
+                    results.push(Reverse(scored));
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+
+        let mut results: Vec<Scored> = results.into_iter().map(|r| r.0).collect();
+        results.sort_by(|a, b| b.cmp(a));
+        results
+    }
+
+    /// Pick up to `max` links from candidates sorted by similarity, skipping
+    /// any candidate closer to an already picked one than to the node itself.
+    /// Links then point in diverse directions, which keeps clusters connected
+    /// to each other.
+    fn select_neighbors(&self, candidates: &[Scored], max: usize) -> Vec<u32> {
+        let mut selected: Vec<u32> = Vec::with_capacity(max);
+        for candidate in candidates {
+            if selected.len() == max {
+                break;
+            }
+            let vector = &self.nodes[candidate.node as usize].vector;
+            let diverse = selected
+                .iter()
+                .all(|&s| dot(vector, &self.nodes[s as usize].vector) < candidate.similarity);
+            if diverse {
+                selected.push(candidate.node);
+            }
+        }
+        selected
+    }
+
+    /// Cut a node's links on a layer back to `max_links`
+    fn prune(&mut self, node: u32, layer: usize, max_links: usize) {
+        let vector = &self.nodes[node as usize].vector;
+        let mut links: Vec<Scored> = self.nodes[node as usize].links[layer]
+            .iter()
+            .map(|&n| Scored {
+                similarity: dot(vector, &self.nodes[n as usize].vector),
+                node: n,
+            })
+            .collect();
+        links.sort_by(|a, b| b.cmp(a));
+        self.nodes[node as usize].links[layer] = self.select_neighbors(&links, max_links);
+    }
+
+    /// Layer for a new node: exponentially fewer nodes on each higher layer
+    fn random_level(&mut self) -> usize {
+        // xorshift64*
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let random = self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d);
+
+        let uniform = ((random >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        (-uniform.ln() / (M as f64).ln()) as usize
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        // Write to a temporary file first so an interrupted run can't leave a truncated index
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, self.encode()).with_context(|| format!("Failed to write index: {}", tmp.display()))?;
+        std::fs::rename(&tmp, path).with_context(|| format!("Failed to write index: {}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path).with_context(|| format!("Failed to read index: {}", path.display()))?;
+        Self::decode(&bytes).with_context(|| format!("Invalid index file: {}", path.display()))
+    }
+
+    /// Layout (little-endian): magic, version, dimensions, fingerprint, entry
+    /// node, node count, then per node the id, vector, layer count and each
+    /// layer's links
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.dimensions as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.fingerprint.to_le_bytes());
+        bytes.extend_from_slice(&self.entry.unwrap_or(NO_ENTRY).to_le_bytes());
+
+        bytes.extend_from_slice(&(self.nodes.len() as u32).to_le_bytes());
+        for node in &self.nodes {
+            bytes.extend_from_slice(&(node.id as u64).to_le_bytes());
+            for value in &node.vector {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            bytes.extend_from_slice(&(node.links.len() as u32).to_le_bytes());
+            for links in &node.links {
+                bytes.extend_from_slice(&(links.len() as u32).to_le_bytes());
+                for link in links {
+                    bytes.extend_from_slice(&link.to_le_bytes());
+                }
+            }
+        }
+
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut reader = ByteReader::new(bytes);
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(anyhow!("not an index file"));
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(anyhow!("unsupported index version {}", version));
+        }
+
+        let mut index = Self::new(reader.u32()? as usize).with_fingerprint(reader.u64()?);
+        let entry = reader.u32()?;
+
+        let count = reader.u32()? as usize;
+        for node in 0..count {
+            let id = reader.u64()? as usize;
+            let vector = reader.f32s(index.dimensions)?;
+            let mut links = Vec::new();
+            for _ in 0..reader.u32()? {
+                let n = reader.u32()? as usize;
+                let layer = (0..n).map(|_| reader.u32()).collect::<Result<Vec<u32>>>()?;
+                if layer.iter().any(|&link| link as usize >= count) {
+                    return Err(anyhow!("link out of range"));
+                }
+                links.push(layer);
+            }
+            if links.is_empty() {
+                return Err(anyhow!("node without layers"));
+            }
+            if index.by_id.insert(id, node as u32).is_some() {
+                return Err(anyhow!("duplicate id {}", id));
+            }
+            index.nodes.push(Node { id, vector, links });
+        }
+
+        // Searches follow links without bounds checks, so every link must
+        // point to a node that is part of the link's layer
+        for (node, links) in index.nodes.iter().map(|n| &n.links).enumerate() {
+            for (layer, layer_links) in links.iter().enumerate() {
+                if let Some(&link) = layer_links.iter().find(|&&link| index.nodes[link as usize].links.len() <= layer) {
+                    return Err(anyhow!("node {} links to node {} on layer {} it isn't part of", node, link, layer));
+                }
+            }
+        }
+
+        // Every search starts on the entry node's top layer
+        let layers = index.nodes.iter().map(|n| n.links.len()).max().unwrap_or(0);
+        index.entry = match entry {
+            NO_ENTRY if count == 0 => None,
+            entry if (entry as usize) < count && index.nodes[entry as usize].links.len() == layers => Some(entry),
+            _ => return Err(anyhow!("invalid entry node")),
+        };
+        Ok(index)
+    }
+}
+
+/// Dot product over eight independent sums, which the compiler can vectorize
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    let mut sums = [0.0f32; 8];
+    let (chunks_a, chunks_b) = (a.chunks_exact(8), b.chunks_exact(8));
+    let tail: f32 = chunks_a.remainder().iter().zip(chunks_b.remainder()).map(|(x, y)| x * y).sum();
+    for (x, y) in chunks_a.zip(chunks_b) {
+        for i in 0..8 {
+            sums[i] += x[i] * y[i];
+        }
+    }
+    sums.iter().sum::<f32>() + tail
+}
+
+/// The vector scaled to unit length; all-zero vectors stay zero
+fn normalized(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter().map(|x| x / norm).collect()
+    } else {
+        vector.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic vectors with components in [-1, 1)
+    fn random_vectors(count: usize, dimensions: usize, mut seed: u64) -> Vec<Vec<f32>> {
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+        };
+        (0..count).map(|_| (0..dimensions).map(|_| next()).collect()).collect()
+    }
+
+    fn build(vectors: &[Vec<f32>]) -> HnswIndex {
+        let mut index = HnswIndex::new(vectors[0].len()).with_fingerprint(42);
+        for (id, vector) in vectors.iter().enumerate() {
+            index.insert(id, vector).unwrap();
+        }
+        index
+    }
+
+    /// The k ids most similar to the query by a scan over all vectors
+    fn brute_force(vectors: &[Vec<f32>], query: &[f32], k: usize) -> Vec<usize> {
+        let query = normalized(query);
+        let mut scored: Vec<(usize, f32)> = vectors.iter().map(|v| dot(&query, &normalized(v))).enumerate().collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.into_iter().take(k).map(|(id, _)| id).collect()
+    }
+
+    #[test]
+    fn finds_the_exact_neighbors_of_most_queries() {
+        let vectors = random_vectors(2000, 32, 7);
+        let index = build(&vectors);
+
+        let queries = random_vectors(100, 32, 11);
+        let found: usize = queries
+            .iter()
+            .map(|query| {
+                let expected = brute_force(&vectors, query, 10);
+                index.search(query, 10).iter().filter(|(id, _)| expected.contains(id)).count()
+            })
+            .sum();
+
+        let recall = found as f64 / (queries.len() * 10) as f64;
+        assert!(recall >= 0.95, "recall@10 is {:.3}", recall);
+    }
+
+    #[test]
+    fn search_returns_the_most_similar_first() {
+        let vectors = random_vectors(200, 16, 3);
+        let index = build(&vectors);
+
+        let results = index.search(&vectors[17], 5);
+        assert_eq!(results[0].0, 17);
+        assert!((results[0].1 - 1.0).abs() < 1e-5);
+        assert!(results.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    }
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let vectors = random_vectors(500, 16, 5);
+        let index = build(&vectors);
+        let path = std::env::temp_dir().join(format!("log-reasoner-index-{}.idx", std::process::id()));
+
+        index.save(&path).unwrap();
+        let loaded = HnswIndex::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+
+        assert_eq!(loaded.fingerprint(), 42);
+        assert_eq!(loaded.dimensions(), 16);
+        assert_eq!(loaded.vector(123), index.vector(123));
+        for query in random_vectors(20, 16, 9) {
+            assert_eq!(loaded.search(&query, 10), index.search(&query, 10));
+        }
+    }
+
+    #[test]
+    fn rejects_links_to_nodes_missing_from_the_layer() {
+        let mut index = build(&random_vectors(200, 16, 5));
+        let entry = index.entry.unwrap() as usize;
+        let bottom_only = index.nodes.iter().position(|n| n.links.len() == 1).unwrap() as u32;
+        index.nodes[entry].links[1].push(bottom_only);
+
+        let error = HnswIndex::decode(&index.encode()).err().unwrap();
+        assert!(error.to_string().contains("on layer 1 it isn't part of"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_an_entry_below_the_top_layer() {
+        let mut index = build(&random_vectors(200, 16, 5));
+        index.entry = index.nodes.iter().position(|n| n.links.len() == 1).map(|node| node as u32);
+
+        let error = HnswIndex::decode(&index.encode()).err().unwrap();
+        assert_eq!(error.to_string(), "invalid entry node");
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let bytes = build(&random_vectors(50, 8, 5)).encode();
+        assert!(HnswIndex::decode(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -1,147 +1,344 @@
-use crate::models::{LogEvent, LogLevel};
-use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
-use regex::Regex;
+use crate::encoding::{Encoding, LineReader};
+use crate::formats::LineParser;
+use crate::formats::container;
+use crate::formats::detect::{self, FormatDetection, SAMPLE_LINES};
+use crate::formats::journal::{self, JournalExportReader};
+use crate::formats::plain::PlainParser;
+use crate::formats::registry::ParserRegistry;
+use crate::formats::timestamp::TimestampParser;
+use crate::models::LogEvent;
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Months, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read};
+use std::sync::Arc;
+
+/// `journalctl -o export` is record-oriented rather than line-oriented,
+/// so it is read separately from the registered line parsers
+const JOURNAL_EXPORT: &str = "journal-export";
+
+/// How an input was parsed, reported in the run summary
+#[derive(Debug, Clone, Serialize)]
+pub struct ParseSummary {
+    pub format: FormatDetection,
+    /// Text encoding the input was decoded with
+    pub encoding: String,
+    /// Lines with bytes invalid in the encoding, decoded with replacement characters
+    pub invalid_lines: usize,
+    /// Events whose timestamp was missing or couldn't be parsed
+    pub unparsed_timestamps: usize,
+}
 
 /// Parses a log file and returns structured events
 pub struct LogParser {
-    /// Regex for ISO8601 timestamps
-    timestamp_regex: Regex,
-    /// Regex for log levels
-    level_regex: Regex,
-    /// Regex for CLF (Common Log Format) style logs
-    clf_regex: Regex,
+    /// Line parsers available for detection and `--format`
+    registry: ParserRegistry,
+    /// Fallback for lines the chosen parser doesn't understand
+    plain: PlainParser,
+    /// Timestamp formats and timezone shared by all parsers
+    timestamps: TimestampParser,
+    /// Format forced with `--format`; detected per input when None
+    format: Option<String>,
+    /// Text encoding of the input, unless it starts with a byte order mark
+    encoding: Encoding,
 }
 
 impl LogParser {
-    pub fn new() -> Self {
+    pub fn new(timestamps: TimestampParser) -> Self {
         Self {
-            // Matches: 2024-01-05T12:01:03Z or 2024-01-05 12:01:03
-            timestamp_regex: Regex::new(
-                r"(\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(?:\.\d+)?(?:Z|[+-]\d{2}:?\d{2})?)"
-            ).unwrap(),
-            
-            // Matches: ERROR, WARN, INFO, etc. (case-insensitive)
-            level_regex: Regex::new(
-                r"(?i)\b(ERROR|ERR|WARN|WARNING|INFO|DEBUG|TRACE)\b"
-            ).unwrap(),
-
-            // Matches: 127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET /index.html HTTP/1.0" 200 2326
-            clf_regex: Regex::new(
-                r#"^(\S+) \S+ \S+ \[([\w:/]+\s[+\-]\d{4})\] ".*?" (\d{3}) (\d+|-)"#
-            ).unwrap(),
+            registry: ParserRegistry::new().with_builtins(&timestamps),
+            plain: PlainParser::new(timestamps.clone()),
+            timestamps,
+            format: None,
+            encoding: Encoding::Utf8,
         }
     }
-        /// Parse a log file from path
-    pub fn parse_file(&self, path: &str) -> Result<Vec<LogEvent>> {
+
+    /// Parse every input with this format (or comma-separated list of
+    /// formats) instead of detecting it
+    pub fn with_format(mut self, format: Option<String>) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Decode inputs with this encoding (default: UTF-8)
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Register additional parsers; they take priority over the built-in ones
+    pub fn with_parsers(mut self, parsers: Vec<Arc<dyn LineParser>>) -> Self {
+        let mut registry = ParserRegistry::new();
+        for parser in parsers {
+            registry.register(parser);
+        }
+        self.registry = registry.with_builtins(&self.timestamps);
+        self
+    }
+
+    /// Names and descriptions of all selectable formats
+    pub fn formats(&self) -> Vec<(String, String)> {
+        let mut formats: Vec<(String, String)> = self
+            .registry
+            .parsers()
+            .iter()
+            .map(|p| (p.name().to_string(), p.description().to_string()))
+            .collect();
+        formats.push((JOURNAL_EXPORT.to_string(), "journalctl -o export dumps".to_string()));
+        formats
+    }
+
+    /// Check that a `--format` value names known formats
+    pub fn validate_format(&self, spec: &str) -> Result<()> {
+        if spec.eq_ignore_ascii_case(JOURNAL_EXPORT) || self.registry.resolve(spec).is_some() {
+            return Ok(());
+        }
+
+        let names: Vec<String> = self.formats().into_iter().map(|(name, _)| name).collect();
+        Err(anyhow!("Unknown format '{}'. Available: auto, {}", spec, names.join(", ")))
+    }
+
+    /// Parse a log file from path
+    pub fn parse_file(&self, path: &str) -> Result<(Vec<LogEvent>, ParseSummary)> {
+        let detection = match self.format {
+            Some(ref format) => {
+                self.validate_format(format)?;
+                FormatDetection::explicit(format)
+            }
+            None => self.detect_format(path)?,
+        };
         let file = File::open(path)
             .with_context(|| format!("Failed to open log file: {}", path))?;
-        
         let reader = BufReader::new(file);
+
+        // journalctl -o export is a binary-safe multi-line record format
+        if detection.format.eq_ignore_ascii_case(JOURNAL_EXPORT) {
+            let events = self.parse_journal_export(reader)?;
+            let summary = Self::summarize(detection, Encoding::Utf8, 0, &events);
+            return Ok((events, summary));
+        }
+
+        let parser = self
+            .registry
+            .resolve(&detection.format)
+            .ok_or_else(|| anyhow!("Unknown format '{}'", detection.format))?;
+
         let mut events = Vec::new();
 
-        for line in reader.lines() {
-            let line = line?;
+        // Years of year-less timestamps are inferred from the other timestamps
+        // and, failing those, from when the file was last written
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok().map(DateTime::<Utc>::from);
+        self.timestamps.begin_input(modified);
+
+        // Kubernetes metadata from the kubelet's file naming, if any
+        let pod_fields = container::kubernetes_metadata(path);
+
+        // Numbered lines of records still waiting for their final line, by record key
+        let mut pending: HashMap<Option<String>, Vec<(usize, String)>> = HashMap::new();
+
+        // Lines are decoded lossily: invalid bytes are counted, not fatal
+        let mut lines = LineReader::new(reader, self.encoding);
+        let mut line_number = 0;
+
+        while let Some(line) = lines.next_line()? {
+            line_number += 1;
             
             // Skip empty lines
             if line.trim().is_empty() {
                 continue;
             }
 
-            events.push(self.parse_line(&line));
+            let partial = parser.is_partial(&line);
+            let key = parser.record_key(&line);
+            let record = pending.entry(key.clone()).or_default();
+            record.push((line_number, line));
+
+            if !partial {
+                events.push(self.parse_record(parser.as_ref(), record));
+                pending.remove(&key);
+            }
+        }
+
+        for record in pending.values() {
+            events.push(self.parse_record(parser.as_ref(), record));
         }
 
-        Ok(events)
+        // Records complete out of order when streams interleave
+        events.sort_by_key(|event| event.line_number);
+
+        let years = self.timestamps.end_input();
+        if years > 0 {
+            for event in &mut events {
+                event.timestamp = event.timestamp.and_then(|t| t.checked_sub_months(Months::new(12 * years)));
+            }
+        }
+
+        for event in &mut events {
+            for (key, value) in &pod_fields {
+                event.fields.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+        }
+
+        let summary = Self::summarize(detection, lines.encoding(), lines.invalid_lines, &events);
+        Ok((events, summary))
     }
-        /// Parse a single log line
-    fn parse_line(&self, line: &str) -> LogEvent {
-        let timestamp = self.extract_timestamp(line);
-        let level = self.extract_level(line);
-        let message = self.extract_message(line, &timestamp, &level);
-
-        LogEvent {
-            timestamp,
-            level,
-            message,
-            raw: line.to_string(),
+
+    fn summarize(format: FormatDetection, encoding: Encoding, invalid_lines: usize, events: &[LogEvent]) -> ParseSummary {
+        ParseSummary {
+            format,
+            encoding: encoding.name().to_string(),
+            invalid_lines,
+            unparsed_timestamps: events.iter().filter(|e| e.timestamp.is_none()).count(),
         }
     }
-        /// Extract timestamp if present
-    fn extract_timestamp(&self, line: &str) -> Option<DateTime<Utc>> {
-        // Try CLF format first as it's more specific
-        if let Some(cap) = self.clf_regex.captures(line) {
-            if let Some(m) = cap.get(2) {
-                if let Ok(dt) = DateTime::parse_from_str(m.as_str(), "%d/%b/%Y:%H:%M:%S %z") {
-                    return Some(dt.with_timezone(&Utc));
-                }
+
+    /// Detect the format from the first lines of a file
+    fn detect_format(&self, path: &str) -> Result<FormatDetection> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open log file: {}", path))?;
+        let mut reader = BufReader::new(file);
+
+        if journal::is_export_format(reader.fill_buf()?) {
+            return Ok(FormatDetection {
+                detected: true,
+                ..FormatDetection::explicit(JOURNAL_EXPORT)
+            });
+        }
+
+        let mut sample = Vec::new();
+        let mut lines = LineReader::new(reader, self.encoding);
+        while let Some(line) = lines.next_line()? {
+            if !line.trim().is_empty() {
+                sample.push(line);
+            }
+            if sample.len() >= SAMPLE_LINES {
+                break;
             }
         }
 
-        // Fallback to general timestamp regex
-        self.timestamp_regex
-            .captures(line)
-            .and_then(|cap| cap.get(1))
-            .and_then(|m| {
-                // Try parsing with multiple formats
-                let ts_str = m.as_str();
-                
-                // ISO8601 with Z
-                if let Ok(dt) = DateTime::parse_from_rfc3339(ts_str) {
-                    return Some(dt.with_timezone(&Utc));
-                }
-                
-                // Space-separated format
-                if let Ok(dt) = DateTime::parse_from_str(
-                    &format!("{}Z", ts_str.replace(" ", "T")),
-                    "%Y-%m-%dT%H:%M:%S%Z"
-                ) {
-                    return Some(dt.with_timezone(&Utc));
-                }
-                
-                None
-            })
+        Ok(detect::detect_sample(self.registry.parsers(), &self.plain, &sample))
     }
-        /// Extract the actual message (remove timestamp and level)
-    fn extract_message(&self, line: &str, timestamp: &Option<DateTime<Utc>>, level: &Option<LogLevel>) -> String {
-        let mut msg = line.to_string();
 
-        // Remove timestamp if found
-        if timestamp.is_some() {
-            msg = self.timestamp_regex.replace(&msg, "").to_string();
-        }
+    /// Parse `journalctl -o export` records
+    fn parse_journal_export<R: Read>(&self, reader: BufReader<R>) -> Result<Vec<LogEvent>> {
+        let mut records = JournalExportReader::new(reader);
+        let mut events = Vec::new();
 
-        // Remove level if found
-        if level.is_some() {
-            msg = self.level_regex.replace(&msg, "").to_string();
+        while let Some((fields, line_number)) = records.next_record()? {
+            events.push(journal::journal_event(&fields, line_number));
         }
 
-        // Clean up whitespace
-        msg.trim().to_string()
+        Ok(events)
     }
-        /// Extract log level if present
-    fn extract_level(&self, line: &str) -> Option<LogLevel> {
-        // Try to find explicit log level first
-        if let Some(cap) = self.level_regex.captures(line) {
-            if let Some(m) = cap.get(1) {
-                return LogLevel::from_str(m.as_str());
-            }
+
+    /// Parse a record (usually a single line) with the chosen parser, falling
+    /// back to the generic extraction for lines the parser doesn't understand
+    fn parse_record(&self, parser: &dyn LineParser, lines: &[(usize, String)]) -> LogEvent {
+        let parsed = match lines {
+            [(line_number, line)] => parser.parse(line, *line_number),
+            _ => parser.parse_record(lines),
+        };
+
+        let Some(mut event) = parsed else {
+            let text: Vec<&str> = lines.iter().map(|(_, line)| line.as_str()).collect();
+            return self.plain.parse_line(&text.join("\n"), lines[0].0);
+        };
+
+        // Structured formats may carry the level only inside the message
+        if event.level.is_none() {
+            event.level = self.plain.extract_level(&event.message);
         }
 
-        // If no explicit level, check if it's a CLF log and infer from status code
-        if let Some(cap) = self.clf_regex.captures(line) {
-            if let Some(m) = cap.get(3) {
-                if let Ok(status) = m.as_str().parse::<u16>() {
-                    return match status {
-                        500..=599 => Some(LogLevel::Error),
-                        400..=499 => Some(LogLevel::Warn),
-                        _ => Some(LogLevel::Info),
-                    };
-                }
-            }
+        event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grouper::LogGrouper;
+    use crate::models::LogLevel;
+
+    /// Parse `contents` as a file named `name` in a scratch directory
+    fn parse(parser: &LogParser, name: &str, contents: &[u8]) -> (Vec<LogEvent>, ParseSummary) {
+        let dir = std::env::temp_dir().join(format!("log-reasoner-ingest-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        let result = parser.parse_file(path.to_str().unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        result
+    }
+
+    #[test]
+    fn reassembles_interleaved_container_streams() {
+        let log = "\
+2024-01-05T12:00:00.000000000Z stdout P first half
+2024-01-05T12:00:00.500000000Z stderr F WARN disk low
+
+2024-01-05T12:00:01.000000000Z stdout F  second half ERROR boom
+2024-01-05T12:00:02.000000000Z stderr P trailing
+";
+        let (events, summary) = parse(&LogParser::new(TimestampParser::new()), "cri.log", log.as_bytes());
+
+        assert_eq!(summary.format.format, "cri");
+        let records: Vec<(usize, &str, Option<LogLevel>)> = events
+            .iter()
+            .map(|e| (e.line_number, e.fields["stream"].as_str(), e.level.clone()))
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                (1, "stdout", Some(LogLevel::Error)),
+                (2, "stderr", Some(LogLevel::Warn)),
+                (5, "stderr", None),
+            ]
+        );
+        assert!(events[0].message.starts_with("first half second half"));
+    }
+
+    #[test]
+    fn splits_journal_patterns_by_unit() {
+        let mut export = Vec::new();
+        for (us, unit, message) in [
+            (1, "web.service", "connection reset by peer"),
+            (2, "db.service", "connection reset by peer"),
+            (3, "web.service", "connection reset by peer"),
+        ] {
+            export.extend(format!("__REALTIME_TIMESTAMP={}\nPRIORITY=3\n_SYSTEMD_UNIT={}\n", 1704456063000000i64 + us, unit).into_bytes());
+            export.extend(b"MESSAGE\n");
+            export.extend((message.len() as u64).to_le_bytes());
+            export.extend(format!("{}\n\n", message).into_bytes());
         }
+        let (events, summary) = parse(&LogParser::new(TimestampParser::new()), "app.export", &export);
+
+        assert_eq!(summary.format.format, JOURNAL_EXPORT);
+        assert_eq!(events.len(), 3);
+
+        let groups = LogGrouper::new().with_split_field(Some("unit".to_string())).group_events(events.clone());
+        let partitions: Vec<(Option<&str>, usize)> = groups.iter().map(|g| (g.partition.as_deref(), g.count)).collect();
+        assert_eq!(partitions, vec![(Some("unit=web.service"), 2), (Some("unit=db.service"), 1)]);
 
-        None
+        assert_eq!(LogGrouper::new().group_events(events).len(), 1);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn format_overrides_detection() {
+        let log = "time=2024-01-05T12:01:03Z level=error msg=\"db timeout\"\n";
+
+        let (_, detected) = parse(&LogParser::new(TimestampParser::new()), "detected.log", log.as_bytes());
+        assert_eq!((detected.format.format.as_str(), detected.format.detected), ("logfmt", true));
+
+        let forced = LogParser::new(TimestampParser::new()).with_format(Some("plain".to_string()));
+        let (events, summary) = parse(&forced, "forced.log", log.as_bytes());
+        assert_eq!((summary.format.format.as_str(), summary.format.detected), ("plain", false));
+        assert_eq!(events[0].fields.get("msg"), None);
+
+        let parser = LogParser::new(TimestampParser::new());
+        assert!(parser.validate_format("json,logfmt").is_ok());
+        assert!(parser.validate_format("json,nope").is_err());
+    }
+}
@@ -5,105 +5,619 @@ mod cli;
 mod output;
 mod backends;
 mod embedding;
+mod analysis;
+mod ask;
+mod formats;
+mod config;
+mod encoding;
+mod reasoning;
+mod report;
+mod cache;
+mod index;
 
 use clap::Parser;
-use cli::{Cli, Commands};
-use ingest::LogParser;
+use cli::{AnalyzeArgs, AskArgs, BackendArgs, CacheAction, CacheArgs, Cli, Commands, FormatsArgs, InputArgs, ReportArgs};
+use ingest::{LogParser, ParseSummary};
+use config::{Config, FormatConfig};
+use encoding::Encoding;
+use formats::LineParser;
+use formats::custom::CustomFormat;
+use formats::timestamp::{TimestampParser, Timezone};
 use grouper::LogGrouper;
+use models::LogEvent;
 use output::OutputFormatter;
-use backends::ollama::OllamaBackend;
+use backends::Backend;
 use embedding::EmbeddingGenerator;
+use cache::EmbeddingCache;
+use reasoning::Explainer;
+use report::{IncidentReport, IncidentReporter, IncidentStats};
+use analysis::{AnalysisReport, TimeBuckets};
+use ask::QuestionAnswerer;
+use analysis::access::AccessAnalyzer;
+use analysis::changepoint::ChangePointDetector;
+use analysis::metrics::MetricExtractor;
+use analysis::correlation::SessionCorrelator;
+use analysis::periodicity::PeriodicityDetector;
+use analysis::precedence::PrecedenceMiner;
+use analysis::root_cause::RootCauseRanker;
+use analysis::similarity::SemanticClusterer;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Instant;
 
 fn main() {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Analyze {
-            file,
-            top,
-            min_count,
-            output,
-            errors_only,
-        } => {
-            analyze_logs(&file, top, min_count, &output, errors_only);
+        Commands::Analyze(args) => {
+            analyze_logs(&args);
+        }
+        Commands::Report(args) => {
+            write_report(&args);
+        }
+        Commands::Ask(args) => {
+            ask_question(&args);
+        }
+        Commands::Formats(args) => {
+            list_formats(&args);
+        }
+        Commands::Cache(args) => {
+            manage_cache(&args);
         }
     }
 }
 
-fn analyze_logs(file_path: &str, top_n: usize, min_count: usize, output_format: &str, errors_only: bool) {
-    println!("Log Reasoner v0.1.0");
-    println!("Analyzing: {}\n", file_path);
+/// Load the config file, exiting on errors
+fn load_config(path: Option<&str>) -> Config {
+    match Config::load(path) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("✗ Error: {:#}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Build the selected backend, exiting on errors. Options and environment
+/// variables override the config; None means `--backend none`.
+fn select_backend(args: &BackendArgs, config: &Config) -> Option<Backend> {
+    let mut settings = config.backend.clone();
+    for (option, setting) in [
+        (&args.backend, &mut settings.name),
+        (&args.llm_url, &mut settings.url),
+        (&args.embed_model, &mut settings.embed_model),
+        (&args.llm_model, &mut settings.llm_model),
+        (&args.fixture, &mut settings.fixture),
+    ] {
+        if option.is_some() {
+            *setting = option.clone();
+        }
+    }
+    if args.record {
+        settings.record = Some(true);
+    }
+
+    match Backend::from_config(&settings, &config.openai) {
+        Ok(backend) => backend,
+        Err(e) => {
+            eprintln!("✗ Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Directory of the embedding cache: the configured one or the default
+fn cache_dir(config: &Config) -> Option<PathBuf> {
+    config.cache.dir.as_ref().map(PathBuf::from).or_else(EmbeddingCache::default_dir)
+}
+
+/// Open the embedding cache unless disabled; an unreadable cache is reported
+/// and the run continues without it
+fn open_cache(args: &BackendArgs, config: &Config) -> Option<EmbeddingCache> {
+    if args.no_cache || config.cache.enabled == Some(false) {
+        return None;
+    }
+
+    let dir = cache_dir(config)?;
+    match EmbeddingCache::open(&dir) {
+        Ok(cache) => {
+            let max_size_mb = config.cache.max_size_mb.unwrap_or(cache::DEFAULT_MAX_SIZE_MB);
+            Some(cache.with_max_size(max_size_mb * 1024 * 1024))
+        }
+        Err(e) => {
+            eprintln!("⚠ Warning: Not using the embedding cache: {:#}", e);
+            eprintln!("  Clear it with: log-reasoner cache clear");
+            None
+        }
+    }
+}
+
+/// Build the timestamp parser from the config; `--timezone` and `--year` override the configured ones
+fn timestamp_parser(config: &Config, timezone: Option<&str>, year: Option<i32>) -> TimestampParser {
+    let mut timestamps = TimestampParser::new()
+        .with_formats(config.timestamps.formats.clone())
+        .with_year(year.or(config.timestamps.year));
+
+    if let Some(name) = timezone.or(config.timestamps.timezone.as_deref()) {
+        match Timezone::from_name(name) {
+            Ok(timezone) => timestamps = timestamps.with_timezone(timezone),
+            Err(e) => {
+                eprintln!("✗ Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    timestamps
+}
+
+/// Build parsers for the custom formats in the config file, plus an ad-hoc
+/// format from the command line, which takes priority
+fn custom_parsers(
+    config: &Config,
+    ad_hoc: Option<FormatConfig>,
+    timestamps: &TimestampParser,
+) -> Vec<Arc<dyn LineParser>> {
+    let mut parsers: Vec<Arc<dyn LineParser>> = Vec::new();
+    for format in ad_hoc.iter().chain(&config.formats) {
+        match CustomFormat::from_config(format, timestamps) {
+            Ok(custom) => parsers.push(Arc::new(custom)),
+            Err(e) => {
+                eprintln!("✗ Error: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    parsers
+}
+
+fn list_formats(args: &FormatsArgs) {
+    let config = load_config(args.config.as_deref());
+    let timestamps = timestamp_parser(&config, None, None);
+    let parser = LogParser::new(timestamps.clone()).with_parsers(custom_parsers(&config, None, &timestamps));
+
+    println!("Available formats (detection order):\n");
+    for (name, description) in parser.formats() {
+        println!("  {:<16} {}", name, description);
+    }
+}
+
+fn manage_cache(args: &CacheArgs) {
+    let config = load_config(args.config.as_deref());
+    let Some(dir) = cache_dir(&config) else {
+        eprintln!("✗ Error: No cache directory; set dir in the [cache] section of the config");
+        std::process::exit(1);
+    };
+
+    match args.action {
+        CacheAction::Stats => {
+            let cache = match EmbeddingCache::open(&dir) {
+                Ok(cache) => cache.with_max_size(config.cache.max_size_mb.unwrap_or(cache::DEFAULT_MAX_SIZE_MB) * 1024 * 1024),
+                Err(e) => {
+                    eprintln!("✗ Error: {:#}", e);
+                    std::process::exit(1);
+                }
+            };
+            OutputFormatter::format_cache_stats(&cache.stats());
+        }
+        CacheAction::Clear => match EmbeddingCache::clear(&dir) {
+            Ok(0) => println!("✓ The embedding cache is already empty"),
+            Ok(freed) => println!("✓ Cleared the embedding cache ({} freed)", OutputFormatter::format_bytes(freed)),
+            Err(e) => {
+                eprintln!("✗ Error: {:#}", e);
+                std::process::exit(1);
+            }
+        },
+    }
+}
+
+/// Parse the input file, exiting on errors. Progress goes to stderr when
+/// stdout carries the result (e.g. a Markdown report).
+fn load_events(args: &InputArgs, config: &Config, status_to_stderr: bool) -> (Vec<LogEvent>, ParseSummary, TimestampParser) {
+    let status = |message: String| {
+        if status_to_stderr {
+            eprintln!("{}", message);
+        } else {
+            println!("{}", message);
+        }
+    };
+
+    // An ad-hoc --pattern or --grok format is used for every line
+    let ad_hoc = (args.pattern.is_some() || args.grok.is_some()).then(|| FormatConfig {
+        name: "custom".to_string(),
+        regex: args.pattern.clone(),
+        grok: args.grok.clone(),
+        time_format: args.time_format.clone(),
+    });
+
+    let format = match args.format.as_str() {
+        _ if ad_hoc.is_some() => Some("custom".to_string()),
+        "auto" => None,
+        name => Some(name.to_string()),
+    };
+
+    let encoding = match Encoding::from_name(&args.encoding) {
+        Ok(encoding) => encoding,
+        Err(e) => {
+            eprintln!("✗ Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let timestamps = timestamp_parser(config, args.timezone.as_deref(), args.year);
+    let parser = LogParser::new(timestamps.clone())
+        .with_parsers(custom_parsers(config, ad_hoc, &timestamps))
+        .with_format(format.clone())
+        .with_encoding(encoding);
+
+    if let Some(ref format) = format
+        && let Err(e) = parser.validate_format(format)
+    {
+        eprintln!("✗ Error: {}", e);
+        std::process::exit(1);
+    }
 
-    // Step 1: Parse logs
     let start = Instant::now();
-    let parser = LogParser::new();
-    let mut events = match parser.parse_file(file_path) {
-        Ok(events) => {
+    match parser.parse_file(&args.file) {
+        Ok((events, summary)) => {
             let parse_time = start.elapsed();
-            println!("✓ Parsed {} log events ({:.2?})", events.len(), parse_time);
-            events
+            let detection = &summary.format;
+            if detection.detected {
+                status(format!(
+                    "✓ Detected format: {} ({:.0}% confidence)",
+                    detection.format,
+                    detection.confidence * 100.0
+                ));
+            }
+            status(format!("✓ Parsed {} log events ({:.2?})", events.len(), parse_time));
+            if summary.invalid_lines > 0 {
+                eprintln!(
+                    "⚠ Warning: {} lines are not valid {}; invalid bytes were replaced",
+                    summary.invalid_lines, summary.encoding
+                );
+            }
+            if summary.unparsed_timestamps > 0 {
+                eprintln!(
+                    "⚠ Warning: {} of {} events have no parseable timestamp",
+                    summary.unparsed_timestamps,
+                    events.len()
+                );
+            }
+            (events, summary, timestamps)
         }
         Err(e) => {
             eprintln!("✗ Error parsing logs: {}", e);
             std::process::exit(1);
         }
-    };
+    }
+}
+
+fn analyze_logs(args: &AnalyzeArgs) {
+    let top_n = args.top;
+
+    println!("Log Reasoner v0.1.0");
+    println!("Analyzing: {}\n", args.input.file);
+
+    // Step 1: Parse logs
+    let config = load_config(args.input.config.as_deref());
+    // The model server is only contacted for the analyses that use it
+    let needs_index = args.clusters || args.similar_to.is_some() || args.backend.index.is_some();
+    let backend = if needs_index || args.explain { select_backend(&args.backend, &config) } else { None };
+    let (mut events, summary, timestamps) = load_events(&args.input, &config, false);
 
     // Filter for errors only if requested
-    if errors_only {
+    if args.errors_only {
         events.retain(|e| {
-            matches!(e.level, Some(models::LogLevel::Error))
+            e.level.as_ref().is_some_and(|l| l.is_error())
         });
         println!("✓ Filtered to {} ERROR events", events.len());
     }
 
     // Step 2: Group similar events
     let start = Instant::now();
-    let grouper = LogGrouper::new();
+    let grouper = LogGrouper::new().with_split_field(args.split_by.clone());
     let mut groups = grouper.group_events(events);
     let group_time = start.elapsed();
     
     // Filter by minimum count
-    groups.retain(|g| g.count >= min_count);
+    groups.retain(|g| g.count >= args.min_count);
     
     let stats = LogGrouper::get_stats(&groups);
     println!("✓ Grouped into {} unique patterns ({:.2?})", stats.unique_patterns, group_time);
 
-    // Step 3: Generate embeddings (optional - check if Ollama is available)
-    let ollama = OllamaBackend::new();
-    
-    match ollama.check_available() {
-        Ok(_) => {
-            println!("\n✓ Ollama detected, generating embeddings...");
+    let mut report = AnalysisReport {
+        source: Some(summary),
+        ..Default::default()
+    };
+
+    // Periodicity runs first since demotion changes the pattern ranking
+    if args.periodicity || args.demote_periodic {
+        let mut periodic = PeriodicityDetector::new().detect(&groups);
+        println!("✓ Detected {} periodic patterns", periodic.len());
+
+        if args.demote_periodic {
+            PeriodicityDetector::demote(&mut groups, &mut periodic);
+        }
+        report.periodicity = Some(periodic);
+    }
+
+    // Step 3: Index the pattern embeddings to compare patterns with each other
+    match backend {
+        Some(ref backend) if needs_index => match backend.check_embedding() {
+            Ok(_) => {
+                println!("\n✓ {} detected, generating embeddings...", backend.name());
             
-            let start = Instant::now();
-            let embedding_gen = EmbeddingGenerator::new(ollama);
+                let start = Instant::now();
+                // Local embeddings are cheaper to recompute than to cache, and
+                // recorded ones must all reach the backend
+                let cache = if backend.is_cacheable() { open_cache(&args.backend, &config) } else { None };
+                let mut embedding_gen = EmbeddingGenerator::new(backend);
+                if let Some(cache) = cache {
+                    embedding_gen = embedding_gen.with_cache(cache);
+                }
             
-            match embedding_gen.embed_groups(&groups) {
-                Ok(embeddings) => {
-                    let embed_time = start.elapsed();
-                    println!("✓ Generated embeddings ({:.2?})", embed_time);
-                    
-                    // TODO: Use embeddings for semantic clustering (Step 15)
-                    // For now, just show we got them
-                    println!("  Embedding dimension: {}", embeddings[0].1.len());
+                // A loaded index would leave the recording without embeddings
+                if let Some(ref path) = args.backend.index {
+                    embedding_gen = embedding_gen
+                        .with_index_file(PathBuf::from(path))
+                        .with_index_reuse(!backend.is_recording());
+                }
+
+                match embedding_gen.index_groups(&groups) {
+                    Ok(index) => {
+                        let embed_time = start.elapsed();
+                        println!("✓ Generated embeddings ({:.2?})", embed_time);
+                        if let Some(stats) = embedding_gen.cache_stats() {
+                            println!("  Cache: {} hits, {} misses, {} entries", stats.hits, stats.misses, stats.entries);
+                        }
+                        println!("  Embedding dimension: {}", index.dimensions());
+
+                        if args.clusters {
+                            let start = Instant::now();
+                            let clusters = SemanticClusterer::new(args.cluster_threshold).cluster(&groups, &index);
+                            println!("✓ Found {} semantic clusters ({:.2?})", clusters.len(), start.elapsed());
+                            report.clusters = Some(clusters);
+                        }
+
+                        if let Some(pattern_id) = args.similar_to {
+                            match SemanticClusterer::similar_to(&groups, &index, pattern_id, top_n) {
+                                Some(similar) => report.similar = Some(similar),
+                                None => eprintln!("⚠ Warning: No pattern #{} to compare with", pattern_id),
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("⚠ Warning: Failed to generate embeddings: {}", e);
+                        eprintln!("  Continuing with pattern-based grouping only...\n");
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("⚠ Warning: {} not available: {}", backend.name(), e);
+                eprintln!("  Continuing with pattern-based grouping only...\n");
+            }
+        },
+        None if args.clusters || args.similar_to.is_some() => {
+            eprintln!("⚠ Warning: --clusters and --similar-to need an embedding backend, skipping them");
+        }
+        _ => {}
+    }
+
+    // Step 4: Optional analyses over the grouped events
+    if args.precedence {
+        let start = Instant::now();
+        let miner = PrecedenceMiner::new(chrono::Duration::seconds(args.precedence_window));
+        let relations = miner.mine(&groups, top_n);
+        println!("✓ Mined {} precedence relationships ({:.2?})", relations.len(), start.elapsed());
+        report.precedence = Some(relations);
+    }
+
+    if let Some(ref incident) = args.incident {
+        let incident = match RootCauseRanker::parse_incident_time(incident, &timestamps) {
+            Ok(incident) => incident,
+            Err(e) => {
+                eprintln!("✗ Error: {}", e);
+                std::process::exit(1);
+            }
+        };
+
+        let ranker = RootCauseRanker::new(incident, chrono::Duration::seconds(args.incident_window));
+        let candidates = ranker.rank(&groups);
+        println!("✓ Ranked {} root-cause candidates", candidates.len());
+        report.root_causes = Some(candidates);
+    }
+
+    if args.sessions {
+        let start = Instant::now();
+        let correlator = SessionCorrelator::new(&args.correlation_keys);
+        let sessions = correlator.reconstruct(&groups, top_n);
+        println!(
+            "✓ Reconstructed {} sessions, {} ended in ERROR ({:.2?})",
+            sessions.total_sessions,
+            sessions.error_sessions,
+            start.elapsed()
+        );
+        report.sessions = Some(sessions);
+    }
+
+    let buckets = TimeBuckets::covering(&groups, args.bucket.map(chrono::Duration::seconds));
+
+    if args.change_points {
+        match buckets {
+            Some(ref buckets) => {
+                let start = Instant::now();
+                let detected = ChangePointDetector::new(buckets.clone()).detect(&groups);
+                println!(
+                    "✓ Detected {} change points over {} buckets ({:.2?})",
+                    detected.change_points.len(),
+                    detected.buckets,
+                    start.elapsed()
+                );
+                report.change_points = Some(detected);
+            }
+            None => eprintln!("⚠ Warning: No timestamps found, skipping change-point detection"),
+        }
+    }
+
+    if args.metrics {
+        let start = Instant::now();
+        let metrics = MetricExtractor::new().analyze(&groups, top_n, buckets.as_ref());
+        println!("✓ Extracted metrics for {} patterns ({:.2?})", metrics.len(), start.elapsed());
+        report.metrics = Some(metrics);
+    }
+
+    if args.access {
+        match AccessAnalyzer::new().analyze(&groups, top_n, buckets.as_ref()) {
+            Some(access) => {
+                println!("✓ Analyzed {} HTTP requests", access.total_requests);
+                report.access = Some(access);
+            }
+            None => eprintln!("⚠ Warning: No access-log lines found, skipping access analytics"),
+        }
+    }
+
+    // Step 5: Explain the top patterns with an LLM
+    if args.explain {
+        match backend {
+            Some(ref backend) => match backend.check_reasoning() {
+                Ok(_) => {
+                    println!("✓ Asking the model to explain the top {} patterns...", top_n.min(groups.len()));
+                    let start = Instant::now();
+                    let explanations = Explainer::new(backend).explain(&groups, top_n);
+                    println!("✓ Explained {} patterns ({:.2?})", explanations.len(), start.elapsed());
+                    report.explanations = Some(explanations);
+                }
+                Err(e) => eprintln!("⚠ Warning: Cannot explain patterns: {}", e),
+            },
+            None => eprintln!("⚠ Warning: --explain needs a model backend, skipping explanations"),
+        }
+    }
+
+    // Step 6: Output results
+    match args.output.as_str() {
+        "json" => OutputFormatter::format_json(&groups, &stats, top_n, &report),
+        _ => OutputFormatter::format_text(&groups, &stats, top_n, &report),
+    }
+}
+
+fn write_report(args: &ReportArgs) {
+    eprintln!("Log Reasoner v0.1.0");
+    eprintln!("Reporting on: {}\n", args.input.file);
+
+    // Progress goes to stderr so the report can be piped
+    let config = load_config(args.input.config.as_deref());
+    let backend = select_backend(&args.backend, &config);
+    let (events, summary, _) = load_events(&args.input, &config, true);
+
+    let grouper = LogGrouper::new().with_split_field(args.split_by.clone());
+    let mut groups = grouper.group_events(events);
+    groups.retain(|g| g.count >= args.min_count);
+    eprintln!("✓ Grouped into {} unique patterns", groups.len());
+
+    let stats = IncidentStats::collect(
+        &args.input.file,
+        &summary.format.format,
+        &groups,
+        args.top,
+        args.bucket.map(chrono::Duration::seconds),
+    );
+
+    let prompt = stats.build_prompt(args.max_tokens);
+    eprintln!("✓ Built a prompt of about {} tokens", prompt.used_tokens());
+
+    let narrative = match backend.map(|backend| (backend.check_reasoning(), backend)) {
+        Some((Ok(_), backend)) => {
+            let start = Instant::now();
+            match IncidentReporter::new(backend).narrate(&prompt) {
+                Ok(narrative) => {
+                    eprintln!("✓ Generated the incident narrative ({:.2?})", start.elapsed());
+                    Some(narrative)
                 }
                 Err(e) => {
-                    eprintln!("⚠ Warning: Failed to generate embeddings: {}", e);
-                    eprintln!("  Continuing with pattern-based grouping only...\n");
+                    eprintln!("⚠ Warning: Failed to generate the incident narrative: {:#}", e);
+                    None
                 }
             }
         }
-        Err(e) => {
-            eprintln!("⚠ Warning: Ollama not available: {}", e);
-            eprintln!("  Continuing with pattern-based grouping only...\n");
+        Some((Err(e), _)) => {
+            eprintln!("⚠ Warning: Cannot generate the incident narrative: {}", e);
+            eprintln!("  Writing the report with statistics only...");
+            None
+        }
+        None => {
+            eprintln!("✓ No model backend, writing the report with statistics only");
+            None
+        }
+    };
+
+    let report = IncidentReport {
+        stats,
+        narrative,
+        prompt_tokens: prompt.used_tokens(),
+        omitted: prompt.omitted(),
+    };
+    let markdown = OutputFormatter::format_markdown(&report);
+
+    match args.output_file {
+        Some(ref path) => {
+            if let Err(e) = std::fs::write(path, markdown) {
+                eprintln!("✗ Error: Failed to write {}: {}", path, e);
+                std::process::exit(1);
+            }
+            eprintln!("✓ Wrote the report to {}", path);
         }
+        None => print!("{}", markdown),
+    }
+}
+
+fn ask_question(args: &AskArgs) {
+    println!("Log Reasoner v0.1.0");
+    println!("Asking about: {}\n", args.input.file);
+
+    let config = load_config(args.input.config.as_deref());
+    // Both the embedding and the reasoning model are required
+    let Some(backend) = select_backend(&args.backend, &config) else {
+        eprintln!("✗ Error: ask needs a model backend (--backend ollama or openai)");
+        std::process::exit(1);
+    };
+    let (events, _, _) = load_events(&args.input, &config, false);
+
+    let grouper = LogGrouper::new().with_split_field(args.split_by.clone());
+    let groups = grouper.group_events(events);
+    println!("✓ Grouped into {} unique patterns", groups.len());
+
+    if let Err(e) = backend.check_embedding().and_then(|_| backend.check_reasoning()) {
+        eprintln!("✗ Error: {}", e);
+        std::process::exit(1);
     }
 
-    // Step 4: Output results
-    match output_format {
-        "json" => OutputFormatter::format_json(&groups, &stats, top_n),
-        _ => OutputFormatter::format_text(&groups, &stats, top_n),
+    let cache = if backend.is_cacheable() { open_cache(&args.backend, &config) } else { None };
+    let mut answerer = QuestionAnswerer::new(&backend, &backend)
+        .with_top_k(args.top_k)
+        .with_samples(args.samples)
+        .with_budget(args.max_tokens);
+    if let Some(cache) = cache {
+        answerer = answerer.with_cache(cache);
     }
-}
\ No newline at end of file
+    if let Some(ref path) = args.backend.index {
+        answerer = answerer
+            .with_index_file(PathBuf::from(path))
+            .with_index_reuse(!backend.is_recording());
+    }
+
+    let start = Instant::now();
+    let answer = match answerer.ask(&args.question, &groups) {
+        Ok(answer) => answer,
+        Err(e) => {
+            eprintln!("✗ Error: Failed to answer the question: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    println!("✓ Answered from {} patterns ({:.2?})", answer.retrieved.len(), start.elapsed());
+
+    match args.output.as_str() {
+        "json" => OutputFormatter::format_answer_json(&answer),
+        _ => OutputFormatter::format_answer_text(&answer),
+    }
+}
@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 use chrono::{DateTime, Utc};
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
 
 /// Represents a single parsed log event
 #[derive(Debug, Clone)]
@@ -16,11 +17,18 @@ pub struct LogEvent {
     
     /// Original raw line (for debugging)
     pub raw: String,
+
+    /// 1-based line number in the source file
+    pub line_number: usize,
+
+    /// Structured key/value fields extracted by the parser
+    pub fields: BTreeMap<String, String>,
 }
 
 /// Standard log levels
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
 pub enum LogLevel {
+    Critical,
     Error,
     Warn,
     Info,
@@ -32,14 +40,32 @@ impl LogLevel {
     /// Parse from string (case-insensitive)
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_uppercase().as_str() {
+            "CRIT" | "CRITICAL" | "ALERT" | "EMERG" | "FATAL" | "PANIC" => Some(LogLevel::Critical),
             "ERROR" | "ERR" => Some(LogLevel::Error),
             "WARN" | "WARNING" => Some(LogLevel::Warn),
-            "INFO" => Some(LogLevel::Info),
+            "INFO" | "NOTICE" => Some(LogLevel::Info),
             "DEBUG" => Some(LogLevel::Debug),
-            "TRACE" => Some(LogLevel::Trace),
+            // Apache uses trace1 .. trace8
+            level if level.starts_with("TRACE") => Some(LogLevel::Trace),
             _ => None,
         }
     }
+
+    /// Map a syslog severity (0 = emerg .. 7 = debug)
+    pub fn from_syslog_severity(severity: u8) -> Self {
+        match severity {
+            0..=2 => LogLevel::Critical,
+            3 => LogLevel::Error,
+            4 => LogLevel::Warn,
+            5 | 6 => LogLevel::Info,
+            _ => LogLevel::Debug,
+        }
+    }
+
+    /// ERROR or more severe
+    pub fn is_error(&self) -> bool {
+        matches!(self, LogLevel::Critical | LogLevel::Error)
+    }
 }
 
 /// Represents a group of similar log events
@@ -47,6 +73,9 @@ impl LogLevel {
 pub struct LogGroup {
     /// Representative message (the pattern)
     pub pattern: String,
+
+    /// Split-field value this group is restricted to, e.g. `unit=nginx.service`
+    pub partition: Option<String>,
     
     /// All events in this group
     pub events: Vec<LogEvent>,
@@ -68,6 +97,7 @@ impl LogGroup {
     pub fn new(pattern: String) -> Self {
         Self {
             pattern,
+            partition: None,
             events: Vec::new(),
             count: 0,
             dominant_level: None,
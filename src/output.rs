@@ -1,14 +1,26 @@
+use crate::analysis::AnalysisReport;
+use crate::ask::Answer;
+use crate::cache::CacheStats;
+use crate::analysis::access::AccessReport;
+use crate::analysis::changepoint::ChangePointReport;
+use crate::analysis::correlation::{SequenceSummary, SessionReport};
+use crate::analysis::metrics::PatternMetrics;
+use crate::analysis::periodicity::Periodicity;
+use crate::analysis::precedence::Precedence;
+use crate::analysis::root_cause::RootCauseCandidate;
+use crate::analysis::similarity::{MAX_SHOWN_MEMBERS, SemanticCluster, SimilarPatterns};
 use crate::models::LogGroup;
+use crate::reasoning::Explanation;
+use crate::report::IncidentReport;
 use crate::grouper::GroupStats;
 
 pub struct OutputFormatter;
 
 impl OutputFormatter {
     /// Format output as human-readable text
-    pub fn format_text(groups: &[LogGroup], stats: &GroupStats, top_n: usize) {
-        println!("\n━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-        println!("  LOG ANALYSIS RESULTS");
-        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
+    pub fn format_text(groups: &[LogGroup], stats: &GroupStats, top_n: usize, report: &AnalysisReport) {
+        println!();
+        Self::print_header("LOG ANALYSIS RESULTS");
 
         println!("Top {} failure patterns:\n", top_n);
         
@@ -16,6 +28,9 @@ impl OutputFormatter {
             println!("┌─ Pattern #{}", i + 1);
             println!("│");
             println!("│  Message: {}", group.pattern);
+            if let Some(ref partition) = group.partition {
+                println!("│  Partition: {}", partition);
+            }
             println!("│  Occurrences: {}", group.count);
             println!("│  Level: {:?}", group.dominant_level.as_ref().unwrap_or(&crate::models::LogLevel::Info));
             
@@ -25,25 +40,90 @@ impl OutputFormatter {
                 println!("│  First seen: {}", start.format("%Y-%m-%d %H:%M:%S"));
                 println!("│  Last seen: {}", end.format("%Y-%m-%d %H:%M:%S"));
             }
+
+            let periodic = report
+                .periodicity
+                .iter()
+                .flatten()
+                .find(|p| p.pattern_id == i + 1);
+            if let Some(p) = periodic {
+                println!("│  Periodic: every {:.1}s (jitter ±{:.1}s)", p.interval_secs, p.jitter_secs);
+            }
             
             println!("└─");
             println!();
         }
 
-        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-        println!("  SUMMARY");
-        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
+        if let Some(ref periodic) = report.periodicity {
+            Self::format_periodicity_text(periodic);
+        }
+
+        if let Some(ref precedence) = report.precedence {
+            Self::format_precedence_text(precedence, top_n);
+        }
+
+        if let Some(ref candidates) = report.root_causes {
+            Self::format_root_causes_text(candidates, top_n);
+        }
+
+        if let Some(ref sessions) = report.sessions {
+            Self::format_sessions_text(sessions);
+        }
+
+        if let Some(ref change_points) = report.change_points {
+            Self::format_change_points_text(change_points);
+        }
+
+        if let Some(ref metrics) = report.metrics {
+            Self::format_metrics_text(metrics);
+        }
+
+        if let Some(ref access) = report.access {
+            Self::format_access_text(access);
+        }
+
+        if let Some(ref clusters) = report.clusters {
+            Self::format_clusters_text(clusters, top_n);
+        }
+
+        if let Some(ref similar) = report.similar {
+            Self::format_similar_text(similar);
+        }
+
+        if let Some(ref explanations) = report.explanations {
+            Self::format_explanations_text(explanations);
+        }
+
+        Self::print_header("SUMMARY");
         println!("  Total events: {}", stats.total_events);
         println!("  Unique patterns: {}", stats.unique_patterns);
         println!("  Largest cluster: {} events", stats.largest_group);
+        if let Some(ref source) = report.source {
+            let detection = &source.format;
+            if detection.detected {
+                println!("  Format: {} (detected, {:.0}% confidence)", detection.format, detection.confidence * 100.0);
+            } else {
+                println!("  Format: {}", detection.format);
+            }
+            if source.encoding != "utf-8" {
+                println!("  Encoding: {}", source.encoding);
+            }
+            if source.invalid_lines > 0 {
+                println!("  Lines with invalid bytes: {}", source.invalid_lines);
+            }
+            if source.unparsed_timestamps > 0 {
+                println!("  Events without timestamp: {}", source.unparsed_timestamps);
+            }
+        }
         println!();
     }
 
     /// Format output as JSON
-    pub fn format_json(groups: &[LogGroup], stats: &GroupStats, top_n: usize) {
+    pub fn format_json(groups: &[LogGroup], stats: &GroupStats, top_n: usize, report: &AnalysisReport) {
         use std::collections::HashMap;
+        use serde_json::Value;
 
-        let mut output = HashMap::new();
+        let mut output = serde_json::Map::new();
         
         let patterns: Vec<HashMap<&str, String>> = groups
             .iter()
@@ -53,6 +133,9 @@ impl OutputFormatter {
                 map.insert("pattern", g.pattern.clone());
                 map.insert("count", g.count.to_string());
                 map.insert("level", format!("{:?}", g.dominant_level));
+                if let Some(ref partition) = g.partition {
+                    map.insert("partition", partition.clone());
+                }
                 
                 if let Some((start, end)) = g.time_window {
                     map.insert("time_window_start", start.to_rfc3339());
@@ -63,10 +146,533 @@ impl OutputFormatter {
             })
             .collect();
 
-        output.insert("patterns", format!("{:?}", patterns));
-        output.insert("total_events", stats.total_events.to_string());
-        output.insert("unique_patterns", stats.unique_patterns.to_string());
+        output.insert("patterns".to_string(), Value::String(format!("{:?}", patterns)));
+        output.insert("total_events".to_string(), Value::String(stats.total_events.to_string()));
+        output.insert("unique_patterns".to_string(), Value::String(stats.unique_patterns.to_string()));
+
+        if let Ok(Value::Object(analyses)) = serde_json::to_value(report) {
+            output.extend(analyses);
+        }
 
         println!("{}", serde_json::to_string_pretty(&output).unwrap_or_else(|_| "{}".to_string()));
     }
-}
\ No newline at end of file
+
+    /// Format an answer to a question as human-readable text
+    pub fn format_answer_text(answer: &Answer) {
+        println!();
+        Self::print_header("ANSWER");
+
+        println!("  Q: {}\n", answer.question);
+        for line in answer.answer.lines() {
+            println!("  {}", line);
+        }
+        println!();
+
+        if !answer.citations.is_empty() {
+            Self::print_header("SOURCES");
+            for citation in &answer.citations {
+                println!("  #{} {}", citation.pattern_id, citation.pattern);
+                if !citation.line_numbers.is_empty() {
+                    let lines: Vec<String> = citation.line_numbers.iter().map(|n| n.to_string()).collect();
+                    println!("    lines {}", lines.join(", "));
+                }
+            }
+            println!();
+        }
+
+        Self::print_header("RETRIEVED CONTEXT");
+        for pattern in &answer.retrieved {
+            println!("  #{} (similarity {:.2}, {} events) {}", pattern.pattern_id, pattern.similarity, pattern.count, pattern.pattern);
+            for sample in &pattern.samples {
+                println!("    {:>6}: {}", sample.line_number, sample.raw);
+            }
+        }
+        println!();
+    }
+
+    /// Format an answer to a question as JSON
+    pub fn format_answer_json(answer: &Answer) {
+        println!("{}", serde_json::to_string_pretty(answer).unwrap_or_else(|_| "{}".to_string()));
+    }
+
+    /// Format the embedding cache statistics as human-readable text
+    pub fn format_cache_stats(stats: &CacheStats) {
+        println!();
+        Self::print_header("EMBEDDING CACHE");
+        println!("  Location: {}", stats.path.display());
+        println!("  Entries: {}", stats.entries);
+        println!("  Size: {} of {}", Self::format_bytes(stats.size_bytes), Self::format_bytes(stats.max_bytes));
+        if !stats.models.is_empty() {
+            println!("\n  Entries per model:");
+            for (model, count) in &stats.models {
+                println!("    {:<32} {}", model, count);
+            }
+        }
+        println!();
+    }
+
+    /// Byte count in KB or MB, whichever reads better
+    pub fn format_bytes(bytes: u64) -> String {
+        if bytes < 1024 * 1024 {
+            format!("{:.1} KB", bytes as f64 / 1024.0)
+        } else {
+            format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+        }
+    }
+
+    /// Format an incident report as Markdown
+    pub fn format_markdown(report: &IncidentReport) -> String {
+        let stats = &report.stats;
+        let time = |t: chrono::DateTime<chrono::Utc>| t.format("%Y-%m-%d %H:%M:%S").to_string();
+        let mut md = Vec::new();
+
+        md.push(format!("# Incident report: {}", stats.source));
+        md.push(String::new());
+        let mut overview = Vec::new();
+        if let Some((start, end)) = stats.time_range {
+            overview.push(format!("**Period:** {} – {} UTC", time(start), time(end)));
+        }
+        overview.push(format!("**Events:** {}", stats.total_events));
+        overview.push(format!("**Errors:** {}", stats.error_events));
+        overview.push(format!("**Patterns:** {}", stats.unique_patterns));
+        md.push(overview.join(" · "));
+
+        if let Some(ref narrative) = report.narrative {
+            md.push(String::new());
+            md.push("## Summary".to_string());
+            md.push(String::new());
+            md.push(narrative.summary.clone());
+
+            for (title, text) in [("Impact", &narrative.impact), ("Likely trigger", &narrative.trigger)] {
+                if !text.is_empty() {
+                    md.push(String::new());
+                    md.push(format!("## {}", title));
+                    md.push(String::new());
+                    md.push(text.clone());
+                }
+            }
+
+            if !narrative.timeline.is_empty() {
+                md.push(String::new());
+                md.push("## Timeline".to_string());
+                md.push(String::new());
+                for (i, entry) in narrative.timeline.iter().enumerate() {
+                    md.push(format!("{}. **{}** — {}", i + 1, entry.time, entry.event));
+                }
+            }
+
+            if !narrative.next_steps.is_empty() {
+                md.push(String::new());
+                md.push("## Next steps".to_string());
+                md.push(String::new());
+                for step in &narrative.next_steps {
+                    md.push(format!("- {}", step));
+                }
+            }
+        }
+
+        md.push(String::new());
+        md.push("## Top patterns".to_string());
+        md.push(String::new());
+        md.push("| # | Pattern | Count | Level | First seen | Last seen |".to_string());
+        md.push("|---|---------|------:|-------|------------|-----------|".to_string());
+        for p in &stats.top_patterns {
+            let (first, last) = p
+                .time_window
+                .map(|(first, last)| (time(first), time(last)))
+                .unwrap_or_default();
+            md.push(format!(
+                "| {} | {} | {} | {:?} | {} | {} |",
+                p.pattern_id,
+                Self::markdown_code(&p.pattern),
+                p.count,
+                p.level.as_ref().unwrap_or(&crate::models::LogLevel::Info),
+                first,
+                last
+            ));
+        }
+
+        if !stats.shifts.is_empty() {
+            md.push(String::new());
+            md.push("## Spikes and drops".to_string());
+            md.push(String::new());
+            for cp in &stats.shifts {
+                let contributors: Vec<String> = cp.contributors.iter().map(|c| format!("#{}", c.pattern_id)).collect();
+                md.push(format!(
+                    "- **{}** {}: {:.2} → {:.2} ({})",
+                    time(cp.at),
+                    cp.metric,
+                    cp.before,
+                    cp.after,
+                    contributors.join(", ")
+                ));
+            }
+        }
+
+        if !stats.new_patterns.is_empty() {
+            md.push(String::new());
+            md.push("## New patterns".to_string());
+            md.push(String::new());
+            for p in &stats.new_patterns {
+                let first = p.time_window.map(|(first, _)| time(first)).unwrap_or_default();
+                md.push(format!(
+                    "- **{}** #{} ({} events): {}",
+                    first,
+                    p.pattern_id,
+                    p.count,
+                    Self::markdown_code(&p.pattern)
+                ));
+            }
+        }
+
+        md.push(String::new());
+        md.push("---".to_string());
+        md.push(String::new());
+        let mut footer = format!(
+            "_Generated by log-reasoner 0.1.0 from a prompt of about {} tokens",
+            report.prompt_tokens
+        );
+        if report.omitted > 0 {
+            footer.push_str(&format!("; {} statistics were left out to fit the budget", report.omitted));
+        }
+        if report.narrative.is_none() {
+            footer.push_str("; no model was available, so the narrative is missing");
+        }
+        footer.push_str("._");
+        md.push(footer);
+
+        md.join("\n") + "\n"
+    }
+
+    /// Inline code that survives inside a Markdown table
+    fn markdown_code(text: &str) -> String {
+        format!("`{}`", text.replace('`', "'").replace('|', "\\|"))
+    }
+
+    fn print_header(title: &str) {
+        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        println!("  {}", title);
+        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
+    }
+
+    fn format_periodicity_text(periodic: &[Periodicity]) {
+        Self::print_header("PERIODICITY");
+
+        if periodic.is_empty() {
+            println!("  No periodic patterns detected.\n");
+            return;
+        }
+
+        for p in periodic {
+            println!(
+                "  #{} every {:.1}s (jitter ±{:.1}s, {:.0}% regular)",
+                p.pattern_id,
+                p.interval_secs,
+                p.jitter_secs,
+                p.regularity * 100.0
+            );
+            println!("    {}", p.pattern);
+
+            for beat in &p.missed_beats {
+                if beat.stopped {
+                    println!(
+                        "    ⚠ Heartbeat stopped: {} beat(s) missing from {} to the end of the log at {}",
+                        beat.missed,
+                        beat.gap_start.format("%Y-%m-%d %H:%M:%S"),
+                        beat.gap_end.format("%Y-%m-%d %H:%M:%S")
+                    );
+                } else {
+                    println!(
+                        "    ⚠ Missing heartbeat: {} beat(s) between {} and {}",
+                        beat.missed,
+                        beat.gap_start.format("%Y-%m-%d %H:%M:%S"),
+                        beat.gap_end.format("%Y-%m-%d %H:%M:%S")
+                    );
+                }
+            }
+            println!();
+        }
+    }
+
+    fn format_precedence_text(relations: &[Precedence], top_n: usize) {
+        Self::print_header("PRECEDENCE");
+
+        if relations.is_empty() {
+            println!("  No precedence relationships found between the top patterns.\n");
+            return;
+        }
+
+        for rel in relations.iter().take(top_n) {
+            println!(
+                "  #{} precedes #{}  (support: {}, confidence: {:.0}%, lift: {:.1}, mean lag: {} ms)",
+                rel.antecedent_id,
+                rel.consequent_id,
+                rel.support,
+                rel.confidence * 100.0,
+                rel.lift,
+                rel.mean_lag_ms
+            );
+            println!("    {}", rel.antecedent);
+            println!("    → {}", rel.consequent);
+            println!();
+        }
+    }
+
+    fn format_root_causes_text(candidates: &[RootCauseCandidate], top_n: usize) {
+        Self::print_header("ROOT-CAUSE CANDIDATES");
+
+        if candidates.is_empty() {
+            println!("  No patterns were active around the incident.\n");
+            return;
+        }
+
+        for (rank, candidate) in candidates.iter().take(top_n).enumerate() {
+            let c = &candidate.components;
+            println!("┌─ Candidate #{} (pattern #{}, score {:.2})", rank + 1, candidate.pattern_id, candidate.score);
+            println!("│");
+            println!("│  Message: {}", candidate.pattern);
+            println!(
+                "│  Components: onset {:.2}, rate change {:.2}, severity {:.2}, precedence {:.2}",
+                c.onset, c.rate_change, c.severity, c.precedence
+            );
+            for reason in &candidate.explanation {
+                println!("│  - {}", reason);
+            }
+            println!("└─");
+            println!();
+        }
+    }
+
+    fn format_explanations_text(explanations: &[Explanation]) {
+        Self::print_header("EXPLANATIONS");
+
+        if explanations.is_empty() {
+            println!("  No patterns could be explained.\n");
+            return;
+        }
+
+        for explanation in explanations {
+            println!("┌─ Pattern #{}", explanation.pattern_id);
+            println!("│");
+            println!("│  Message: {}", explanation.pattern);
+            println!("│  Probable cause: {}", explanation.cause);
+            if !explanation.next_steps.is_empty() {
+                println!("│  Next steps:");
+                for step in &explanation.next_steps {
+                    println!("│  - {}", step);
+                }
+            }
+            println!("└─");
+            println!();
+        }
+    }
+
+    fn format_sessions_text(report: &SessionReport) {
+        Self::print_header("SESSIONS");
+
+        println!(
+            "  {} sessions reconstructed, {} ended in ERROR\n",
+            report.total_sessions, report.error_sessions
+        );
+        if report.total_sessions == 0 {
+            return;
+        }
+
+        println!("  Most common sequences:");
+        Self::print_sequences(&report.common_sequences);
+
+        if !report.error_sequences.is_empty() {
+            println!("  Sequences ending in ERROR:");
+            Self::print_sequences(&report.error_sequences);
+        }
+
+        for session in &report.sessions {
+            println!("┌─ {}={}{}", session.key, session.id, if session.ended_in_error { " (ERROR)" } else { "" });
+            for step in &session.steps {
+                let time = step
+                    .timestamp
+                    .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_else(|| "-".to_string());
+                println!("│  L{:<6} {}  #{}", step.line_number, time, step.pattern_id);
+            }
+            println!("└─");
+            println!();
+        }
+    }
+
+    fn print_sequences(sequences: &[SequenceSummary]) {
+        for sequence in sequences {
+            let path: Vec<String> = sequence.path.iter().map(|id| format!("#{}", id)).collect();
+            println!(
+                "    {}  ({} sessions, {} ended in ERROR)",
+                path.join(" → "),
+                sequence.sessions,
+                sequence.ended_in_error
+            );
+        }
+        println!();
+    }
+
+    fn format_change_points_text(report: &ChangePointReport) {
+        Self::print_header("CHANGE POINTS");
+
+        println!("  {} buckets of {} seconds\n", report.buckets, report.bucket_secs);
+        if report.change_points.is_empty() {
+            println!("  No significant shifts detected.\n");
+            return;
+        }
+
+        for cp in &report.change_points {
+            println!(
+                "┌─ {}  {}: {:.2} → {:.2}",
+                cp.at.format("%Y-%m-%d %H:%M:%S"),
+                cp.metric,
+                cp.before,
+                cp.after
+            );
+            for c in &cp.contributors {
+                println!("│  #{} {:.2} → {:.2} per bucket: {}", c.pattern_id, c.before, c.after, c.pattern);
+            }
+            println!("└─");
+            println!();
+        }
+    }
+
+    fn format_metrics_text(patterns: &[PatternMetrics]) {
+        const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+        Self::print_header("METRICS");
+
+        if patterns.is_empty() {
+            println!("  No numeric metrics found in the top patterns.\n");
+            return;
+        }
+
+        for p in patterns {
+            println!("┌─ Pattern #{}: {}", p.pattern_id, p.pattern);
+            for m in &p.metrics {
+                let s = &m.overall;
+                println!(
+                    "│  {} ({}): n={} min={:.1} mean={:.1} p50={:.1} p90={:.1} p99={:.1} max={:.1}",
+                    m.name, m.unit, s.count, s.min, s.mean, s.p50, s.p90, s.p99, s.max
+                );
+
+                if m.buckets.len() > 1 {
+                    let peak = m.buckets.iter().map(|b| b.stats.p90).fold(0.0, f64::max);
+                    let line: String = m
+                        .buckets
+                        .iter()
+                        .map(|b| {
+                            let level = if peak > 0.0 { b.stats.p90 / peak * 7.0 } else { 0.0 };
+                            SPARKS[level.round() as usize]
+                        })
+                        .collect();
+                    println!("│    p90 per bucket: {}", line);
+                }
+            }
+            println!("└─");
+            println!();
+        }
+    }
+
+    fn format_access_text(report: &AccessReport) {
+        Self::print_header("HTTP ACCESS");
+
+        println!("  Requests: {}", report.total_requests);
+        println!("  Bytes served: {}", report.total_bytes);
+        let classes: Vec<String> = report
+            .status_classes
+            .iter()
+            .map(|(class, n)| format!("{} {}", class, n))
+            .collect();
+        println!("  Status classes: {}\n", classes.join(", "));
+
+        if !report.timeline.is_empty() {
+            println!("  Status classes over time:");
+            for bucket in &report.timeline {
+                let classes: Vec<String> = bucket
+                    .classes
+                    .iter()
+                    .map(|(class, n)| format!("{} {}", class, n))
+                    .collect();
+                println!("    {}  {}", bucket.start.format("%Y-%m-%d %H:%M:%S"), classes.join(", "));
+            }
+            println!();
+        }
+
+        if !report.top_error_paths.is_empty() {
+            println!("  Top paths by 5xx rate:");
+            for p in &report.top_error_paths {
+                println!(
+                    "    {:>5.1}%  {}/{} requests  {}",
+                    p.error_rate * 100.0,
+                    p.server_errors,
+                    p.requests,
+                    p.path
+                );
+            }
+            println!();
+        }
+
+        println!("  Top paths by bytes served:");
+        for p in &report.top_paths_by_bytes {
+            println!("    {:>12} bytes  {} requests  {}", p.bytes, p.requests, p.path);
+        }
+        println!();
+
+        println!("  Top clients:");
+        for c in &report.top_clients {
+            println!("    {:<20} {} requests, {} bytes", c.client, c.requests, c.bytes);
+        }
+        println!();
+
+        if !report.top_user_agents.is_empty() {
+            println!("  Top user agents:");
+            for (agent, n) in &report.top_user_agents {
+                println!("    {:>6}  {}", n, agent);
+            }
+            println!();
+        }
+
+        if !report.top_referers.is_empty() {
+            println!("  Top referers:");
+            for (referer, n) in &report.top_referers {
+                println!("    {:>6}  {}", n, referer);
+            }
+            println!();
+        }
+    }
+
+    fn format_clusters_text(clusters: &[SemanticCluster], top_n: usize) {
+        Self::print_header("SEMANTIC CLUSTERS");
+
+        if clusters.is_empty() {
+            println!("  No patterns are similar enough to cluster.\n");
+            return;
+        }
+
+        for (i, cluster) in clusters.iter().take(top_n).enumerate() {
+            println!("  Cluster {}: {} patterns, {} events", i + 1, cluster.members.len(), cluster.total_count);
+            for member in cluster.members.iter().take(MAX_SHOWN_MEMBERS) {
+                println!(
+                    "    #{:<5} {:>6} events  {:.2}  {}",
+                    member.pattern_id, member.count, member.similarity, member.pattern
+                );
+            }
+            if cluster.members.len() > MAX_SHOWN_MEMBERS {
+                println!("    ... and {} more", cluster.members.len() - MAX_SHOWN_MEMBERS);
+            }
+            println!();
+        }
+    }
+
+    fn format_similar_text(similar: &SimilarPatterns) {
+        Self::print_header(&format!("SIMILAR TO #{}", similar.pattern_id));
+
+        println!("  {}\n", similar.pattern);
+        for p in &similar.similar {
+            println!("  {:.2}  #{:<5} {:>6} events  {}", p.similarity, p.pattern_id, p.count, p.pattern);
+        }
+        println!();
+    }
+}
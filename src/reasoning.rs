@@ -0,0 +1,236 @@
+use crate::analysis::metrics::MetricExtractor;
+use crate::backends::ReasoningBackend;
+use crate::models::{LogGroup, LogLevel};
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+/// Number of distinct raw lines sent with each pattern
+const SAMPLE_LINES: usize = 3;
+
+/// Sample lines longer than this are truncated in the prompt
+const MAX_SAMPLE_CHARS: usize = 400;
+
+/// Rough number of characters per token, for budgeting prompts
+const CHARS_PER_TOKEN: usize = 4;
+
+const SYSTEM_PROMPT: &str = "You are an experienced site reliability engineer analyzing application logs. \
+Given a recurring log pattern and its statistics, explain its most probable cause in one or two sentences \
+and suggest up to three concrete next steps for investigating or fixing it. Only state what the evidence \
+supports. Reply with JSON of the form {\"cause\": \"...\", \"next_steps\": [\"...\"]}.";
+
+/// A model's explanation of one pattern
+#[derive(Debug, Clone, Serialize)]
+pub struct Explanation {
+    /// Rank of the pattern (1-based, as displayed)
+    pub pattern_id: usize,
+    pub pattern: String,
+    /// Probable cause of the pattern
+    pub cause: String,
+    /// Suggested next steps
+    pub next_steps: Vec<String>,
+}
+
+/// The JSON reply requested from the model
+#[derive(Deserialize)]
+struct ExplanationReply {
+    cause: String,
+    #[serde(default)]
+    next_steps: Vec<String>,
+}
+
+/// Asks a reasoning backend to explain the top patterns
+pub struct Explainer<B: ReasoningBackend> {
+    backend: B,
+    metrics: MetricExtractor,
+}
+
+impl<B: ReasoningBackend> Explainer<B> {
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend,
+            metrics: MetricExtractor::new(),
+        }
+    }
+
+    /// Explain each of the top patterns. A failed pattern is reported
+    /// and skipped so one bad reply doesn't lose the others.
+    pub fn explain(&self, groups: &[LogGroup], top_n: usize) -> Vec<Explanation> {
+        let mut explanations = Vec::new();
+
+        for (i, group) in groups.iter().take(top_n).enumerate() {
+            match self.explain_group(group, i + 1) {
+                Ok(explanation) => explanations.push(explanation),
+                Err(e) => eprintln!("⚠ Warning: Failed to explain pattern #{}: {}", i + 1, e),
+            }
+        }
+
+        explanations
+    }
+
+    /// Explain one pattern
+    pub fn explain_group(&self, group: &LogGroup, pattern_id: usize) -> Result<Explanation> {
+        let reply = self.backend.generate(SYSTEM_PROMPT, &self.build_prompt(group))?;
+        let (cause, next_steps) = Self::parse_reply(&reply)?;
+
+        Ok(Explanation {
+            pattern_id,
+            pattern: group.pattern.clone(),
+            cause,
+            next_steps,
+        })
+    }
+
+    /// Describe the pattern: counts, time window, parameter statistics and samples
+    fn build_prompt(&self, group: &LogGroup) -> String {
+        let mut lines = Vec::new();
+
+        lines.push(format!("Pattern: {}", group.pattern));
+        if let Some(ref partition) = group.partition {
+            lines.push(format!("Partition: {}", partition));
+        }
+        lines.push(format!("Occurrences: {}", group.count));
+        lines.push(format!("Level: {:?}", group.dominant_level.as_ref().unwrap_or(&LogLevel::Info)));
+
+        if let Some((start, end)) = group.time_window {
+            lines.push(format!(
+                "Time window: {} to {} ({} seconds)",
+                start.to_rfc3339(),
+                end.to_rfc3339(),
+                end.signed_duration_since(start).num_seconds()
+            ));
+        }
+
+        let metrics = self.metrics.analyze_group(group, None);
+        if !metrics.is_empty() {
+            lines.push("Parameters:".to_string());
+            for metric in &metrics {
+                let s = &metric.overall;
+                lines.push(format!(
+                    "  {} ({}): min {:.1}, p50 {:.1}, p90 {:.1}, max {:.1} over {} values",
+                    metric.name, metric.unit, s.min, s.p50, s.p90, s.max, s.count
+                ));
+            }
+        }
+
+        lines.push("Sample lines:".to_string());
+        let mut seen: Vec<&str> = Vec::new();
+        for event in &group.events {
+            if seen.len() == SAMPLE_LINES {
+                break;
+            }
+            if seen.contains(&event.raw.as_str()) {
+                continue;
+            }
+            seen.push(&event.raw);
+
+            lines.push(format!("  {}", truncate(&event.raw, MAX_SAMPLE_CHARS)));
+        }
+
+        lines.join("\n")
+    }
+
+    /// Parse the JSON reply; a model that ignored the format still
+    /// gets its plain-text answer used as the cause
+    fn parse_reply(reply: &str) -> Result<(String, Vec<String>)> {
+        let reply = reply.trim();
+        if reply.is_empty() {
+            return Err(anyhow!("The model returned an empty reply"));
+        }
+
+        match serde_json::from_str::<ExplanationReply>(reply) {
+            Ok(parsed) => Ok((parsed.cause.trim().to_string(), parsed.next_steps)),
+            Err(_) => Ok((reply.to_string(), Vec::new())),
+        }
+    }
+}
+
+/// Assembles a prompt from titled sections within a token budget. Sections
+/// should be added in priority order: once the budget is spent, the remaining
+/// items are dropped and counted instead of overflowing the context window.
+pub struct PromptBuilder {
+    /// Maximum estimated tokens
+    budget: usize,
+    used: usize,
+    lines: Vec<String>,
+    /// Items left out for lack of budget
+    omitted: usize,
+}
+
+impl PromptBuilder {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            used: 0,
+            lines: Vec::new(),
+            omitted: 0,
+        }
+    }
+
+    /// Estimate the tokens a piece of text takes up
+    pub fn estimate_tokens(text: &str) -> usize {
+        text.chars().count().div_ceil(CHARS_PER_TOKEN) + 1
+    }
+
+    /// Add a section with as many of its items as the budget allows.
+    /// Returns the number of items included.
+    pub fn section(&mut self, title: &str, items: impl IntoIterator<Item = String>) -> usize {
+        let mut items = items.into_iter().peekable();
+        let Some(first) = items.peek() else {
+            return 0;
+        };
+
+        let header = format!("{}:", title);
+        if self.used + Self::estimate_tokens(&header) + Self::estimate_tokens(first) > self.budget {
+            self.omitted += items.count();
+            return 0;
+        }
+        if !self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+        self.used += Self::estimate_tokens(&header);
+        self.lines.push(header);
+
+        let mut included = 0;
+        let mut dropped = 0;
+        for item in items {
+            let line = format!("- {}", item);
+            let cost = Self::estimate_tokens(&line);
+            if dropped > 0 || self.used + cost > self.budget {
+                dropped += 1;
+                continue;
+            }
+            self.used += cost;
+            self.lines.push(line);
+            included += 1;
+        }
+
+        // The marker may go slightly over budget so the model knows the list is partial
+        if dropped > 0 {
+            self.omitted += dropped;
+            self.lines.push(format!("- ... {} more omitted", dropped));
+        }
+        included
+    }
+
+    /// Estimated tokens used so far
+    pub fn used_tokens(&self) -> usize {
+        self.used
+    }
+
+    /// Number of items dropped to stay within the budget
+    pub fn omitted(&self) -> usize {
+        self.omitted
+    }
+
+    pub fn build(&self) -> String {
+        self.lines.join("\n")
+    }
+}
+
+/// Truncate to at most `max` characters
+pub fn truncate(s: &str, max: usize) -> String {
+    match s.char_indices().nth(max) {
+        Some((end, _)) => format!("{}…", &s[..end]),
+        None => s.to_string(),
+    }
+}
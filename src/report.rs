@@ -0,0 +1,286 @@
+use crate::analysis::TimeBuckets;
+use crate::analysis::changepoint::{ChangePoint, ChangePointDetector};
+use crate::backends::ReasoningBackend;
+use crate::models::{LogGroup, LogLevel};
+use crate::reasoning::{PromptBuilder, truncate};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+
+/// Patterns first seen after this fraction of the log's time span count as new
+const NEW_PATTERN_AFTER: f64 = 0.1;
+
+/// Caps on the number of shifts and new patterns listed
+const MAX_SHIFTS: usize = 10;
+const MAX_NEW_PATTERNS: usize = 10;
+
+/// The error timeline is merged into at most this many buckets
+const MAX_TIMELINE_BUCKETS: usize = 30;
+
+/// Patterns and sample lines longer than this are truncated in the prompt
+const MAX_ITEM_CHARS: usize = 300;
+
+const SYSTEM_PROMPT: &str = "You are an experienced site reliability engineer writing the first draft of a postmortem \
+from log statistics. Describe what happened, estimate the impact (what failed, for how long, roughly how many \
+requests or events were affected), name the most likely trigger, and reconstruct an ordered timeline of the key \
+events. Only state what the statistics support and say so when they are inconclusive. Reply with JSON of the form \
+{\"summary\": \"...\", \"impact\": \"...\", \"trigger\": \"...\", \"timeline\": [{\"time\": \"...\", \"event\": \"...\"}], \
+\"next_steps\": [\"...\"]}.";
+
+/// One pattern as listed in the report
+#[derive(Debug, Clone)]
+pub struct PatternSummary {
+    /// Rank of the pattern (1-based, as displayed)
+    pub pattern_id: usize,
+    pub pattern: String,
+    pub count: usize,
+    pub level: Option<LogLevel>,
+    pub time_window: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    /// First raw line of the pattern
+    pub sample: String,
+}
+
+impl PatternSummary {
+    fn new(pattern_id: usize, group: &LogGroup) -> Self {
+        Self {
+            pattern_id,
+            pattern: group.pattern.clone(),
+            count: group.count,
+            level: group.dominant_level.clone(),
+            time_window: group.time_window,
+            sample: group.events.first().map(|e| e.raw.clone()).unwrap_or_default(),
+        }
+    }
+
+    /// One line for the prompt: rank, level, count, time window and pattern
+    fn describe(&self) -> String {
+        let window = self
+            .time_window
+            .map(|(first, last)| format!(", {} to {}", first.format("%Y-%m-%d %H:%M:%S"), last.format("%H:%M:%S")))
+            .unwrap_or_default();
+        format!(
+            "#{} [{:?}] {} events{}: {}",
+            self.pattern_id,
+            self.level.as_ref().unwrap_or(&LogLevel::Info),
+            self.count,
+            window,
+            truncate(&self.pattern, MAX_ITEM_CHARS)
+        )
+    }
+}
+
+/// Statistics of the whole run that the incident report is built from
+#[derive(Debug, Clone)]
+pub struct IncidentStats {
+    /// Input file and the format it was parsed as
+    pub source: String,
+    pub format: String,
+    pub total_events: usize,
+    pub error_events: usize,
+    pub unique_patterns: usize,
+    pub time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    pub top_patterns: Vec<PatternSummary>,
+    /// Shifts in volume, error ratio and pattern diversity
+    pub shifts: Vec<ChangePoint>,
+    /// Patterns that only appeared after the start of the log, by first appearance
+    pub new_patterns: Vec<PatternSummary>,
+    /// Error events per bucket, for buckets with errors
+    pub error_timeline: Vec<(DateTime<Utc>, usize)>,
+    pub bucket_secs: i64,
+}
+
+impl IncidentStats {
+    /// Collect the statistics from groups sorted by count
+    pub fn collect(source: &str, format: &str, groups: &[LogGroup], top_n: usize, bucket: Option<Duration>) -> Self {
+        let top_patterns = groups
+            .iter()
+            .take(top_n)
+            .enumerate()
+            .map(|(i, g)| PatternSummary::new(i + 1, g))
+            .collect();
+
+        let buckets = TimeBuckets::covering(groups, bucket);
+        let time_range = buckets.as_ref().map(|b| {
+            let end = groups.iter().filter_map(|g| g.time_window).map(|(_, end)| end).max().unwrap_or(b.start);
+            (b.start, end)
+        });
+
+        let shifts = buckets
+            .as_ref()
+            .map(|b| ChangePointDetector::new(b.clone()).detect(groups).change_points)
+            .unwrap_or_default()
+            .into_iter()
+            .take(MAX_SHIFTS)
+            .collect();
+
+        let (error_timeline, bucket_secs) = match buckets {
+            Some(ref buckets) => Self::error_timeline(groups, buckets),
+            None => (Vec::new(), 0),
+        };
+
+        Self {
+            source: source.to_string(),
+            format: format.to_string(),
+            total_events: groups.iter().map(|g| g.count).sum(),
+            error_events: groups.iter().flat_map(|g| &g.events).filter(|e| e.level.as_ref().is_some_and(|l| l.is_error())).count(),
+            unique_patterns: groups.len(),
+            time_range,
+            top_patterns,
+            shifts,
+            new_patterns: Self::new_patterns(groups, time_range),
+            error_timeline,
+            bucket_secs,
+        }
+    }
+
+    /// Build the prompt, highest-priority statistics first so the error
+    /// timeline is what gets cut when the budget runs out
+    pub fn build_prompt(&self, budget: usize) -> PromptBuilder {
+        let time = |t: DateTime<Utc>| t.format("%Y-%m-%d %H:%M:%S").to_string();
+        let mut prompt = PromptBuilder::new(budget);
+
+        let mut overview = vec![format!("Source: {} (format: {})", self.source, self.format)];
+        if let Some((start, end)) = self.time_range {
+            overview.push(format!("Period: {} to {} UTC ({} minutes)", time(start), time(end), (end - start).num_minutes()));
+        }
+        overview.push(format!(
+            "Events: {}, errors: {}, distinct patterns: {}",
+            self.total_events, self.error_events, self.unique_patterns
+        ));
+        prompt.section("Overview", overview);
+
+        prompt.section(
+            "Top patterns by count",
+            self.top_patterns.iter().map(|p| format!("{}; e.g. {}", p.describe(), truncate(&p.sample, MAX_ITEM_CHARS))),
+        );
+
+        prompt.section(
+            "Shifts in volume, error ratio or active patterns",
+            self.shifts.iter().map(|cp| {
+                let contributors: Vec<String> = cp.contributors.iter().map(|c| format!("#{}", c.pattern_id)).collect();
+                format!(
+                    "{}: {} {:.2} -> {:.2} per bucket, driven by {}",
+                    time(cp.at),
+                    cp.metric,
+                    cp.before,
+                    cp.after,
+                    contributors.join(", ")
+                )
+            }),
+        );
+
+        prompt.section(
+            &format!("New patterns (first seen more than {:.0}% into the log)", NEW_PATTERN_AFTER * 100.0),
+            self.new_patterns.iter().map(PatternSummary::describe),
+        );
+
+        prompt.section(
+            &format!("Error events per {}-second bucket", self.bucket_secs),
+            self.error_timeline.iter().map(|(start, n)| format!("{}: {}", time(*start), n)),
+        );
+
+        prompt
+    }
+
+    fn new_patterns(groups: &[LogGroup], time_range: Option<(DateTime<Utc>, DateTime<Utc>)>) -> Vec<PatternSummary> {
+        let Some((start, end)) = time_range else {
+            return Vec::new();
+        };
+        let span_ms = (end - start).num_milliseconds() as f64;
+        if span_ms <= 0.0 {
+            return Vec::new();
+        }
+        let cutoff = start + Duration::milliseconds((span_ms * NEW_PATTERN_AFTER) as i64);
+
+        let mut new_patterns: Vec<PatternSummary> = groups
+            .iter()
+            .enumerate()
+            .filter(|(_, g)| g.time_window.is_some_and(|(first, _)| first > cutoff))
+            .map(|(i, g)| PatternSummary::new(i + 1, g))
+            .collect();
+        new_patterns.sort_by_key(|p| p.time_window.map(|(first, _)| first));
+        new_patterns.truncate(MAX_NEW_PATTERNS);
+        new_patterns
+    }
+
+    /// Count error events per bucket, merging buckets so there are at most
+    /// `MAX_TIMELINE_BUCKETS`. Returns the non-empty buckets and their width.
+    fn error_timeline(groups: &[LogGroup], buckets: &TimeBuckets) -> (Vec<(DateTime<Utc>, usize)>, i64) {
+        let factor = buckets.count.div_ceil(MAX_TIMELINE_BUCKETS).max(1);
+        let mut counts = vec![0; buckets.count.div_ceil(factor)];
+
+        for event in groups.iter().flat_map(|g| &g.events) {
+            if let Some(ts) = event.timestamp
+                && event.level.as_ref().is_some_and(|l| l.is_error())
+            {
+                counts[buckets.index(ts) / factor] += 1;
+            }
+        }
+
+        let timeline = counts
+            .into_iter()
+            .enumerate()
+            .filter(|(_, n)| *n > 0)
+            .map(|(i, n)| (buckets.bucket_start(i * factor), n))
+            .collect();
+        (timeline, buckets.width.num_seconds() * factor as i64)
+    }
+}
+
+/// One entry of the model's incident timeline
+#[derive(Debug, Clone, Deserialize)]
+pub struct TimelineEntry {
+    pub time: String,
+    pub event: String,
+}
+
+/// The incident narrative written by the model
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct IncidentNarrative {
+    pub summary: String,
+    #[serde(default)]
+    pub impact: String,
+    #[serde(default)]
+    pub trigger: String,
+    #[serde(default)]
+    pub timeline: Vec<TimelineEntry>,
+    #[serde(default)]
+    pub next_steps: Vec<String>,
+}
+
+/// Everything rendered into the Markdown report
+pub struct IncidentReport {
+    pub stats: IncidentStats,
+    /// None when no model was available
+    pub narrative: Option<IncidentNarrative>,
+    /// Estimated prompt tokens used for the statistics
+    pub prompt_tokens: usize,
+    /// Statistics left out of the prompt for lack of budget
+    pub omitted: usize,
+}
+
+/// Asks a reasoning backend for an incident narrative
+pub struct IncidentReporter<B: ReasoningBackend> {
+    backend: B,
+}
+
+impl<B: ReasoningBackend> IncidentReporter<B> {
+    pub fn new(backend: B) -> Self {
+        Self { backend }
+    }
+
+    /// Ask the model for the narrative; a model that ignored the format
+    /// still gets its plain-text answer used as the summary
+    pub fn narrate(&self, prompt: &PromptBuilder) -> Result<IncidentNarrative> {
+        let reply = self.backend.generate(SYSTEM_PROMPT, &prompt.build())?;
+        let reply = reply.trim();
+        if reply.is_empty() {
+            return Err(anyhow!("The model returned an empty reply"));
+        }
+
+        Ok(serde_json::from_str(reply).unwrap_or_else(|_| IncidentNarrative {
+            summary: reply.to_string(),
+            ..Default::default()
+        }))
+    }
+}
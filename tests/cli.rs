@@ -0,0 +1,137 @@
+//! End-to-end runs of the binary with the mock backend and fixture replay
+
+use serde_json::Value;
+use std::path::PathBuf;
+use std::process::Command;
+
+const LOG: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/service.log");
+
+/// Scratch directory for one test, removed when dropped
+struct Scratch(PathBuf);
+
+impl Scratch {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("log-reasoner-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    fn path(&self, name: &str) -> String {
+        self.0.join(name).display().to_string()
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Run the binary in the scratch directory, away from the host's config,
+/// cache and backend settings, and return its standard output
+fn run(scratch: &Scratch, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_log-reasoner"))
+        .args(args)
+        .current_dir(&scratch.0)
+        .env("XDG_CONFIG_HOME", scratch.0.join("config"))
+        .env("XDG_CACHE_HOME", scratch.0.join("cache"))
+        .env_remove("LOG_REASONER_BACKEND")
+        .env_remove("LOG_REASONER_LLM_URL")
+        .env_remove("LOG_REASONER_EMBED_MODEL")
+        .env_remove("LOG_REASONER_LLM_MODEL")
+        .env_remove("LOG_REASONER_FIXTURE")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// The JSON document that follows the progress lines
+fn json(stdout: &str) -> Value {
+    let start = stdout.find("\n{\n").expect("no JSON in the output");
+    serde_json::from_str(&stdout[start..]).unwrap()
+}
+
+#[test]
+fn analyze_clusters_and_explains() {
+    let scratch = Scratch::new("analyze");
+    let report = json(&run(&scratch, &["analyze", LOG, "--backend", "mock", "--clusters", "--explain", "-o", "json"]));
+
+    // The two wordings of the database timeout form the only cluster
+    let clusters = report["clusters"].as_array().unwrap();
+    assert_eq!(clusters.len(), 1);
+    let members: Vec<u64> = clusters[0]["members"].as_array().unwrap().iter().map(|m| m["pattern_id"].as_u64().unwrap()).collect();
+    assert_eq!(members, vec![4, 5]);
+    assert_eq!(clusters[0]["total_count"], 11);
+
+    let explanations = report["explanations"].as_array().unwrap();
+    assert_eq!(explanations.len(), 5);
+    assert_eq!(explanations[0]["pattern"], "health check ok in <VAR>ms");
+    assert_eq!(explanations[0]["cause"], "Mock explanation: no model was asked.");
+}
+
+#[test]
+fn report_is_narrated() {
+    let scratch = Scratch::new("report");
+    let markdown = run(&scratch, &["report", LOG, "--backend", "mock"]);
+
+    assert!(markdown.contains("# Incident report: "));
+    assert!(markdown.contains("## Summary"));
+    assert!(markdown.contains("`request_id=req-<VAR> database connection timeout after <VAR>ms`"));
+}
+
+#[test]
+fn ask_retrieves_the_relevant_patterns() {
+    let scratch = Scratch::new("ask");
+    let answer = json(&run(&scratch, &["ask", LOG, "Why did the database time out?", "--backend", "mock", "-o", "json"]));
+
+    assert_eq!(answer["question"], "Why did the database time out?");
+    assert!(answer["answer"].as_str().unwrap().starts_with("Mock answer"));
+    let retrieved = answer["retrieved"].as_array().unwrap();
+    assert!(!retrieved.is_empty());
+    assert!(retrieved[0]["pattern"].as_str().unwrap().contains("database connection timeout"));
+}
+
+#[test]
+fn recorded_runs_replay_without_the_backend() {
+    let scratch = Scratch::new("replay");
+    let (index, fixture) = (scratch.path("patterns.idx"), scratch.path("fixture.json"));
+    let analyze = ["analyze", LOG, "--clusters", "--explain", "-o", "json"];
+
+    // A saved index must not keep the recording from embedding the patterns
+    run(&scratch, &[&analyze[..], &["--backend", "mock", "--index", &index]].concat());
+    let recorded = run(&scratch, &[&analyze[..], &["--backend", "mock", "--index", &index, "--record", "--fixture", &fixture]].concat());
+    let replayed = run(&scratch, &[&analyze[..], &["--backend", "replay", "--fixture", &fixture]].concat());
+    let (recorded, replayed) = (json(&recorded), json(&replayed));
+    assert_eq!(recorded["clusters"], replayed["clusters"]);
+    assert_eq!(recorded["explanations"], replayed["explanations"]);
+
+    let ask = ["ask", LOG, "What failed?", "-o", "json"];
+    let recorded = run(&scratch, &[&ask[..], &["--backend", "mock", "--record", "--fixture", &fixture]].concat());
+    let replayed = run(&scratch, &[&ask[..], &["--backend", "replay", "--fixture", &fixture]].concat());
+    assert_eq!(json(&recorded), json(&replayed));
+}
+
+#[test]
+fn config_formats_are_detected() {
+    let scratch = Scratch::new("config-format");
+    std::fs::write(
+        scratch.path("log-reasoner.toml"),
+        r#"
+[[formats]]
+name = "worker"
+grok = "%{TIMESTAMP_ISO8601:ts} \\[%{WORD:thread}\\] %{LOGLEVEL:level} %{GREEDYDATA:msg}"
+"#,
+    )
+    .unwrap();
+    let log = scratch.path("worker.log");
+    std::fs::write(&log, "2024-01-05T12:00:00Z [main] ERROR queue full\n2024-01-05T12:00:01Z [w2] INFO job done\n").unwrap();
+
+    let report = json(&run(&scratch, &["analyze", &log, "--backend", "none", "-o", "json"]));
+
+    assert_eq!(report["source"]["format"]["format"], "worker");
+    assert_eq!(report["source"]["format"]["detected"], true);
+    assert_eq!(report["unique_patterns"], "2");
+}